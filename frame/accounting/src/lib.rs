@@ -2,29 +2,58 @@
 
 pub use pallet::*;
 
-// #[cfg(test)]
-// mod mock;
+pub mod migrations;
 
-// #[cfg(test)]
-// mod tests;
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
 
 #[frame_support::pallet]
 pub mod pallet {
-    use automata_traits::{AttestorAccounting, GeodeAccounting};
+    use automata_traits::{distribute_penalty, AttestorAccounting, GeodeAccounting, Penalty};
     use core::convert::TryInto;
-    use frame_support::traits::{Currency, ReservableCurrency};
+    use frame_support::traits::{
+        Currency, Imbalance, OnUnbalanced, ReservableCurrency, StorageVersion,
+    };
     use frame_support::{dispatch::DispatchResultWithPostInfo, pallet_prelude::*};
     use frame_system::pallet_prelude::*;
     use pallet_geode::GeodeOf;
+    use sp_runtime::traits::{Saturating, Zero};
     use sp_std::collections::{btree_map::BTreeMap, btree_set::BTreeSet};
     use sp_std::prelude::*;
 
     type BalanceOf<T> =
         <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+    type NegativeImbalanceOf<T> = <<T as Config>::Currency as Currency<
+        <T as frame_system::Config>::AccountId,
+    >>::NegativeImbalance;
+
+    /// What a stake is bonded for. Each kind is only released and slashed for its own purpose.
+    #[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug)]
+    pub enum StakeKind {
+        Attestor,
+        Geode,
+    }
+
+    /// Stake on its way back to its owner.
+    #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+    pub struct UnbondingChunk<Balance, BlockNumber> {
+        /// What the stake was bonded for.
+        pub kind: StakeKind,
+        /// Amount still reserved.
+        pub value: Balance,
+        /// Block from which the amount can be withdrawn.
+        pub unlock_at: BlockNumber,
+    }
+
+    pub type UnbondingChunkOf<T> =
+        UnbondingChunk<BalanceOf<T>, <T as frame_system::Config>::BlockNumber>;
 
     /// Configure the pallet by specifying the parameters and types on which it depends.
     #[pallet::config]
-    pub trait Config: frame_system::Config {
+    pub trait Config: frame_system::Config + pallet_geode::Config {
         /// Because this pallet emits events, it depends on the runtime's definition of an event.
         type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
         /// The currency in which fees are paid and contract balances are held.
@@ -38,12 +67,24 @@ pub mod pallet {
         type AttestorTotalReward: Get<BalanceOf<Self>>;
         type GeodeTotalReward: Get<BalanceOf<Self>>;
 
+        /// Stake a geode loses for a misconduct.
+        #[pallet::constant]
         type GeodeTerminatePenalty: Get<BalanceOf<Self>>;
+        /// Part of a geode penalty shared by the reporting attestors.
+        #[pallet::constant]
         type GeodeMisconductForAttestor: Get<BalanceOf<Self>>;
+        /// Part of a geode penalty paid to the owner of the affected service.
+        #[pallet::constant]
         type GeodeMisconductForServiceUser: Get<BalanceOf<Self>>;
+        /// Handler for the rest of a geode penalty, e.g. the treasury.
+        type Slash: OnUnbalanced<NegativeImbalanceOf<Self>>;
 
         type SlotLength: Get<Self::BlockNumber>;
 
+        /// Blocks released stake stays reserved and slashable before it can be withdrawn.
+        #[pallet::constant]
+        type UnbondingPeriod: Get<Self::BlockNumber>;
+
         type AttestorBasicRewardRatio: Get<u8>;
         type CommissionRateForService: Get<u8>;
         type CommissionRateForOnDemand: Get<u8>;
@@ -52,8 +93,11 @@ pub mod pallet {
         type GeodeRewardEachSlot: Get<BalanceOf<Self>>;
     }
 
+    pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(_);
 
     #[pallet::storage]
@@ -70,8 +114,31 @@ pub mod pallet {
     pub type Attestors<T: Config> =
         StorageMap<_, Blake2_128Concat, T::AccountId, T::AccountId, ValueQuery>;
 
+    /// Stake of accounts which isn't unbonding, by what it is bonded for.
+    #[pallet::storage]
+    #[pallet::getter(fn bonded)]
+    pub type Bonded<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        StakeKind,
+        BalanceOf<T>,
+        ValueQuery,
+    >;
+
+    /// Unbonding stake of accounts, oldest first.
+    #[pallet::storage]
+    #[pallet::getter(fn unbonding)]
+    pub type Unbonding<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, Vec<UnbondingChunkOf<T>>, ValueQuery>;
+
     #[pallet::event]
-    #[pallet::metadata(T::BlockNumber = "BlockNumber")]
+    #[pallet::metadata(
+        T::AccountId = "AccountId",
+        T::BlockNumber = "BlockNumber",
+        BalanceOf<T> = "Balance"
+    )]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
         /// Attestor rewarded. \[attestor_id\]
@@ -82,15 +149,27 @@ pub mod pallet {
         AttestorRewardRanOut(),
         /// No reward left for geode
         GeodeRewardRanOut(),
+        /// Stake started unbonding. \[who, amount, unlock_at\]
+        Unbonded(T::AccountId, BalanceOf<T>, T::BlockNumber),
+        /// Unbonded stake withdrawn. \[who, amount\]
+        Withdrawn(T::AccountId, BalanceOf<T>),
+        /// Stake slashed. \[who, amount\]
+        Slashed(T::AccountId, BalanceOf<T>),
     }
 
     #[pallet::error]
     pub enum Error<T> {
         InvalidAttestor,
+        /// No unbonded stake can be withdrawn yet.
+        NothingToWithdraw,
     }
 
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_runtime_upgrade() -> Weight {
+            crate::migrations::v1::migrate::<T>()
+        }
+
         fn on_initialize(block_number: T::BlockNumber) -> Weight {
             if let Some(value) = Self::total_attestor_distributed_reward() {
                 if value >= T::AttestorTotalReward::get() {
@@ -129,6 +208,35 @@ pub mod pallet {
 
             Ok(().into())
         }
+
+        /// Withdraw the stake which finished unbonding.
+        #[pallet::weight(0)]
+        pub fn withdraw_unbonded(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            let now = <frame_system::Pallet<T>>::block_number();
+
+            let mut amount = BalanceOf::<T>::zero();
+            let mut chunks = <Unbonding<T>>::get(&who);
+            chunks.retain(|chunk| {
+                if chunk.unlock_at <= now {
+                    amount += chunk.value;
+                    false
+                } else {
+                    true
+                }
+            });
+            ensure!(!amount.is_zero(), Error::<T>::NothingToWithdraw);
+
+            <T as Config>::Currency::unreserve(&who, amount);
+            if chunks.is_empty() {
+                <Unbonding<T>>::remove(&who);
+            } else {
+                <Unbonding<T>>::insert(&who, chunks);
+            }
+
+            Self::deposit_event(Event::Withdrawn(who, amount));
+            Ok(().into())
+        }
     }
 
     impl<T: Config> Pallet<T> {
@@ -137,6 +245,10 @@ pub mod pallet {
             /// Get all attestors and its verified geodes number
             let attestors = T::GetAttestors::get();
 
+            if attestors.is_empty() {
+                return;
+            }
+
             let attestors_length = attestors.len();
             let geodes_length: usize = attestors.iter().map(|(_, geodes)| geodes).sum();
             let reward_each_slot = T::AttestorRewardEachSlot::get();
@@ -148,8 +260,11 @@ pub mod pallet {
             let basic_reward_per_attestor =
                 basic_reward / BalanceOf::<T>::from(attestors_length as u32);
             let commission_reward = reward_each_slot - basic_reward;
-            let commission_reward_per_geode =
-                commission_reward / BalanceOf::<T>::from(geodes_length as u32);
+            let commission_reward_per_geode = if geodes_length == 0 {
+                BalanceOf::<T>::zero()
+            } else {
+                commission_reward / BalanceOf::<T>::from(geodes_length as u32)
+            };
 
             /// Reward each attestor
             attestors.iter().map(|(accountId, geodes)| {
@@ -166,6 +281,9 @@ pub mod pallet {
 
         pub fn reward_geodes() {
             let geodes = T::GetGeodes::get();
+            if geodes.is_empty() {
+                return;
+            }
 
             let geodes_len = geodes.len();
             let reward_each_slot = T::GeodeRewardEachSlot::get();
@@ -182,29 +300,147 @@ pub mod pallet {
         }
     }
 
+    impl<T: Config> Pallet<T> {
+        /// Reserve `value` of the account's funds as stake bonded for `kind`.
+        pub fn bond(who: &T::AccountId, kind: StakeKind, value: BalanceOf<T>) -> DispatchResult {
+            <T as Config>::Currency::reserve(who, value)?;
+            <Bonded<T>>::mutate(who, kind, |bonded| *bonded = bonded.saturating_add(value));
+            Ok(())
+        }
+
+        /// Queue stake bonded for `kind` for withdrawal after the unbonding period, up to what
+        /// is bonded.
+        pub fn unbond(who: &T::AccountId, kind: StakeKind, value: BalanceOf<T>) {
+            let bonded = <Bonded<T>>::get(who, kind);
+            let value = value.min(bonded);
+            if value.is_zero() {
+                return;
+            }
+            Self::set_bonded(who, kind, bonded - value);
+            let unlock_at = <frame_system::Pallet<T>>::block_number() + T::UnbondingPeriod::get();
+            <Unbonding<T>>::append(
+                who,
+                UnbondingChunk {
+                    kind,
+                    value,
+                    unlock_at,
+                },
+            );
+            Self::deposit_event(Event::Unbonded(who.clone(), value, unlock_at));
+        }
+
+        /// Slash stake bonded for `kind`, bonded stake goes first and unbonding stake latest
+        /// first. Return the slashed amount.
+        pub fn slash_stake(
+            who: &T::AccountId,
+            kind: StakeKind,
+            value: BalanceOf<T>,
+        ) -> BalanceOf<T> {
+            Self::take_stake(who, kind, value).peek()
+        }
+
+        /// Slash stake like `slash_stake`, return the slashed funds.
+        fn take_stake(
+            who: &T::AccountId,
+            kind: StakeKind,
+            value: BalanceOf<T>,
+        ) -> NegativeImbalanceOf<T> {
+            let bonded = <Bonded<T>>::get(who, kind);
+            let mut chunks = <Unbonding<T>>::get(who);
+            let unbonding = chunks
+                .iter()
+                .filter(|chunk| chunk.kind == kind)
+                .fold(BalanceOf::<T>::zero(), |acc, chunk| acc + chunk.value);
+
+            // reserves of other pallets aren't stake
+            let (imbalance, _) = <T as Config>::Currency::slash_reserved(
+                who,
+                value.min(bonded.saturating_add(unbonding)),
+            );
+            let slashed = imbalance.peek();
+            let from_bonded = slashed.min(bonded);
+            Self::set_bonded(who, kind, bonded - from_bonded);
+
+            let mut from_unbonding = slashed - from_bonded;
+            for chunk in chunks.iter_mut().rev().filter(|chunk| chunk.kind == kind) {
+                if from_unbonding.is_zero() {
+                    break;
+                }
+                let taken = chunk.value.min(from_unbonding);
+                chunk.value -= taken;
+                from_unbonding -= taken;
+            }
+            chunks.retain(|chunk| !chunk.value.is_zero());
+            if chunks.is_empty() {
+                <Unbonding<T>>::remove(who);
+            } else {
+                <Unbonding<T>>::insert(who, chunks);
+            }
+
+            Self::deposit_event(Event::Slashed(who.clone(), slashed));
+            imbalance
+        }
+
+        pub(crate) fn set_bonded(who: &T::AccountId, kind: StakeKind, value: BalanceOf<T>) {
+            if value.is_zero() {
+                <Bonded<T>>::remove(who, kind);
+            } else {
+                <Bonded<T>>::insert(who, kind, value);
+            }
+        }
+    }
+
     impl<T: Config> AttestorAccounting for Pallet<T> {
         type AccountId = <T as frame_system::Config>::AccountId;
         fn attestor_staking(who: T::AccountId) -> DispatchResultWithPostInfo {
-            <T as Config>::Currency::reserve(&who, T::AttestorStakingAmount::get())?;
+            Self::bond(&who, StakeKind::Attestor, T::AttestorStakingAmount::get())?;
             Ok(().into())
         }
 
         fn attestor_unreserve(who: T::AccountId) -> DispatchResultWithPostInfo {
-            <T as Config>::Currency::unreserve(&who, T::AttestorStakingAmount::get());
+            Self::unbond(&who, StakeKind::Attestor, T::AttestorStakingAmount::get());
             Ok(().into())
         }
     }
 
     impl<T: Config> GeodeAccounting for Pallet<T> {
         type AccountId = <T as frame_system::Config>::AccountId;
-        fn geode_staking(who: T::AccountId) -> DispatchResultWithPostInfo {
-            <T as Config>::Currency::reserve(&who, T::GeodeStakingAmount::get())?;
+        type Balance = BalanceOf<T>;
+        fn geode_min_stake() -> BalanceOf<T> {
+            T::GeodeStakingAmount::get()
+        }
+
+        fn geode_penalty() -> BalanceOf<T> {
+            T::GeodeTerminatePenalty::get()
+        }
+
+        fn geode_staking(who: T::AccountId, value: BalanceOf<T>) -> DispatchResultWithPostInfo {
+            Self::bond(&who, StakeKind::Geode, value)?;
             Ok(().into())
         }
 
-        fn geode_unreserve(who: T::AccountId) -> DispatchResultWithPostInfo {
-            <T as Config>::Currency::unreserve(&who, T::GeodeStakingAmount::get());
+        fn geode_unreserve(who: T::AccountId, value: BalanceOf<T>) -> DispatchResultWithPostInfo {
+            Self::unbond(&who, StakeKind::Geode, value);
             Ok(().into())
         }
+
+        fn geode_slash(who: T::AccountId, value: BalanceOf<T>) -> BalanceOf<T> {
+            Self::slash_stake(&who, StakeKind::Geode, value)
+        }
+
+        fn geode_penalize(
+            who: T::AccountId,
+            value: BalanceOf<T>,
+            reporters: Vec<T::AccountId>,
+            service_owner: Option<T::AccountId>,
+        ) -> Penalty<T::AccountId, BalanceOf<T>> {
+            distribute_penalty::<_, <T as Config>::Currency, T::Slash>(
+                Self::take_stake(&who, StakeKind::Geode, value),
+                reporters,
+                T::GeodeMisconductForAttestor::get(),
+                service_owner,
+                T::GeodeMisconductForServiceUser::get(),
+            )
+        }
     }
 }
@@ -0,0 +1,44 @@
+use crate::{Bonded, Config, Pallet, StakeKind};
+use frame_support::{
+    traits::{Get, StorageVersion},
+    weights::Weight,
+};
+use sp_runtime::{traits::Saturating, SaturatedConversion};
+
+/// Credit the stake reserved before bonded stake was tracked, so that it can be released and
+/// slashed. Attestors bonded `AttestorStakingAmount`, providers the stake of each geode.
+pub mod v1 {
+    use super::*;
+
+    pub fn migrate<T: Config>() -> Weight {
+        if StorageVersion::get::<Pallet<T>>() >= 1 {
+            return T::DbWeight::get().reads(1);
+        }
+
+        let mut reads = 1u64;
+        let mut writes = 1u64;
+
+        for (attestor, _) in pallet_attestor::Attestors::<T>::iter() {
+            <Bonded<T>>::mutate(&attestor, StakeKind::Attestor, |bonded| {
+                *bonded = bonded.saturating_add(T::AttestorStakingAmount::get())
+            });
+            reads += 2;
+            writes += 1;
+        }
+
+        for (id, geode) in pallet_geode::Geodes::<T>::iter() {
+            // geodes registered before stakes were recorded bonded the minimum
+            let stake = pallet_geode::GeodeStakes::<T>::try_get(&id)
+                .map(|stake| stake.saturated_into::<u128>().saturated_into())
+                .unwrap_or_else(|_| T::GeodeStakingAmount::get());
+            <Bonded<T>>::mutate(&geode.provider, StakeKind::Geode, |bonded| {
+                *bonded = bonded.saturating_add(stake)
+            });
+            reads += 3;
+            writes += 1;
+        }
+
+        StorageVersion::new(1).put::<Pallet<T>>();
+        T::DbWeight::get().reads_writes(reads, writes)
+    }
+}
@@ -1,12 +1,13 @@
 use crate as accounting;
 use frame_support::parameter_types;
 use frame_system as system;
+use pallet_attestor::attestation::{AttestationError, AttestationVerifier, EnclaveReport};
 use sp_core::H256;
 use sp_runtime::{
     testing::Header,
     traits::{BlakeTwo256, IdentityLookup},
 };
-use primitives::*;
+use sp_std::collections::btree_map::BTreeMap;
 
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
 type Block = frame_system::mocking::MockBlock<Test>;
@@ -23,6 +24,7 @@ frame_support::construct_runtime!(
         System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
         Balances: pallet_balances::{Pallet, Call, Storage, Event<T>},
         AttestorModule: pallet_attestor::{Pallet, Call, Storage, Event<T>},
+        GeodeModule: pallet_geode::{Pallet, Call, Storage, Event<T>},
         AccountingModule: accounting::{Pallet, Call, Storage, Event<T>},
     }
 );
@@ -33,7 +35,7 @@ parameter_types! {
 }
 
 impl system::Config for Test {
-    type BaseCallFilter = ();
+    type BaseCallFilter = frame_support::traits::Everything;
     type BlockWeights = ();
     type BlockLength = ();
     type DbWeight = ();
@@ -55,6 +57,7 @@ impl system::Config for Test {
     type OnKilledAccount = ();
     type SystemWeightInfo = ();
     type SS58Prefix = SS58Prefix;
+    type OnSetCode = ();
 }
 
 parameter_types! {
@@ -71,49 +74,110 @@ impl pallet_balances::Config for Test {
     type ExistentialDeposit = ExistentialDeposit;
     type AccountStore = System;
     type WeightInfo = ();
+    type MaxReserves = ();
+    type ReserveIdentifier = [u8; 8];
+}
+
+impl<C> frame_system::offchain::SendTransactionTypes<C> for Test
+where
+    Call: From<C>,
+{
+    type Extrinsic = UncheckedExtrinsic;
+    type OverarchingCall = Call;
+}
+
+/// Attestors are registered through storage in these tests.
+impl AttestationVerifier for Test {
+    fn verify(_report: &[u8], _root: &[u8]) -> Result<EnclaveReport, AttestationError> {
+        Err(AttestationError::Malformed)
+    }
+}
+
+parameter_types! {
+    pub const NotifyFreshnessBlockNumber: u32 = 5;
+    pub const NotifyInterval: u32 = 2;
+    pub const ReputationDecayPeriod: u32 = 10;
+    pub const ReputationDecay: sp_runtime::Percent = sp_runtime::Percent::from_percent(50);
+    pub const MaxGeodesPerAttestor: u32 = 3;
+}
+
+impl pallet_attestor::Config for Test {
+    type Event = Event;
+    type Currency = Balances;
+    type Call = Call;
+    type AttestorAccounting = AccountingModule;
+    type AttestationVerifier = Test;
+    type NotifyFreshnessBlockNumber = NotifyFreshnessBlockNumber;
+    type NotifyInterval = NotifyInterval;
+    type MaxGeodesPerAttestor = MaxGeodesPerAttestor;
+    type ReputationDecayPeriod = ReputationDecayPeriod;
+    type ReputationDecay = ReputationDecay;
+    type AttestorAdminOrigin = frame_system::EnsureRoot<Self::AccountId>;
+    type OnAttestorRegistered = ();
+}
+
+parameter_types! {
+    pub const DispatchConfirmationTimeout: u32 = 12;
+    pub const PutOnlineTimeout: u32 = 40;
+    pub const AttestationExpiryBlockNumber: u32 = 30;
+    pub const MaxBatchSize: u32 = 4;
+    pub const MaxHistoryLength: u32 = 3;
+    pub const DnsApprovalRatio: sp_runtime::Percent = sp_runtime::Percent::from_percent(50);
+    pub const DnsChallengeExpiry: u32 = 10;
+}
+
+impl pallet_geode::Config for Test {
+    type Event = Event;
+    type Balance = u128;
+    type GeodeAccounting = AccountingModule;
+    type DispatchConfirmationTimeout = DispatchConfirmationTimeout;
+    type PutOnlineTimeout = PutOnlineTimeout;
+    type AttestationExpiryBlockNumber = AttestationExpiryBlockNumber;
+    type MaxBatchSize = MaxBatchSize;
+    type MaxHistoryLength = MaxHistoryLength;
+    type DnsApprovalRatio = DnsApprovalRatio;
+    type DnsChallengeExpiry = DnsChallengeExpiry;
+    type CapabilityAdminOrigin = frame_system::EnsureRoot<Self::AccountId>;
+    type OnGeodeStateChange = ();
 }
 
 // All parameters for accounting
-pub const ATTESTOR_STAKING_AMOUNT: Balance = 1 * CENTS;
-pub const GEODE_STAKING_AMOUNT: Balance = 1 * CENTS;
-pub const ATTESTOR_TOTAL_REWARD: Balance = 1 * CENTS;
-pub const GEODE_TOTAL_REWARD: Balance = 1 * CENTS;
-pub const ATTESTOR_REWARD_TIMESPAN: u128 = YEARS as u128 * 5 as u128;
-pub const GEODE_REWARD_TIMESPAN: u128 = YEARS as u128 * 5 as u128;
-pub const ATTESTOR_BASIC_REWARD_RATIO: u8 = 1_u8;
-pub const GEODE_TERMINATE_PENALTY: Balance = 1 * CENTS;
-pub const GEODE_MISCONDUCT_FOR_ATTESTOR: Balance = 1 * CENTS;
-pub const GEODE_MISCONDUCT_FOR_SERVICE_USER: Balance = 1 * CENTS;
-pub const COMMISSION_RATE_FOR_SERVICE: u8 = 1_u8;
-pub const COMMISSION_RATE_FOR_ON_DEMAND: u8 = 1_u8;
-pub const SLOT_LENGTH: BlockNumber = 1;
+pub const ATTESTOR_STAKING_AMOUNT: u128 = 100;
+pub const GEODE_STAKING_AMOUNT: u128 = 100;
+pub const GEODE_TERMINATE_PENALTY: u128 = 100;
+pub const GEODE_MISCONDUCT_FOR_ATTESTOR: u128 = 30;
+pub const GEODE_MISCONDUCT_FOR_SERVICE_USER: u128 = 30;
 
 parameter_types! {
-    pub const AttestorStakingAmount: Balance = ATTESTOR_STAKING_AMOUNT;
-    pub const GeodeStakingAmount: Balance = GEODE_STAKING_AMOUNT;
-    pub const AttestorTotalReward: Balance = ATTESTOR_TOTAL_REWARD;
-    pub const GeodeTotalReward: Balance = GEODE_TOTAL_REWARD;
+    pub RewardedAttestors: BTreeMap<u64, usize> = BTreeMap::new();
+    pub RewardedGeodes: Vec<u64> = Vec::new();
+
+    pub const AttestorStakingAmount: u128 = ATTESTOR_STAKING_AMOUNT;
+    pub const GeodeStakingAmount: u128 = GEODE_STAKING_AMOUNT;
+    pub const AttestorTotalReward: u128 = 1_000;
+    pub const GeodeTotalReward: u128 = 1_000;
 
-    pub const GeodeTerminatePenalty: Balance = GEODE_TERMINATE_PENALTY;
-    pub const GeodeMisconductForAttestor: Balance = GEODE_MISCONDUCT_FOR_ATTESTOR;
-    pub const GeodeMisconductForServiceUser: Balance = GEODE_MISCONDUCT_FOR_SERVICE_USER;  
+    pub const GeodeTerminatePenalty: u128 = GEODE_TERMINATE_PENALTY;
+    pub const GeodeMisconductForAttestor: u128 = GEODE_MISCONDUCT_FOR_ATTESTOR;
+    pub const GeodeMisconductForServiceUser: u128 = GEODE_MISCONDUCT_FOR_SERVICE_USER;
 
-    pub const SlotLength: BlockNumber = SLOT_LENGTH;
+    pub const SlotLength: u64 = 1;
+    pub const UnbondingPeriod: u64 = 10;
 
-    pub const AttestorBasicRewardRatio: u8 = ATTESTOR_BASIC_REWARD_RATIO;
-    pub const CommissionRateForService: u8 = COMMISSION_RATE_FOR_SERVICE;
-    pub const CommissionRateForOnDemand: u8 = COMMISSION_RATE_FOR_ON_DEMAND;
+    pub const AttestorBasicRewardRatio: u8 = 1;
+    pub const CommissionRateForService: u8 = 1;
+    pub const CommissionRateForOnDemand: u8 = 1;
 
-    pub const AttestorRewardEachSlot: Balance = ATTESTOR_TOTAL_REWARD * (SLOT_LENGTH as u128) / ATTESTOR_REWARD_TIMESPAN;
-    pub const GeodeRewardEachSlot: Balance = GEODE_TOTAL_REWARD * (SLOT_LENGTH as u128) / GEODE_REWARD_TIMESPAN;
+    pub const AttestorRewardEachSlot: u128 = 10;
+    pub const GeodeRewardEachSlot: u128 = 10;
 }
 
 impl accounting::Config for Test {
     type Event = Event;
     type Currency = Balances;
 
-    type GetAttestors = AttestorModule;
-    type GetGeodes = GeodeModule;
+    type GetAttestors = RewardedAttestors;
+    type GetGeodes = RewardedGeodes;
 
     type AttestorStakingAmount = AttestorStakingAmount;
     type GeodeStakingAmount = GeodeStakingAmount;
@@ -122,9 +186,11 @@ impl accounting::Config for Test {
 
     type GeodeTerminatePenalty = GeodeTerminatePenalty;
     type GeodeMisconductForAttestor = GeodeMisconductForAttestor;
-    type GeodeMisconductForServiceUser = GeodeMisconductForServiceUser;  
+    type GeodeMisconductForServiceUser = GeodeMisconductForServiceUser;
+    type Slash = ();
 
     type SlotLength = SlotLength;
+    type UnbondingPeriod = UnbondingPeriod;
 
     type AttestorBasicRewardRatio = AttestorBasicRewardRatio;
     type CommissionRateForService = CommissionRateForService;
@@ -134,12 +200,6 @@ impl accounting::Config for Test {
     type GeodeRewardEachSlot = GeodeRewardEachSlot;
 }
 
-impl pallet_attestor::Config for Test {
-    type Event = Event;
-    type Currency = Balances;
-    type AttestorAccounting = AccountingModule;
-}
-
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
     let mut t = system::GenesisConfig::default()
@@ -165,4 +225,4 @@ pub fn events() -> Vec<Event> {
     System::reset_events();
 
     evt
-}
\ No newline at end of file
+}
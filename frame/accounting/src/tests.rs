@@ -1,9 +1,170 @@
-use crate::{mock::*, Attestor};
-use frame_support::assert_ok;
+use crate::{mock::*, Error, StakeKind, UnbondingChunk};
+use automata_traits::{AttestorAccounting, GeodeAccounting};
+use frame_support::{
+    assert_noop, assert_ok,
+    traits::{Currency, ReservableCurrency},
+};
 
 #[test]
-fn it_works_for_attestor_register() {
+fn it_works_for_withdraw_unbonded() {
     new_test_ext().execute_with(|| {
-       
+        assert_ok!(AccountingModule::attestor_staking(1));
+        assert_ok!(AccountingModule::geode_staking(1, 200));
+        assert_eq!(AccountingModule::bonded(1, StakeKind::Attestor), 100);
+        assert_eq!(AccountingModule::bonded(1, StakeKind::Geode), 200);
+        assert_eq!(Balances::reserved_balance(1), 300);
+
+        assert_ok!(AccountingModule::geode_unreserve(1, 200));
+        System::set_block_number(5);
+        assert_ok!(AccountingModule::attestor_unreserve(1));
+        assert_eq!(AccountingModule::bonded(1, StakeKind::Attestor), 0);
+        assert_eq!(AccountingModule::bonded(1, StakeKind::Geode), 0);
+        assert_eq!(
+            AccountingModule::unbonding(1),
+            vec![
+                UnbondingChunk {
+                    kind: StakeKind::Geode,
+                    value: 200,
+                    unlock_at: 11
+                },
+                UnbondingChunk {
+                    kind: StakeKind::Attestor,
+                    value: 100,
+                    unlock_at: 15
+                },
+            ]
+        );
+
+        // unbonding stake stays reserved for the whole period
+        System::set_block_number(10);
+        assert_noop!(
+            AccountingModule::withdraw_unbonded(Origin::signed(1)),
+            Error::<Test>::NothingToWithdraw
+        );
+        assert_eq!(Balances::reserved_balance(1), 300);
+
+        System::set_block_number(11);
+        assert_ok!(AccountingModule::withdraw_unbonded(Origin::signed(1)));
+        System::assert_last_event(crate::Event::Withdrawn(1, 200).into());
+        assert_eq!(Balances::reserved_balance(1), 100);
+        assert_noop!(
+            AccountingModule::withdraw_unbonded(Origin::signed(1)),
+            Error::<Test>::NothingToWithdraw
+        );
+
+        System::set_block_number(15);
+        assert_ok!(AccountingModule::withdraw_unbonded(Origin::signed(1)));
+        assert_eq!(Balances::reserved_balance(1), 0);
+        assert!(!crate::Unbonding::<Test>::contains_key(1));
+    });
+}
+
+#[test]
+fn it_works_for_slashing_unbonding_stake() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(AccountingModule::geode_staking(1, 300));
+        // reserved by some other pallet, not stake
+        assert_ok!(Balances::reserve(&1, 50));
+        assert_ok!(AccountingModule::geode_unreserve(1, 100));
+        System::set_block_number(2);
+        assert_ok!(AccountingModule::geode_unreserve(1, 100));
+
+        // bonded stake goes first, then the latest unbonding stake
+        assert_eq!(AccountingModule::geode_slash(1, 250), 250);
+        assert_eq!(AccountingModule::bonded(1, StakeKind::Geode), 0);
+        assert_eq!(
+            AccountingModule::unbonding(1),
+            vec![UnbondingChunk {
+                kind: StakeKind::Geode,
+                value: 50,
+                unlock_at: 11
+            }]
+        );
+        assert_eq!(Balances::reserved_balance(1), 100);
+
+        // the other reserves can't be slashed
+        assert_eq!(AccountingModule::geode_slash(1, 1_000), 50);
+        assert!(!crate::Unbonding::<Test>::contains_key(1));
+        assert_eq!(Balances::reserved_balance(1), 50);
+        assert_eq!(Balances::total_balance(&1), INIT_BALANCE - 300);
+
+        System::set_block_number(11);
+        assert_noop!(
+            AccountingModule::withdraw_unbonded(Origin::signed(1)),
+            Error::<Test>::NothingToWithdraw
+        );
+    });
+}
+
+#[test]
+fn it_keeps_attestor_and_geode_stake_apart() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(AccountingModule::attestor_staking(1));
+        assert_ok!(AccountingModule::geode_staking(1, 200));
+
+        // only what is bonded for geodes is released
+        assert_ok!(AccountingModule::geode_unreserve(1, 500));
+        assert_eq!(AccountingModule::bonded(1, StakeKind::Geode), 0);
+        assert_eq!(AccountingModule::bonded(1, StakeKind::Attestor), 100);
+        System::assert_last_event(crate::Event::Unbonded(1, 200, 11).into());
+
+        // geode penalties can't reach the attestor stake
+        assert_eq!(AccountingModule::geode_slash(1, 1_000), 200);
+        assert_eq!(AccountingModule::bonded(1, StakeKind::Attestor), 100);
+        assert!(!crate::Unbonding::<Test>::contains_key(1));
+        assert_eq!(Balances::reserved_balance(1), 100);
+    });
+}
+
+#[test]
+fn migrate_bonded_stake_to_v1() {
+    use frame_support::traits::StorageVersion;
+
+    new_test_ext().execute_with(|| {
+        let provider = 2;
+        let geode = 3;
+        let legacy_geode = 4;
+
+        // stake reserved before bonded stake was tracked
+        pallet_attestor::Attestors::<Test>::insert(1, pallet_attestor::Attestor::default());
+        assert_ok!(Balances::reserve(&1, ATTESTOR_STAKING_AMOUNT));
+        for id in [geode, legacy_geode].iter() {
+            pallet_geode::Geodes::<Test>::insert(
+                id,
+                pallet_geode::Geode {
+                    id: *id,
+                    provider,
+                    ..Default::default()
+                },
+            );
+        }
+        pallet_geode::GeodeStakes::<Test>::insert(geode, 300);
+        assert_ok!(Balances::reserve(&provider, 300 + GEODE_STAKING_AMOUNT));
+        StorageVersion::new(0).put::<AccountingModule>();
+
+        crate::migrations::v1::migrate::<Test>();
+        assert_eq!(
+            StorageVersion::get::<AccountingModule>(),
+            StorageVersion::new(1)
+        );
+        assert_eq!(
+            AccountingModule::bonded(1, StakeKind::Attestor),
+            ATTESTOR_STAKING_AMOUNT
+        );
+        assert_eq!(
+            AccountingModule::bonded(provider, StakeKind::Geode),
+            300 + GEODE_STAKING_AMOUNT
+        );
+
+        // the migrated stake can be slashed
+        assert_eq!(GeodeModule::slash_stake(&geode, 250), 250);
+        assert_eq!(AccountingModule::bonded(provider, StakeKind::Geode), 150);
+        assert_eq!(Balances::reserved_balance(provider), 150);
+
+        // and released
+        assert_ok!(AccountingModule::attestor_unreserve(1));
+        System::set_block_number(11);
+        assert_ok!(AccountingModule::withdraw_unbonded(Origin::signed(1)));
+        assert_eq!(Balances::reserved_balance(1), 0);
     });
-}
\ No newline at end of file
+}
@@ -0,0 +1,18 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+pub use pallet_attestor::{AttestationType, AttestorFilter};
+use sp_runtime::traits::MaybeDisplay;
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+    pub trait AttestorRuntimeApi<AccountId> where
+        AccountId: Codec + MaybeDisplay
+    {
+        fn attestor_list(filter: AttestorFilter) -> Vec<(Vec<u8>, Vec<u8>, u32)>;
+        fn attestors_of_geode(geode: AccountId) -> Vec<(Vec<u8>, Vec<u8>)>;
+        fn suggest_attestors(geode: AccountId, count: u32) -> Vec<(AccountId, u32)>;
+        fn attestor_reputation(attestor: AccountId) -> u32;
+        fn attestor_reputations() -> Vec<(AccountId, u32)>;
+    }
+}
@@ -0,0 +1,140 @@
+use codec::Codec;
+use jsonrpc_core::{Error, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use pallet_attestor::AttestorFilter;
+pub use pallet_attestor_rpc_runtime_api::AttestorRuntimeApi;
+use sp_api::BlockId;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::Block as BlockT;
+use sp_runtime::traits::MaybeDisplay;
+use std::sync::Arc;
+
+const RUNTIME_ERROR: i64 = 1;
+
+#[rpc]
+pub trait AttestorApi<BlockHash, AccountId> {
+    /// Return url, pubkey and number of attested geodes of the attestors matching the filter.
+    #[rpc(name = "attestor_list")]
+    fn attestor_list(
+        &self,
+        filter: Option<AttestorFilter>,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>, u32)>>;
+
+    /// Return url and pubkey of the attestors of a geode.
+    #[rpc(name = "attestor_attestorsOfGeode")]
+    fn attestors_of_geode(
+        &self,
+        geode: AccountId,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>>;
+
+    /// Return the least loaded attestors able to attest a geode.
+    #[rpc(name = "attestor_suggestAttestors")]
+    fn suggest_attestors(
+        &self,
+        geode: AccountId,
+        count: u32,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<(AccountId, u32)>>;
+
+    /// Return the reputation score of an attestor.
+    #[rpc(name = "attestor_reputation")]
+    fn attestor_reputation(&self, attestor: AccountId, at: Option<BlockHash>) -> Result<u32>;
+
+    /// Return the reputation scores of every attestor.
+    #[rpc(name = "attestor_reputations")]
+    fn attestor_reputations(&self, at: Option<BlockHash>) -> Result<Vec<(AccountId, u32)>>;
+}
+
+pub struct AttestorClient<C, P> {
+    client: Arc<C>,
+    _marker: std::marker::PhantomData<P>,
+}
+
+impl<C, P> AttestorClient<C, P> {
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            _marker: Default::default(),
+        }
+    }
+}
+
+fn runtime_error(message: &str, e: impl std::fmt::Debug) -> Error {
+    Error {
+        code: ErrorCode::ServerError(RUNTIME_ERROR),
+        message: message.into(),
+        data: Some(format!("{:?}", e).into()),
+    }
+}
+
+impl<C, Block, AccountId> AttestorApi<<Block as BlockT>::Hash, AccountId>
+    for AttestorClient<C, Block>
+where
+    Block: BlockT,
+    C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+    C::Api: AttestorRuntimeApi<Block, AccountId>,
+    AccountId: Codec + MaybeDisplay,
+{
+    fn attestor_list(
+        &self,
+        filter: Option<AttestorFilter>,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>, u32)>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.attestor_list(&at, filter.unwrap_or_default())
+            .map_err(|e| runtime_error("Runtime unable to get attestor list.", e))
+    }
+
+    fn attestors_of_geode(
+        &self,
+        geode: AccountId,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.attestors_of_geode(&at, geode)
+            .map_err(|e| runtime_error("Runtime unable to get attestors of geode.", e))
+    }
+
+    fn suggest_attestors(
+        &self,
+        geode: AccountId,
+        count: u32,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<(AccountId, u32)>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.suggest_attestors(&at, geode, count)
+            .map_err(|e| runtime_error("Runtime unable to suggest attestors.", e))
+    }
+
+    fn attestor_reputation(
+        &self,
+        attestor: AccountId,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<u32> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.attestor_reputation(&at, attestor)
+            .map_err(|e| runtime_error("Runtime unable to get attestor reputation.", e))
+    }
+
+    fn attestor_reputations(
+        &self,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<(AccountId, u32)>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.attestor_reputations(&at)
+            .map_err(|e| runtime_error("Runtime unable to get attestor reputations.", e))
+    }
+}
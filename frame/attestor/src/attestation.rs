@@ -0,0 +1,326 @@
+use p256::ecdsa::{
+    signature::{Signature as _, Verifier},
+    Signature, VerifyingKey,
+};
+use sha2::{Digest, Sha256};
+use sp_std::prelude::*;
+
+/// Quote version produced by the DCAP quoting enclave.
+pub const QUOTE_VERSION_3: u16 = 3;
+/// Attestation key type of ECDSA-256-with-P-256 curve.
+pub const ATT_KEY_TYPE_ECDSA_P256: u16 = 2;
+/// Certification data type of a PEM encoded PCK certificate chain.
+pub const CERT_TYPE_PCK_CHAIN: u16 = 5;
+
+const HEADER_LEN: usize = 48;
+const REPORT_BODY_LEN: usize = 384;
+const SIGNED_LEN: usize = HEADER_LEN + REPORT_BODY_LEN;
+const MR_ENCLAVE_OFFSET: usize = 64;
+const MR_SIGNER_OFFSET: usize = 128;
+const REPORT_DATA_OFFSET: usize = 320;
+
+const PEM_BEGIN: &[u8] = b"-----BEGIN CERTIFICATE-----";
+const PEM_END: &[u8] = b"-----END CERTIFICATE-----";
+
+/// Reasons why an attestation report is rejected.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AttestationError {
+    /// The report can't be parsed.
+    Malformed,
+    /// Quote version or attestation key type isn't supported.
+    Unsupported,
+    /// The PCK certificate chain doesn't end at the trusted root.
+    UntrustedChain,
+    /// One of the signatures in the report is invalid.
+    BadSignature,
+}
+
+/// The verified content of an enclave report.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct EnclaveReport {
+    /// Measurement of the enclave code (MRENCLAVE).
+    pub mr_enclave: [u8; 32],
+    /// Measurement of the enclave signer (MRSIGNER).
+    pub mr_signer: [u8; 32],
+    /// User data the enclave bound into the report.
+    pub report_data: [u8; 64],
+}
+
+impl EnclaveReport {
+    /// Whether the report data commits to `pubkey`, i.e. its first 32 bytes are
+    /// `sha256(pubkey)`.
+    pub fn binds(&self, pubkey: &[u8]) -> bool {
+        self.report_data[..32] == sha256(pubkey)[..]
+    }
+}
+
+/// Verify an SGX DCAP (v3) quote. `root` is the SEC1 encoded P-256 public key of the
+/// root CA that the embedded PCK certificate chain has to end at.
+///
+/// Certificate validity periods, revocation lists and TCB levels are not checked.
+pub fn verify_dcap_quote(quote: &[u8], root: &[u8]) -> Result<EnclaveReport, AttestationError> {
+    if quote.len() < SIGNED_LEN + 4 {
+        return Err(AttestationError::Malformed);
+    }
+    if read_u16(quote, 0)? != QUOTE_VERSION_3 || read_u16(quote, 2)? != ATT_KEY_TYPE_ECDSA_P256 {
+        return Err(AttestationError::Unsupported);
+    }
+
+    let sig_len = read_u32(quote, SIGNED_LEN)? as usize;
+    let sig_data = slice(quote, SIGNED_LEN + 4, sig_len)?;
+
+    let isv_signature = slice(sig_data, 0, 64)?;
+    let attest_key = slice(sig_data, 64, 64)?;
+    let qe_report = slice(sig_data, 128, REPORT_BODY_LEN)?;
+    let qe_signature = slice(sig_data, 512, 64)?;
+    let qe_auth_len = read_u16(sig_data, 576)? as usize;
+    let qe_auth = slice(sig_data, 578, qe_auth_len)?;
+    let cert_offset = 578 + qe_auth_len;
+    if read_u16(sig_data, cert_offset)? != CERT_TYPE_PCK_CHAIN {
+        return Err(AttestationError::Unsupported);
+    }
+    let cert_len = read_u32(sig_data, cert_offset + 2)? as usize;
+    let cert_data = slice(sig_data, cert_offset + 6, cert_len)?;
+
+    // the PCK chain must be leaf -> intermediate(s) -> root
+    let chain = parse_pem_chain(cert_data)?;
+    if chain.len() < 2 {
+        return Err(AttestationError::UntrustedChain);
+    }
+    let certs = chain
+        .iter()
+        .map(|der| Certificate::parse(der))
+        .collect::<Result<Vec<_>, _>>()?;
+    let root_cert = &certs[certs.len() - 1];
+    if root_cert.public_key != root {
+        return Err(AttestationError::UntrustedChain);
+    }
+    for (i, cert) in certs.iter().enumerate() {
+        let issuer = certs.get(i + 1).unwrap_or(root_cert);
+        verify_signature(issuer.public_key, cert.tbs, &cert.signature)?;
+    }
+
+    // QE report is signed by the PCK key and commits to the attestation key
+    verify_signature(certs[0].public_key, qe_report, &to_array(qe_signature))?;
+    let mut binding = Vec::with_capacity(attest_key.len() + qe_auth.len());
+    binding.extend_from_slice(attest_key);
+    binding.extend_from_slice(qe_auth);
+    let qe_report_data = &qe_report[REPORT_DATA_OFFSET..];
+    if qe_report_data[..32] != sha256(&binding)[..] || qe_report_data[32..].iter().any(|b| *b != 0)
+    {
+        return Err(AttestationError::BadSignature);
+    }
+
+    // the enclave report is signed by the attestation key
+    let mut sec1_key = Vec::with_capacity(65);
+    sec1_key.push(0x04);
+    sec1_key.extend_from_slice(attest_key);
+    verify_signature(&sec1_key, &quote[..SIGNED_LEN], &to_array(isv_signature))?;
+
+    let body = &quote[HEADER_LEN..SIGNED_LEN];
+    let mut report = EnclaveReport {
+        mr_enclave: [0u8; 32],
+        mr_signer: [0u8; 32],
+        report_data: [0u8; 64],
+    };
+    report
+        .mr_enclave
+        .copy_from_slice(&body[MR_ENCLAVE_OFFSET..MR_ENCLAVE_OFFSET + 32]);
+    report
+        .mr_signer
+        .copy_from_slice(&body[MR_SIGNER_OFFSET..MR_SIGNER_OFFSET + 32]);
+    report
+        .report_data
+        .copy_from_slice(&body[REPORT_DATA_OFFSET..REPORT_DATA_OFFSET + 64]);
+    Ok(report)
+}
+
+/// The parts of an X.509 certificate needed to check the chain.
+struct Certificate<'a> {
+    /// DER encoded `tbsCertificate`, the signed part.
+    tbs: &'a [u8],
+    /// SEC1 encoded subject public key.
+    public_key: &'a [u8],
+    /// Issuer's signature over `tbs` as `r || s`.
+    signature: [u8; 64],
+}
+
+impl<'a> Certificate<'a> {
+    fn parse(der: &'a [u8]) -> Result<Self, AttestationError> {
+        let (_, cert, _) = der_expect(der, 0x30)?;
+        let (_, tbs_body, rest) = der_expect(cert, 0x30)?;
+        let tbs = &cert[..cert.len() - rest.len()];
+        let (_, _algorithm, rest) = der_expect(rest, 0x30)?;
+        let (_, signature, _) = der_expect(rest, 0x03)?;
+        let signature = parse_der_signature(bit_string(signature)?)?;
+
+        // skip version, serial, signature, issuer, validity and subject
+        let mut fields = tbs_body;
+        let (tag, _, rest) = der_read(fields)?;
+        if tag == 0xa0 {
+            fields = rest;
+        }
+        for _ in 0..5 {
+            let (_, _, rest) = der_read(fields)?;
+            fields = rest;
+        }
+        let (_, spki, _) = der_expect(fields, 0x30)?;
+        let (_, _algorithm, rest) = der_expect(spki, 0x30)?;
+        let (_, key, _) = der_expect(rest, 0x03)?;
+
+        Ok(Certificate {
+            tbs,
+            public_key: bit_string(key)?,
+            signature,
+        })
+    }
+}
+
+fn verify_signature(
+    public_key: &[u8],
+    message: &[u8],
+    signature: &[u8; 64],
+) -> Result<(), AttestationError> {
+    let key =
+        VerifyingKey::from_sec1_bytes(public_key).map_err(|_| AttestationError::BadSignature)?;
+    let signature =
+        Signature::from_bytes(&signature[..]).map_err(|_| AttestationError::BadSignature)?;
+    key.verify(message, &signature)
+        .map_err(|_| AttestationError::BadSignature)
+}
+
+/// Verify an ECDSA P-256 `signature` (`r || s`) of `message` by an attestor pubkey, which is
+/// either SEC1 encoded or the raw `x || y` coordinates.
+pub fn verify_p256_signature(pubkey: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    if signature.len() != 64 {
+        return false;
+    }
+    let mut sec1_key = Vec::with_capacity(65);
+    if pubkey.len() == 64 {
+        sec1_key.push(0x04);
+    }
+    sec1_key.extend_from_slice(pubkey);
+    verify_signature(&sec1_key, message, &to_array(signature)).is_ok()
+}
+
+/// Split a PEM bundle into DER encoded certificates.
+fn parse_pem_chain(data: &[u8]) -> Result<Vec<Vec<u8>>, AttestationError> {
+    let mut certs = Vec::new();
+    let mut rest = data;
+    while let Some(begin) = find(rest, PEM_BEGIN) {
+        let body = &rest[begin + PEM_BEGIN.len()..];
+        let end = find(body, PEM_END).ok_or(AttestationError::Malformed)?;
+        let encoded = body[..end]
+            .iter()
+            .filter(|b| !b.is_ascii_whitespace())
+            .cloned()
+            .collect::<Vec<u8>>();
+        certs.push(base64::decode(&encoded).map_err(|_| AttestationError::Malformed)?);
+        rest = &body[end + PEM_END.len()..];
+    }
+    Ok(certs)
+}
+
+/// Convert a DER `Ecdsa-Sig-Value` into `r || s`.
+fn parse_der_signature(der: &[u8]) -> Result<[u8; 64], AttestationError> {
+    let (_, seq, _) = der_expect(der, 0x30)?;
+    let (_, r, rest) = der_expect(seq, 0x02)?;
+    let (_, s, _) = der_expect(rest, 0x02)?;
+    let mut signature = [0u8; 64];
+    for (scalar, out) in [r, s].iter().zip(signature.chunks_mut(32)) {
+        let scalar = match scalar.iter().position(|b| *b != 0) {
+            Some(pos) => &scalar[pos..],
+            None => &[][..],
+        };
+        if scalar.len() > 32 {
+            return Err(AttestationError::Malformed);
+        }
+        out[32 - scalar.len()..].copy_from_slice(scalar);
+    }
+    Ok(signature)
+}
+
+/// Read one DER element, returning its tag, content and the remaining input.
+fn der_read(data: &[u8]) -> Result<(u8, &[u8], &[u8]), AttestationError> {
+    let tag = *data.get(0).ok_or(AttestationError::Malformed)?;
+    let first = *data.get(1).ok_or(AttestationError::Malformed)? as usize;
+    let (len, header) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let bytes = first & 0x7f;
+        if bytes == 0 || bytes > 4 {
+            return Err(AttestationError::Malformed);
+        }
+        let len = slice(data, 2, bytes)?
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        (len, 2 + bytes)
+    };
+    let content = slice(data, header, len)?;
+    Ok((tag, content, &data[header + len..]))
+}
+
+fn der_expect(data: &[u8], tag: u8) -> Result<(u8, &[u8], &[u8]), AttestationError> {
+    let element = der_read(data)?;
+    if element.0 != tag {
+        return Err(AttestationError::Malformed);
+    }
+    Ok(element)
+}
+
+/// Content of a DER bit string without unused bits.
+fn bit_string(content: &[u8]) -> Result<&[u8], AttestationError> {
+    match content.split_first() {
+        Some((0, bits)) => Ok(bits),
+        _ => Err(AttestationError::Malformed),
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn slice(data: &[u8], offset: usize, len: usize) -> Result<&[u8], AttestationError> {
+    let end = offset.checked_add(len).ok_or(AttestationError::Malformed)?;
+    data.get(offset..end).ok_or(AttestationError::Malformed)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, AttestationError> {
+    let bytes = slice(data, offset, 2)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, AttestationError> {
+    let bytes = slice(data, offset, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn to_array(data: &[u8]) -> [u8; 64] {
+    let mut array = [0u8; 64];
+    array.copy_from_slice(data);
+    array
+}
+
+/// SHA-256 digest of `data`.
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&Sha256::digest(data)[..]);
+    hash
+}
+
+/// Verifies the attestation report an attestor submits to prove it runs in an enclave.
+pub trait AttestationVerifier {
+    /// Verify `report` against the trusted `root` key and return the enclave report in it.
+    fn verify(report: &[u8], root: &[u8]) -> Result<EnclaveReport, AttestationError>;
+}
+
+/// Verifier accepting SGX DCAP quotes.
+pub struct DcapVerifier;
+
+impl AttestationVerifier for DcapVerifier {
+    fn verify(report: &[u8], root: &[u8]) -> Result<EnclaveReport, AttestationError> {
+        verify_dcap_quote(report, root)
+    }
+}
@@ -3,6 +3,10 @@
 
 pub use pallet::*;
 
+pub mod attestation;
+pub mod migrations;
+pub mod reputation;
+
 #[cfg(test)]
 mod mock;
 
@@ -14,8 +18,10 @@ mod benchmarking;
 
 #[frame_support::pallet]
 pub mod pallet {
+    use crate::attestation::{verify_p256_signature, AttestationVerifier};
+    use crate::reputation::{Reputation, ReputationChange};
     use automata_traits::AttestorAccounting;
-    use frame_support::traits::{Currency, ReservableCurrency};
+    use frame_support::traits::{Currency, ReservableCurrency, StorageVersion};
     use frame_support::{
         dispatch::DispatchResultWithPostInfo, pallet_prelude::*, unsigned::ValidateUnsigned,
     };
@@ -24,15 +30,89 @@ pub mod pallet {
         pallet_prelude::*,
     };
     use primitives::BlockNumber;
+    use sp_core::crypto::KeyTypeId;
     #[cfg(feature = "full_crypto")]
     use sp_core::crypto::Pair;
     #[cfg(feature = "full_crypto")]
     use sp_core::sr25519::Pair as Sr25519Pair;
     use sp_core::sr25519::{Public, Signature};
-    use sp_runtime::{RuntimeDebug, SaturatedConversion};
+    use sp_runtime::offchain::storage::{MutateStorageError, StorageValueRef};
+    use sp_runtime::{Percent, RuntimeDebug, SaturatedConversion};
     use sp_std::collections::{btree_map::BTreeMap, btree_set::BTreeSet};
     use sp_std::prelude::*;
 
+    #[cfg(feature = "std")]
+    use serde::{Deserialize, Serialize};
+
+    /// Type of TEE attestation an attestor can verify.
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug)]
+    pub enum AttestationType {
+        /// Intel SGX with EPID attestation.
+        SgxEpid,
+        /// Intel SGX with DCAP attestation.
+        SgxDcap,
+        /// AMD SEV-SNP.
+        AmdSevSnp,
+    }
+
+    /// Attestor metadata geodes choose attestors by.
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, Default)]
+    pub struct AttestorMetadata {
+        /// ISO 3166-1 alpha-2 region code, empty if not disclosed.
+        pub region: Vec<u8>,
+        /// Attestor software version.
+        pub version: Vec<u8>,
+        /// Attestation types the attestor supports.
+        pub attestation_types: Vec<AttestationType>,
+        /// Endpoints in addition to the attestor's url.
+        pub endpoints: Vec<Vec<u8>>,
+    }
+
+    impl AttestorMetadata {
+        /// Whether the region is a two-letter code and all fields are within bounds.
+        pub fn is_valid(&self) -> bool {
+            (self.region.is_empty()
+                || (self.region.len() == 2 && self.region.iter().all(u8::is_ascii_uppercase)))
+                && self.version.len() <= MAX_METADATA_FIELD_LEN
+                && self.attestation_types.len() <= MAX_ATTESTATION_TYPES
+                && self.endpoints.len() <= MAX_ENDPOINTS
+                && self
+                    .endpoints
+                    .iter()
+                    .all(|endpoint| endpoint.len() <= MAX_METADATA_FIELD_LEN)
+        }
+    }
+
+    /// Filter of `attestor_list`, unset fields match any attestor.
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, Default)]
+    pub struct AttestorFilter {
+        /// Region code the attestor has to be in.
+        pub region: Option<Vec<u8>>,
+        /// Software version the attestor has to run.
+        pub version: Option<Vec<u8>>,
+        /// Attestation type the attestor has to support.
+        pub attestation_type: Option<AttestationType>,
+    }
+
+    impl AttestorFilter {
+        /// Whether an attestor with the metadata passes the filter.
+        pub fn matches(&self, metadata: &AttestorMetadata) -> bool {
+            self.region
+                .as_ref()
+                .map_or(true, |region| *region == metadata.region)
+                && self
+                    .version
+                    .as_ref()
+                    .map_or(true, |version| *version == metadata.version)
+                && self
+                    .attestation_type
+                    .map_or(true, |t| metadata.attestation_types.contains(&t))
+        }
+    }
+
     /// Attestor struct
     #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, Default)]
     pub struct Attestor<AccountId: Ord> {
@@ -42,6 +122,8 @@ pub mod pallet {
         pub pubkey: Vec<u8>,
         /// Geode being attested by this attestor
         pub geodes: BTreeSet<AccountId>,
+        /// Region, version, attestation types and endpoints of the attestor.
+        pub metadata: AttestorMetadata,
     }
 
     type BalanceOf<T> =
@@ -49,7 +131,17 @@ pub mod pallet {
     pub type AttestorOf<T> = Attestor<<T as frame_system::Config>::AccountId>;
 
     pub const UNSIGNED_TXS_PRIORITY: u64 = 100;
+    /// Key type of the attestor keys the offchain worker signs heartbeats with.
+    pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"atst");
+    /// Prefix of the offchain local storage keys of the last submitted heartbeats.
+    pub const NOTIFY_LOCK_PREFIX: &[u8] = b"automata/attestor/notify/";
     pub const DEFAULT_ATT_STAKE_MIN: primitives::Balance = 1000;
+    pub const MAX_METADATA_FIELD_LEN: usize = 256;
+    pub const MAX_ATTESTATION_TYPES: usize = 8;
+    pub const MAX_ENDPOINTS: usize = 8;
+
+    /// The current storage version.
+    pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
 
     /// Configure the pallet by specifying the parameters and types on which it depends.
     #[pallet::config]
@@ -60,10 +152,49 @@ pub mod pallet {
         type Currency: ReservableCurrency<Self::AccountId>;
         type Call: From<Call<Self>>;
         type AttestorAccounting: AttestorAccounting<AccountId = Self::AccountId>;
+        /// Verifier of the attestation reports submitted by attestors.
+        type AttestationVerifier: AttestationVerifier;
+
+        /// How many blocks old the block referenced by a heartbeat can be.
+        #[pallet::constant]
+        type NotifyFreshnessBlockNumber: Get<BlockNumber>;
+
+        /// Blocks between heartbeats submitted by the offchain worker, has to be shorter than
+        /// the attestor notify timeout of the liveness pallet.
+        #[pallet::constant]
+        type NotifyInterval: Get<BlockNumber>;
+
+        /// Maximum number of geodes a single attestor can attest.
+        #[pallet::constant]
+        type MaxGeodesPerAttestor: Get<u32>;
+
+        /// Blocks after which reputation scores decay.
+        #[pallet::constant]
+        type ReputationDecayPeriod: Get<BlockNumber>;
+
+        /// Part of the distance to the neutral score lost every decay period.
+        #[pallet::constant]
+        type ReputationDecay: Get<Percent>;
+
+        /// Origin which manages the attestor allowlist and registration mode.
+        type AttestorAdminOrigin: EnsureOrigin<Self::Origin>;
+
+        /// Handler for newly registered attestors.
+        type OnAttestorRegistered: OnAttestorRegistered<Self::AccountId>;
+    }
+
+    /// Handler for newly registered attestors, e.g. to watch their heartbeats.
+    pub trait OnAttestorRegistered<AccountId> {
+        fn on_attestor_registered(attestor: &AccountId, when: BlockNumber);
+    }
+
+    impl<AccountId> OnAttestorRegistered<AccountId> for () {
+        fn on_attestor_registered(_: &AccountId, _: BlockNumber) {}
     }
 
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(_);
 
     // The pallet's runtime storage items.
@@ -82,6 +213,12 @@ pub mod pallet {
     pub type AttestorLastNotify<T: Config> =
         StorageMap<_, Blake2_128Concat, T::AccountId, BlockNumber, ValueQuery>;
 
+    /// Block number referenced by the last accepted heartbeat of an attestor.
+    #[pallet::storage]
+    #[pallet::getter(fn attestor_last_nonce)]
+    pub type AttestorLastNonce<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, u64, OptionQuery>;
+
     #[pallet::type_value]
     pub fn DefaultAttestorNum<T: Config>() -> u32 {
         0
@@ -91,6 +228,48 @@ pub mod pallet {
     #[pallet::getter(fn attestor_num)]
     pub type AttestorNum<T: Config> = StorageValue<_, u32, ValueQuery, DefaultAttestorNum<T>>;
 
+    /// SEC1 encoded public key of the root CA attestation reports have to chain up to.
+    #[pallet::storage]
+    #[pallet::getter(fn attestation_root)]
+    pub type AttestationRoot<T: Config> = StorageValue<_, Vec<u8>, ValueQuery>;
+
+    /// Enclave measurements (MRENCLAVE) allowed to run an attestor, value is the block
+    /// number when it was allowed.
+    #[pallet::storage]
+    #[pallet::getter(fn allowed_measurements)]
+    pub type AllowedMeasurements<T: Config> =
+        StorageMap<_, Blake2_128Concat, [u8; 32], BlockNumber, ValueQuery>;
+
+    /// Measurement and block number of the latest verified attestation of an attestor.
+    #[pallet::storage]
+    #[pallet::getter(fn attestor_enclaves)]
+    pub type AttestorEnclaves<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, ([u8; 32], BlockNumber), ValueQuery>;
+
+    /// Whether only allowlisted accounts can register as attestors.
+    #[pallet::storage]
+    #[pallet::getter(fn permissioned)]
+    pub type Permissioned<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+    /// Accounts approved to register as attestors in permissioned mode, with the block they
+    /// got approved at.
+    #[pallet::storage]
+    #[pallet::getter(fn attestor_allowlist)]
+    pub type AttestorAllowlist<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, BlockNumber, OptionQuery>;
+
+    /// Pubkeys an attestor has used and the block number from which each was active, oldest
+    /// first. Kept after the attestor exits so past attestations can still be checked.
+    #[pallet::storage]
+    #[pallet::getter(fn attestor_key_history)]
+    pub type AttestorKeyHistory<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, Vec<(Vec<u8>, BlockNumber)>, ValueQuery>;
+
+    /// Reputation of attestors, kept after they exit.
+    #[pallet::storage]
+    pub type AttestorReputation<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, Reputation, ValueQuery>;
+
     // Pallets use events to inform users when important changes are made.
     // https://substrate.dev/docs/en/knowledgebase/runtime/events
     #[pallet::event]
@@ -103,6 +282,22 @@ pub mod pallet {
         AttestorRemove(T::AccountId),
         /// Attestor's url updated. \[attestor_id\]
         AttestorUpdate(T::AccountId),
+        /// Attestor rotated its pubkey. \[attestor_id\]
+        AttestorKeyRotated(T::AccountId),
+        /// Attestor submitted a new valid attestation report. \[attestor_id\]
+        AttestorReattested(T::AccountId),
+        /// Root key of attestation reports updated.
+        AttestationRootUpdated,
+        /// Enclave measurement added to the allowlist. \[mr_enclave\]
+        MeasurementAllowed([u8; 32]),
+        /// Enclave measurement removed from the allowlist. \[mr_enclave\]
+        MeasurementRevoked([u8; 32]),
+        /// Account added to the attestor allowlist. \[account\]
+        AttestorApproved(T::AccountId),
+        /// Account removed from the attestor allowlist. \[account\]
+        AttestorApprovalRevoked(T::AccountId),
+        /// Registration switched between open and permissioned. \[permissioned\]
+        PermissionedSet(bool),
         /// Event documentation should end with an array that provides descriptive names for event
         /// parameters. [something, who]
         SomethingStored(u32, T::AccountId),
@@ -117,6 +312,35 @@ pub mod pallet {
         AlreadyRegistered,
         /// Invalid notification input.
         InvalidNotification,
+        /// Notification references a block outside the freshness window or not newer than
+        /// the last accepted one.
+        StaleNotification,
+        /// Notification references a future block.
+        FutureNotification,
+        /// No root key of attestation reports has been set.
+        NoAttestationRoot,
+        /// The attestation report failed verification.
+        InvalidAttestationReport,
+        /// The enclave measurement isn't on the allowlist.
+        UntrustedMeasurement,
+        /// The attestation report doesn't bind the attestor's pubkey.
+        PubkeyNotBound,
+        /// The new pubkey isn't signed by the current one.
+        InvalidKeySignature,
+        /// The new pubkey is the same as the current one.
+        SameKey,
+        /// The enclave measurement is already on the allowlist.
+        AlreadyAllowed,
+        /// The enclave measurement isn't on the allowlist.
+        NotAllowed,
+        /// Attestor already attests the maximum number of geodes.
+        AttestorFull,
+        /// Malformed region code or metadata fields out of bounds.
+        InvalidMetadata,
+        /// Registration is permissioned and the account isn't on the allowlist.
+        NotApproved,
+        /// The account is already on the attestor allowlist.
+        AlreadyApproved,
     }
 
     #[pallet::validate_unsigned]
@@ -126,35 +350,34 @@ pub mod pallet {
         fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
             match call {
                 Call::attestor_notify_chain(message, signature_raw_bytes) => {
-                    // validate inputs
-                    if message.len() != 40 {
-                        return InvalidTransaction::Call.into();
-                    }
+                    let (_acc, nonce) =
+                        match Self::verify_notification(message, signature_raw_bytes) {
+                            Ok(v) => v,
+                            Err(Error::<T>::StaleNotification) => {
+                                return InvalidTransaction::Stale.into();
+                            }
+                            Err(Error::<T>::FutureNotification) => {
+                                return InvalidTransaction::Future.into();
+                            }
+                            Err(_) => {
+                                return InvalidTransaction::Call.into();
+                            }
+                        };
 
                     let mut attestor = [0u8; 32];
                     attestor.copy_from_slice(&message[0..32]);
 
-                    let pubkey = Public::from_raw(attestor.clone());
-                    let signature = Signature::from_raw(signature_raw_bytes.clone());
-
-                    #[cfg(feature = "full_crypto")]
-                    if !Sr25519Pair::verify(&signature, message, &pubkey) {
-                        return InvalidTransaction::Call.into();
-                    }
-
-                    let acc = T::AccountId::decode(&mut &attestor[..]).unwrap_or_default();
-                    if !<Attestors<T>>::contains_key(acc) {
-                        return InvalidTransaction::Call.into();
-                    }
+                    // valid until the referenced block leaves the freshness window
+                    let block_number =
+                        <frame_system::Pallet<T>>::block_number().saturated_into::<BlockNumber>();
+                    let longevity = (nonce + T::NotifyFreshnessBlockNumber::get() as u64)
+                        .saturating_sub(block_number as u64)
+                        + 1;
 
                     ValidTransaction::with_tag_prefix("Automata/attestor/notify")
                         .priority(UNSIGNED_TXS_PRIORITY)
-                        .and_provides((
-                            attestor,
-                            <frame_system::Pallet<T>>::block_number()
-                                .saturated_into::<BlockNumber>(),
-                        ))
-                        .longevity(3)
+                        .and_provides((attestor, nonce))
+                        .longevity(longevity)
                         .propagate(true)
                         .build()
                 }
@@ -164,47 +387,76 @@ pub mod pallet {
     }
 
     #[pallet::hooks]
-    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_runtime_upgrade() -> Weight {
+            crate::migrations::v1::migrate::<T>()
+        }
+
+        /// Submit heartbeats for the registered attestors whose sr25519 key is in the local
+        /// keystore.
+        fn offchain_worker(block_number: T::BlockNumber) {
+            let now = block_number.saturated_into::<BlockNumber>();
+            for public in sp_io::crypto::sr25519_public_keys(KEY_TYPE) {
+                Self::offchain_notify_chain(&public, now);
+            }
+        }
+    }
 
     // Dispatchable functions allows users to interact with the pallet and invoke state changes.
     // These functions materialize as "extrinsics", which are often compared to transactions.
     // Dispatchable functions must be annotated with a weight and must return a DispatchResult.
     #[pallet::call]
     impl<T: Config> Pallet<T> {
-        /// Register as an attestor.
+        /// Register as an attestor. `attestation` is the enclave's attestation report, its
+        /// report data has to bind `pubkey`.
         #[pallet::weight(0)]
         pub fn attestor_register(
             origin: OriginFor<T>,
             url: Vec<u8>,
             pubkey: Vec<u8>,
+            attestation: Vec<u8>,
         ) -> DispatchResultWithPostInfo {
             let who = ensure_signed(origin)?;
             ensure!(
                 !<Attestors<T>>::contains_key(&who),
                 Error::<T>::AlreadyRegistered
             );
+            ensure!(
+                !<Permissioned<T>>::get() || <AttestorAllowlist<T>>::contains_key(&who),
+                Error::<T>::NotApproved
+            );
+            let mr_enclave = Self::verify_attestation(&pubkey, &attestation)?;
             T::AttestorAccounting::attestor_staking(who.clone().into())?;
 
+            let block_number =
+                <frame_system::Pallet<T>>::block_number().saturated_into::<BlockNumber>();
+            <AttestorKeyHistory<T>>::append(&who, (pubkey.clone(), block_number));
+
             let attestor = AttestorOf::<T> {
                 url,
                 pubkey,
                 geodes: BTreeSet::new(),
+                metadata: Default::default(),
             };
             <Attestors<T>>::insert(&who, attestor);
 
-            let block_number =
-                <frame_system::Pallet<T>>::block_number().saturated_into::<BlockNumber>();
             <AttestorLastNotify<T>>::insert(&who, block_number);
+            <AttestorEnclaves<T>>::insert(&who, (mr_enclave, block_number));
 
             <AttestorNum<T>>::put(<AttestorNum<T>>::get() + 1);
+            T::OnAttestorRegistered::on_attestor_registered(&who, block_number);
 
             Self::deposit_event(Event::AttestorRegister(who));
             Ok(().into())
         }
 
-        /// Called by attestor to update its url.
+        /// Called by attestor to update its url and, if given, its metadata.
         #[pallet::weight(0)]
-        pub fn attestor_update(origin: OriginFor<T>, url: Vec<u8>) -> DispatchResultWithPostInfo {
+        pub fn attestor_update(
+            origin: OriginFor<T>,
+            url: Vec<u8>,
+            metadata: Option<AttestorMetadata>,
+        ) -> DispatchResultWithPostInfo {
             let who = ensure_signed(origin)?;
             ensure!(
                 <Attestors<T>>::contains_key(&who),
@@ -212,17 +464,222 @@ pub mod pallet {
             );
             let mut attestor = <Attestors<T>>::get(&who);
             attestor.url = url;
+            if let Some(metadata) = metadata {
+                ensure!(metadata.is_valid(), Error::<T>::InvalidMetadata);
+                attestor.metadata = metadata;
+            }
             <Attestors<T>>::insert(&who, attestor);
             Self::deposit_event(Event::AttestorUpdate(who));
             Ok(().into())
         }
 
+        /// Called by attestor to replace its pubkey. `signature` is made by the current key
+        /// over `key_rotation_message` and `attestation` has to bind the new key. Attested
+        /// geodes stay linked to the attestor.
+        #[pallet::weight(0)]
+        pub fn rotate_attestor_key(
+            origin: OriginFor<T>,
+            new_pubkey: Vec<u8>,
+            signature: Vec<u8>,
+            attestation: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            ensure!(
+                <Attestors<T>>::contains_key(&who),
+                Error::<T>::InvalidAttestor
+            );
+            let mut attestor = <Attestors<T>>::get(&who);
+            ensure!(attestor.pubkey != new_pubkey, Error::<T>::SameKey);
+
+            let mut history = <AttestorKeyHistory<T>>::get(&who);
+            if history.is_empty() {
+                // attestor registered before key history was recorded
+                history.push((attestor.pubkey.clone(), 0));
+            }
+            let message = Self::key_rotation_message(&who, history.len() as u32, &new_pubkey);
+            ensure!(
+                verify_p256_signature(&attestor.pubkey, &message, &signature),
+                Error::<T>::InvalidKeySignature
+            );
+            let mr_enclave = Self::verify_attestation(&new_pubkey, &attestation)?;
+
+            let block_number =
+                <frame_system::Pallet<T>>::block_number().saturated_into::<BlockNumber>();
+            history.push((new_pubkey.clone(), block_number));
+            <AttestorKeyHistory<T>>::insert(&who, history);
+            <AttestorEnclaves<T>>::insert(&who, (mr_enclave, block_number));
+
+            attestor.pubkey = new_pubkey;
+            <Attestors<T>>::insert(&who, attestor);
+
+            Self::deposit_event(Event::AttestorKeyRotated(who));
+            Ok(().into())
+        }
+
+        /// Called by attestor to submit a fresh attestation report for its pubkey.
+        #[pallet::weight(0)]
+        pub fn attestor_reattest(
+            origin: OriginFor<T>,
+            attestation: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            ensure!(
+                <Attestors<T>>::contains_key(&who),
+                Error::<T>::InvalidAttestor
+            );
+            let attestor = <Attestors<T>>::get(&who);
+            let mr_enclave = Self::verify_attestation(&attestor.pubkey, &attestation)?;
+            let block_number =
+                <frame_system::Pallet<T>>::block_number().saturated_into::<BlockNumber>();
+            <AttestorEnclaves<T>>::insert(&who, (mr_enclave, block_number));
+            Self::deposit_event(Event::AttestorReattested(who));
+            Ok(().into())
+        }
+
+        /// Called by root to set the root CA key attestation reports are verified against.
+        #[pallet::weight(0)]
+        pub fn set_attestation_root(
+            origin: OriginFor<T>,
+            root: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            let _who = ensure_root(origin)?;
+            <AttestationRoot<T>>::put(root);
+            Self::deposit_event(Event::AttestationRootUpdated);
+            Ok(().into())
+        }
+
+        /// Called by root to allow an enclave measurement to run an attestor.
+        #[pallet::weight(0)]
+        pub fn allow_measurement(
+            origin: OriginFor<T>,
+            mr_enclave: [u8; 32],
+        ) -> DispatchResultWithPostInfo {
+            let _who = ensure_root(origin)?;
+            ensure!(
+                !<AllowedMeasurements<T>>::contains_key(&mr_enclave),
+                Error::<T>::AlreadyAllowed
+            );
+            let block_number =
+                <frame_system::Pallet<T>>::block_number().saturated_into::<BlockNumber>();
+            <AllowedMeasurements<T>>::insert(&mr_enclave, block_number);
+            Self::deposit_event(Event::MeasurementAllowed(mr_enclave));
+            Ok(().into())
+        }
+
+        /// Called by root to remove an enclave measurement from the allowlist. Attestors
+        /// running it can't register, re-attest, send heartbeats or attest geodes anymore, so
+        /// they time out unless they re-attest with an allowed measurement.
+        #[pallet::weight(0)]
+        pub fn revoke_measurement(
+            origin: OriginFor<T>,
+            mr_enclave: [u8; 32],
+        ) -> DispatchResultWithPostInfo {
+            let _who = ensure_root(origin)?;
+            ensure!(
+                <AllowedMeasurements<T>>::contains_key(&mr_enclave),
+                Error::<T>::NotAllowed
+            );
+            <AllowedMeasurements<T>>::remove(&mr_enclave);
+            Self::deposit_event(Event::MeasurementRevoked(mr_enclave));
+            Ok(().into())
+        }
+
+        /// Called by the admin origin to approve an account to register as attestor.
+        #[pallet::weight(0)]
+        pub fn approve_attestor(
+            origin: OriginFor<T>,
+            account: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            T::AttestorAdminOrigin::ensure_origin(origin)?;
+            ensure!(
+                !<AttestorAllowlist<T>>::contains_key(&account),
+                Error::<T>::AlreadyApproved
+            );
+            let block_number =
+                <frame_system::Pallet<T>>::block_number().saturated_into::<BlockNumber>();
+            <AttestorAllowlist<T>>::insert(&account, block_number);
+            Self::deposit_event(Event::AttestorApproved(account));
+            Ok(().into())
+        }
+
+        /// Called by the admin origin to remove an account from the attestor allowlist. A
+        /// registered attestor stays registered but can't register again after exiting.
+        #[pallet::weight(0)]
+        pub fn revoke_attestor_approval(
+            origin: OriginFor<T>,
+            account: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            T::AttestorAdminOrigin::ensure_origin(origin)?;
+            ensure!(
+                <AttestorAllowlist<T>>::contains_key(&account),
+                Error::<T>::NotApproved
+            );
+            <AttestorAllowlist<T>>::remove(&account);
+            Self::deposit_event(Event::AttestorApprovalRevoked(account));
+            Ok(().into())
+        }
+
+        /// Called by the admin origin to switch between open and permissioned registration.
+        #[pallet::weight(0)]
+        pub fn set_permissioned(
+            origin: OriginFor<T>,
+            permissioned: bool,
+        ) -> DispatchResultWithPostInfo {
+            T::AttestorAdminOrigin::ensure_origin(origin)?;
+            <Permissioned<T>>::put(permissioned);
+            Self::deposit_event(Event::PermissionedSet(permissioned));
+            Ok(().into())
+        }
+
+        /// Heartbeat of an attestor. `message` is the attestor id followed by a little endian
+        /// u64 number of a recent block, signed by the attestor's sr25519 key.
         #[pallet::weight(0)]
         pub fn attestor_notify_chain(
             _origin: OriginFor<T>,
             message: Vec<u8>,
             signature_raw_bytes: [u8; 64],
         ) -> DispatchResultWithPostInfo {
+            let (acc, nonce) = Self::verify_notification(&message, &signature_raw_bytes)?;
+
+            let block_number =
+                <frame_system::Pallet<T>>::block_number().saturated_into::<BlockNumber>();
+            let change = if <AttestorLastNotify<T>>::get(&acc) + 2 * T::NotifyInterval::get()
+                >= block_number
+            {
+                ReputationChange::Heartbeat
+            } else {
+                ReputationChange::LateHeartbeat
+            };
+            Self::update_reputation(&acc, change);
+            <AttestorLastNotify<T>>::insert(&acc, block_number);
+            <AttestorLastNonce<T>>::insert(&acc, nonce);
+
+            Ok(().into())
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// Verify an attestation report against the trusted root and the measurement
+        /// allowlist, return the enclave measurement.
+        fn verify_attestation(pubkey: &[u8], attestation: &[u8]) -> Result<[u8; 32], Error<T>> {
+            let root = <AttestationRoot<T>>::get();
+            ensure!(!root.is_empty(), Error::<T>::NoAttestationRoot);
+            let enclave = T::AttestationVerifier::verify(attestation, &root)
+                .map_err(|_| Error::<T>::InvalidAttestationReport)?;
+            ensure!(
+                <AllowedMeasurements<T>>::contains_key(&enclave.mr_enclave),
+                Error::<T>::UntrustedMeasurement
+            );
+            ensure!(enclave.binds(pubkey), Error::<T>::PubkeyNotBound);
+            Ok(enclave.mr_enclave)
+        }
+
+        /// Check signature, attestor and freshness of a heartbeat message, return the
+        /// attestor and the block number it references.
+        fn verify_notification(
+            message: &[u8],
+            signature_raw_bytes: &[u8; 64],
+        ) -> Result<(T::AccountId, u64), Error<T>> {
             // validate inputs
             ensure!(message.len() == 40, Error::<T>::InvalidNotification);
 
@@ -243,16 +700,85 @@ pub mod pallet {
                 <Attestors<T>>::contains_key(&acc),
                 Error::<T>::InvalidAttestor
             );
+            ensure!(Self::is_trusted(&acc), Error::<T>::UntrustedMeasurement);
 
+            let mut nonce = [0u8; 8];
+            nonce.copy_from_slice(&message[32..40]);
+            let nonce = u64::from_le_bytes(nonce);
             let block_number =
-                <frame_system::Pallet<T>>::block_number().saturated_into::<BlockNumber>();
-            <AttestorLastNotify<T>>::insert(&acc, block_number);
+                <frame_system::Pallet<T>>::block_number().saturated_into::<BlockNumber>() as u64;
+            ensure!(nonce <= block_number, Error::<T>::FutureNotification);
+            ensure!(
+                nonce + T::NotifyFreshnessBlockNumber::get() as u64 >= block_number,
+                Error::<T>::StaleNotification
+            );
+            if let Some(last) = <AttestorLastNonce<T>>::get(&acc) {
+                ensure!(nonce > last, Error::<T>::StaleNotification);
+            }
 
-            Ok(().into())
+            Ok((acc, nonce))
+        }
+
+        /// Message the current key of `attestor` signs to hand over to `new_pubkey`.
+        /// `rotation` is the number of keys recorded in the attestor's key history, so a
+        /// handover signature can't be replayed.
+        pub fn key_rotation_message(
+            attestor: &T::AccountId,
+            rotation: u32,
+            new_pubkey: &[u8],
+        ) -> Vec<u8> {
+            let mut message = b"automata/attestor/rotate".to_vec();
+            message.extend_from_slice(&attestor.encode());
+            message.extend_from_slice(&rotation.encode());
+            message.extend_from_slice(new_pubkey);
+            message
+        }
+
+        /// Return the pubkey an attestor used at block `at`.
+        pub fn attestor_key_at(attestor: &T::AccountId, at: BlockNumber) -> Option<Vec<u8>> {
+            <AttestorKeyHistory<T>>::get(attestor)
+                .into_iter()
+                .rev()
+                .find(|(_, since)| *since <= at)
+                .map(|(pubkey, _)| pubkey)
+        }
+
+        /// Sign and submit a heartbeat for the attestor of `public` if one is due and no
+        /// submission of it could still be pending in the pool.
+        fn offchain_notify_chain(public: &Public, now: BlockNumber) -> bool {
+            let acc = match T::AccountId::decode(&mut &public.0[..]) {
+                Ok(acc) => acc,
+                Err(_) => return false,
+            };
+            if !<Attestors<T>>::contains_key(&acc)
+                || <AttestorLastNotify<T>>::get(&acc) + T::NotifyInterval::get() > now
+            {
+                return false;
+            }
+
+            // rate limit through offchain local storage
+            let mut lock_key = NOTIFY_LOCK_PREFIX.to_vec();
+            lock_key.extend_from_slice(&public.0);
+            let lock = StorageValueRef::persistent(&lock_key);
+            let res = lock.mutate(|last: Result<Option<BlockNumber>, _>| match last {
+                Ok(Some(last)) if last + T::NotifyFreshnessBlockNumber::get() >= now => Err(()),
+                _ => Ok(now),
+            });
+            match res {
+                Ok(_) => {}
+                Err(MutateStorageError::ValueFunctionFailed(_))
+                | Err(MutateStorageError::ConcurrentModification(_)) => return false,
+            }
+
+            let mut message = public.0.to_vec();
+            message.extend_from_slice(&(now as u64).to_le_bytes());
+            let signature = match sp_io::crypto::sr25519_sign(KEY_TYPE, public, &message) {
+                Some(signature) => signature,
+                None => return false,
+            };
+            Self::unsigned_attestor_notify_chain(message, signature.0).is_ok()
         }
-    }
 
-    impl<T: Config> Pallet<T> {
         pub fn unsigned_attestor_notify_chain(
             message: Vec<u8>,
             signature_raw_bytes: [u8; 64],
@@ -269,21 +795,98 @@ pub mod pallet {
             result
         }
 
-        /// Return attestors' url and pubkey list for rpc.
-        pub fn attestor_list() -> Vec<(Vec<u8>, Vec<u8>, u32)> {
+        /// Return url and pubkey list of the attestors matching the filter for rpc.
+        pub fn attestor_list(filter: AttestorFilter) -> Vec<(Vec<u8>, Vec<u8>, u32)> {
             let mut res = Vec::<(Vec<u8>, Vec<u8>, u32)>::new();
             <Attestors<T>>::iter()
                 .map(|(_, attestor)| {
-                    res.push((
-                        attestor.url.clone(),
-                        attestor.pubkey,
-                        attestor.geodes.len() as u32,
-                    ));
+                    if filter.matches(&attestor.metadata) {
+                        res.push((
+                            attestor.url.clone(),
+                            attestor.pubkey,
+                            attestor.geodes.len() as u32,
+                        ));
+                    }
+                })
+                .all(|_| true);
+            res
+        }
+
+        /// Apply a change to the reputation of an attestor.
+        pub fn update_reputation(attestor: &T::AccountId, change: ReputationChange) {
+            let now = <frame_system::Pallet<T>>::block_number().saturated_into::<BlockNumber>();
+            <AttestorReputation<T>>::mutate(attestor, |reputation| {
+                reputation.decay(
+                    now,
+                    T::ReputationDecayPeriod::get(),
+                    T::ReputationDecay::get(),
+                );
+                reputation.apply(change);
+            });
+        }
+
+        /// Return the current reputation score of an attestor.
+        pub fn attestor_reputation(attestor: T::AccountId) -> u32 {
+            let now = <frame_system::Pallet<T>>::block_number().saturated_into::<BlockNumber>();
+            let mut reputation = <AttestorReputation<T>>::get(&attestor);
+            reputation.decay(
+                now,
+                T::ReputationDecayPeriod::get(),
+                T::ReputationDecay::get(),
+            );
+            reputation.score
+        }
+
+        /// Return the current reputation scores of the registered attestors.
+        pub fn attestor_reputations() -> Vec<(T::AccountId, u32)> {
+            let mut res = Vec::<(T::AccountId, u32)>::new();
+            <Attestors<T>>::iter()
+                .map(|(id, _)| {
+                    let score = Self::attestor_reputation(id.clone());
+                    res.push((id, score));
                 })
                 .all(|_| true);
             res
         }
 
+        /// Whether the measurement of the latest attestation of the attestor is still allowed.
+        /// Attestors registered before attestation was required have no measurement.
+        pub fn is_trusted(attestor: &T::AccountId) -> bool {
+            <AttestorEnclaves<T>>::try_get(attestor).map_or(true, |(mr_enclave, _)| {
+                <AllowedMeasurements<T>>::contains_key(&mr_enclave)
+            })
+        }
+
+        /// Whether the attestor can attest one more geode.
+        pub fn has_capacity(attestor: &AttestorOf<T>) -> bool {
+            (attestor.geodes.len() as u32) < T::MaxGeodesPerAttestor::get()
+        }
+
+        /// Return up to `count` attestors with spare capacity that don't attest the geode yet,
+        /// least loaded first and most reputable among equally loaded ones.
+        pub fn suggest_attestors(geode: T::AccountId, count: u32) -> Vec<(T::AccountId, u32)> {
+            let attesting = <GeodeAttestors<T>>::get(&geode);
+            let mut candidates = Vec::<(T::AccountId, u32, u32)>::new();
+            <Attestors<T>>::iter()
+                .map(|(id, attestor)| {
+                    if Self::has_capacity(&attestor) && !attesting.contains(&id) {
+                        let score = Self::attestor_reputation(id.clone());
+                        candidates.push((id, attestor.geodes.len() as u32, score));
+                    }
+                })
+                .all(|_| true);
+            candidates.sort_by(|a, b| {
+                a.1.cmp(&b.1)
+                    .then_with(|| b.2.cmp(&a.2))
+                    .then_with(|| a.0.cmp(&b.0))
+            });
+            candidates
+                .into_iter()
+                .take(count as usize)
+                .map(|(id, load, _)| (id, load))
+                .collect()
+        }
+
         /// Return list of attestors of a geode
         pub fn attestors_of_geode(geode: T::AccountId) -> Vec<(Vec<u8>, Vec<u8>)> {
             let mut res = Vec::new();
@@ -310,6 +913,8 @@ pub mod pallet {
             // change storage
             <AttestorNum<T>>::put(<AttestorNum<T>>::get() - 1);
             <AttestorLastNotify<T>>::remove(&attestor);
+            <AttestorLastNonce<T>>::remove(&attestor);
+            <AttestorEnclaves<T>>::remove(&attestor);
             <Attestors<T>>::remove(&attestor);
 
             // deposit event
@@ -372,6 +977,72 @@ pub mod pallet {
                 }
             }
 
+            // clean AttestorLastNonce
+            {
+                let mut attestor_last_nonces = Vec::new();
+                <AttestorLastNonce<T>>::iter()
+                    .map(|(key, _)| {
+                        attestor_last_nonces.push(key);
+                    })
+                    .all(|_| true);
+                for attestor_last_nonce in attestor_last_nonces.iter() {
+                    <AttestorLastNonce<T>>::remove(attestor_last_nonce);
+                }
+            }
+
+            // clean AttestorEnclaves
+            {
+                let mut attestor_enclaves = Vec::new();
+                <AttestorEnclaves<T>>::iter()
+                    .map(|(key, _)| {
+                        attestor_enclaves.push(key);
+                    })
+                    .all(|_| true);
+                for attestor_enclave in attestor_enclaves.iter() {
+                    <AttestorEnclaves<T>>::remove(attestor_enclave);
+                }
+            }
+
+            // clean AttestorKeyHistory
+            {
+                let mut attestor_key_histories = Vec::new();
+                <AttestorKeyHistory<T>>::iter()
+                    .map(|(key, _)| {
+                        attestor_key_histories.push(key);
+                    })
+                    .all(|_| true);
+                for attestor_key_history in attestor_key_histories.iter() {
+                    <AttestorKeyHistory<T>>::remove(attestor_key_history);
+                }
+            }
+
+            // clean AttestorAllowlist
+            {
+                let mut allowlisted = Vec::new();
+                <AttestorAllowlist<T>>::iter()
+                    .map(|(key, _)| {
+                        allowlisted.push(key);
+                    })
+                    .all(|_| true);
+                for account in allowlisted.iter() {
+                    <AttestorAllowlist<T>>::remove(account);
+                }
+            }
+            <Permissioned<T>>::kill();
+
+            // clean AttestorReputation
+            {
+                let mut attestor_reputations = Vec::new();
+                <AttestorReputation<T>>::iter()
+                    .map(|(key, _)| {
+                        attestor_reputations.push(key);
+                    })
+                    .all(|_| true);
+                for attestor_reputation in attestor_reputations.iter() {
+                    <AttestorReputation<T>>::remove(attestor_reputation);
+                }
+            }
+
             // reset AttestorNum
             <AttestorNum<T>>::put(0);
         }
@@ -0,0 +1,41 @@
+use crate::{Attestor, Attestors, Config, Pallet};
+use codec::Decode;
+use frame_support::{
+    traits::{Get, StorageVersion},
+    weights::Weight,
+};
+use sp_std::collections::btree_set::BTreeSet;
+use sp_std::prelude::*;
+
+/// Add metadata to attestors.
+pub mod v1 {
+    use super::*;
+
+    /// Attestor before metadata was introduced.
+    #[derive(Decode)]
+    pub struct OldAttestor<AccountId: Ord> {
+        pub url: Vec<u8>,
+        pub pubkey: Vec<u8>,
+        pub geodes: BTreeSet<AccountId>,
+    }
+
+    pub fn migrate<T: Config>() -> Weight {
+        if StorageVersion::get::<Pallet<T>>() >= 1 {
+            return T::DbWeight::get().reads(1);
+        }
+
+        let mut translated = 0u64;
+        <Attestors<T>>::translate::<OldAttestor<T::AccountId>, _>(|_, old| {
+            translated += 1;
+            Some(Attestor {
+                url: old.url,
+                pubkey: old.pubkey,
+                geodes: old.geodes,
+                metadata: Default::default(),
+            })
+        });
+        StorageVersion::new(1).put::<Pallet<T>>();
+
+        T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+    }
+}
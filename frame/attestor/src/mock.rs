@@ -1,4 +1,5 @@
 use crate as attestor;
+use crate::attestation::{sha256, AttestationError, AttestationVerifier, EnclaveReport};
 use frame_support::parameter_types;
 use frame_system as system;
 use sp_core::H256;
@@ -8,7 +9,7 @@ use sp_runtime::{
 };
 
 use frame_support::dispatch::DispatchResultWithPostInfo;
-type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+pub type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
 type Block = frame_system::mocking::MockBlock<Test>;
 use automata_traits::AttestorAccounting;
 
@@ -87,6 +88,33 @@ impl AttestorAccounting for Test {
     }
 }
 
+pub const MR_ENCLAVE: [u8; 32] = [7u8; 32];
+
+/// Mock reports are `mr_enclave || report_data`.
+impl AttestationVerifier for Test {
+    fn verify(report: &[u8], _root: &[u8]) -> Result<EnclaveReport, AttestationError> {
+        if report.len() != 96 {
+            return Err(AttestationError::Malformed);
+        }
+        let mut enclave = EnclaveReport {
+            mr_enclave: [0u8; 32],
+            mr_signer: [0u8; 32],
+            report_data: [0u8; 64],
+        };
+        enclave.mr_enclave.copy_from_slice(&report[..32]);
+        enclave.report_data.copy_from_slice(&report[32..]);
+        Ok(enclave)
+    }
+}
+
+/// Build a mock attestation report of `mr_enclave` binding `pubkey`.
+pub fn attestation_report(mr_enclave: [u8; 32], pubkey: &[u8]) -> Vec<u8> {
+    let mut report = mr_enclave.to_vec();
+    report.extend_from_slice(&sha256(pubkey));
+    report.extend_from_slice(&[0u8; 32]);
+    report
+}
+
 impl<C> frame_system::offchain::SendTransactionTypes<C> for Test
 where
     Call: From<C>,
@@ -95,11 +123,27 @@ where
     type OverarchingCall = Call;
 }
 
+parameter_types! {
+    pub const NotifyFreshnessBlockNumber: primitives::BlockNumber = 5;
+    pub const NotifyInterval: primitives::BlockNumber = 2;
+    pub const ReputationDecayPeriod: primitives::BlockNumber = 10;
+    pub const ReputationDecay: sp_runtime::Percent = sp_runtime::Percent::from_percent(50);
+    pub const MaxGeodesPerAttestor: u32 = 3;
+}
+
 impl attestor::Config for Test {
     type Event = Event;
     type Currency = Balances;
     type Call = Call;
     type AttestorAccounting = Test;
+    type AttestationVerifier = Test;
+    type NotifyFreshnessBlockNumber = NotifyFreshnessBlockNumber;
+    type NotifyInterval = NotifyInterval;
+    type MaxGeodesPerAttestor = MaxGeodesPerAttestor;
+    type ReputationDecayPeriod = ReputationDecayPeriod;
+    type ReputationDecay = ReputationDecay;
+    type AttestorAdminOrigin = frame_system::EnsureRoot<Self::AccountId>;
+    type OnAttestorRegistered = ();
 }
 
 // Build genesis storage according to the mock runtime.
@@ -114,7 +158,11 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
     .unwrap();
 
     let mut ext = sp_io::TestExternalities::new(t);
-    ext.execute_with(|| System::set_block_number(1));
+    ext.execute_with(|| {
+        System::set_block_number(1);
+        attestor::AttestationRoot::<Test>::put(vec![1]);
+        attestor::AllowedMeasurements::<Test>::insert(MR_ENCLAVE, 0);
+    });
     ext
 }
 
@@ -0,0 +1,98 @@
+use codec::{Decode, Encode};
+use primitives::BlockNumber;
+use sp_runtime::{PerThing, Percent, RuntimeDebug};
+
+/// Score of an attestor without any record, scores decay towards it.
+pub const REPUTATION_NEUTRAL: u32 = 1_000;
+/// Upper bound of a score.
+pub const REPUTATION_MAX: u32 = 2 * REPUTATION_NEUTRAL;
+
+/// Things an attestor did that affect its reputation.
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug)]
+pub enum ReputationChange {
+    /// Heartbeat arrived in time.
+    Heartbeat,
+    /// Heartbeat arrived later than twice the notify interval.
+    LateHeartbeat,
+    /// A misconduct report the attestor joined got approved.
+    ReportApproved,
+    /// A misconduct report the attestor joined expired without approval.
+    ReportExpired,
+    /// A geode the attestor vouched for got slashed without it reporting.
+    GeodeSlashed,
+    /// A misconduct report the attestor joined got overturned by a dispute.
+    ReportDisputed,
+}
+
+impl ReputationChange {
+    /// Signed amount the score moves by.
+    pub fn delta(&self) -> i64 {
+        match self {
+            ReputationChange::Heartbeat => 1,
+            ReputationChange::LateHeartbeat => -20,
+            ReputationChange::ReportApproved => 20,
+            ReputationChange::ReportExpired => -20,
+            ReputationChange::GeodeSlashed => -50,
+            ReputationChange::ReportDisputed => -100,
+        }
+    }
+}
+
+/// Reputation of an attestor.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct Reputation {
+    /// Score at block `updated`.
+    pub score: u32,
+    /// Start of the decay period the score was last updated in.
+    pub updated: BlockNumber,
+}
+
+impl Default for Reputation {
+    fn default() -> Self {
+        Reputation {
+            score: REPUTATION_NEUTRAL,
+            updated: 0,
+        }
+    }
+}
+
+impl Reputation {
+    /// Move the score towards neutral by `decay` of the distance for every full `period`
+    /// elapsed since the last update.
+    pub fn decay(&mut self, now: BlockNumber, period: BlockNumber, decay: Percent) {
+        if period == 0 || now <= self.updated {
+            return;
+        }
+        let periods = (now - self.updated) / period;
+        self.updated += periods * period;
+
+        let mut distance = if self.score >= REPUTATION_NEUTRAL {
+            self.score - REPUTATION_NEUTRAL
+        } else {
+            REPUTATION_NEUTRAL - self.score
+        };
+        if decay.is_zero() {
+            return;
+        }
+        // every round shrinks the distance by at least 1
+        for _ in 0..periods {
+            if distance == 0 {
+                break;
+            }
+            distance -= decay.mul_ceil(distance);
+        }
+        self.score = if self.score >= REPUTATION_NEUTRAL {
+            REPUTATION_NEUTRAL + distance
+        } else {
+            REPUTATION_NEUTRAL - distance
+        };
+    }
+
+    /// Apply a change to the score.
+    pub fn apply(&mut self, change: ReputationChange) {
+        let score = (self.score as i64 + change.delta())
+            .max(0)
+            .min(REPUTATION_MAX as i64);
+        self.score = score as u32;
+    }
+}
@@ -1,5 +1,6 @@
+use crate::reputation::*;
 use crate::{Attestor, *};
-use frame_support::assert_ok;
+use frame_support::{assert_noop, assert_ok};
 use frame_system::pallet_prelude::*;
 use hex_literal::hex;
 use mock::*;
@@ -17,7 +18,8 @@ fn it_works_for_attestor_register() {
         assert_ok!(AttestorModule::attestor_register(
             Origin::signed(attestor_account),
             url.clone(),
-            pubkey.clone()
+            pubkey.clone(),
+            attestation_report(MR_ENCLAVE, &pubkey)
         ));
         let data = AttestorModule::attestors(&attestor_account);
 
@@ -28,11 +30,51 @@ fn it_works_for_attestor_register() {
                 url: url,
                 pubkey: pubkey,
                 geodes: Default::default(),
+                metadata: Default::default(),
             }
         );
     });
 }
 
+#[test]
+fn it_works_for_suggest_attestors() {
+    new_test_ext().execute_with(|| {
+        for attestor_account in 1..3 {
+            assert_ok!(AttestorModule::attestor_register(
+                Origin::signed(attestor_account),
+                vec![1],
+                vec![2],
+                attestation_report(MR_ENCLAVE, &[2])
+            ));
+        }
+        let geode = 10;
+
+        // least loaded first
+        Attestors::<Test>::mutate(1, |attestor| {
+            attestor.geodes.insert(11);
+        });
+        assert_eq!(
+            AttestorModule::suggest_attestors(geode, 2),
+            vec![(2, 0), (1, 1)]
+        );
+        assert_eq!(AttestorModule::suggest_attestors(geode, 1), vec![(2, 0)]);
+
+        // attestors already attesting the geode are skipped
+        GeodeAttestors::<Test>::mutate(geode, |attestors| {
+            attestors.insert(2);
+        });
+        assert_eq!(AttestorModule::suggest_attestors(geode, 2), vec![(1, 1)]);
+
+        // full attestors are skipped
+        Attestors::<Test>::mutate(1, |attestor| {
+            attestor.geodes.insert(12);
+            attestor.geodes.insert(13);
+        });
+        assert!(!AttestorModule::has_capacity(&AttestorModule::attestors(1)));
+        assert_eq!(AttestorModule::suggest_attestors(geode, 2), vec![]);
+    });
+}
+
 #[test]
 fn get_ss58_address_from_pubkey() {
     new_test_ext().execute_with(|| {
@@ -55,7 +97,8 @@ fn it_works_for_attestor_remove() {
         assert_ok!(AttestorModule::attestor_register(
             Origin::signed(attestor_account),
             url.clone(),
-            pubkey.clone()
+            pubkey.clone(),
+            attestation_report(MR_ENCLAVE, &pubkey)
         ));
 
         // remove old events
@@ -72,6 +115,7 @@ fn it_works_for_attestor_remove() {
                 url: vec![],
                 pubkey: vec![],
                 geodes: Default::default(),
+                metadata: Default::default(),
             }
         );
     });
@@ -89,7 +133,8 @@ fn it_works_for_attestor_update() {
         assert_ok!(AttestorModule::attestor_register(
             Origin::signed(attestor_account),
             url.clone(),
-            pubkey.clone()
+            pubkey.clone(),
+            attestation_report(MR_ENCLAVE, &pubkey)
         ));
 
         let data = AttestorModule::attestors(&attestor_account);
@@ -101,6 +146,7 @@ fn it_works_for_attestor_update() {
                 url: url,
                 pubkey: pubkey.clone(),
                 geodes: Default::default(),
+                metadata: Default::default(),
             }
         );
 
@@ -111,7 +157,8 @@ fn it_works_for_attestor_update() {
         let new_url = vec![3];
         assert_ok!(AttestorModule::attestor_update(
             Origin::signed(attestor_account),
-            new_url.clone()
+            new_url.clone(),
+            None
         ));
         let data = AttestorModule::attestors(&attestor_account);
 
@@ -122,7 +169,693 @@ fn it_works_for_attestor_update() {
                 url: new_url,
                 pubkey: pubkey,
                 geodes: Default::default(),
+                metadata: Default::default(),
             }
         );
     });
 }
+
+fn metadata(region: &[u8], version: &[u8]) -> AttestorMetadata {
+    AttestorMetadata {
+        region: region.to_vec(),
+        version: version.to_vec(),
+        attestation_types: vec![AttestationType::SgxDcap],
+        endpoints: vec![b"https://backup.attestor".to_vec()],
+    }
+}
+
+#[test]
+fn it_works_for_attestor_update_metadata() {
+    new_test_ext().execute_with(|| {
+        let attestor_account = 1;
+        assert_ok!(AttestorModule::attestor_register(
+            Origin::signed(attestor_account),
+            vec![1],
+            vec![2],
+            attestation_report(MR_ENCLAVE, &[2])
+        ));
+
+        assert_ok!(AttestorModule::attestor_update(
+            Origin::signed(attestor_account),
+            vec![3],
+            Some(metadata(b"SG", b"1.0.0"))
+        ));
+        let data = AttestorModule::attestors(&attestor_account);
+        assert_eq!(data.url, vec![3]);
+        assert_eq!(data.metadata, metadata(b"SG", b"1.0.0"));
+
+        // metadata is kept when not given
+        assert_ok!(AttestorModule::attestor_update(
+            Origin::signed(attestor_account),
+            vec![4],
+            None
+        ));
+        assert_eq!(
+            AttestorModule::attestors(&attestor_account).metadata,
+            metadata(b"SG", b"1.0.0")
+        );
+
+        assert_noop!(
+            AttestorModule::attestor_update(
+                Origin::signed(attestor_account),
+                vec![4],
+                Some(metadata(b"sg", b"1.0.0"))
+            ),
+            Error::<Test>::InvalidMetadata
+        );
+        let mut too_many_endpoints = metadata(b"SG", b"1.0.0");
+        too_many_endpoints.endpoints = vec![vec![1]; MAX_ENDPOINTS + 1];
+        assert_noop!(
+            AttestorModule::attestor_update(
+                Origin::signed(attestor_account),
+                vec![4],
+                Some(too_many_endpoints)
+            ),
+            Error::<Test>::InvalidMetadata
+        );
+    });
+}
+
+#[test]
+fn attestor_list_filters_by_metadata() {
+    new_test_ext().execute_with(|| {
+        for (attestor_account, region) in [(1, b"SG"), (2, b"US")].iter() {
+            assert_ok!(AttestorModule::attestor_register(
+                Origin::signed(*attestor_account),
+                vec![*attestor_account as u8],
+                vec![2],
+                attestation_report(MR_ENCLAVE, &[2])
+            ));
+            assert_ok!(AttestorModule::attestor_update(
+                Origin::signed(*attestor_account),
+                vec![*attestor_account as u8],
+                Some(metadata(*region, b"1.0.0"))
+            ));
+        }
+
+        assert_eq!(AttestorModule::attestor_list(Default::default()).len(), 2);
+        let by_region = AttestorFilter {
+            region: Some(b"US".to_vec()),
+            ..Default::default()
+        };
+        assert_eq!(
+            AttestorModule::attestor_list(by_region),
+            vec![(vec![2], vec![2], 0)]
+        );
+        let by_version = AttestorFilter {
+            version: Some(b"2.0.0".to_vec()),
+            ..Default::default()
+        };
+        assert!(AttestorModule::attestor_list(by_version).is_empty());
+        let by_type = AttestorFilter {
+            attestation_type: Some(AttestationType::SgxDcap),
+            ..Default::default()
+        };
+        assert_eq!(AttestorModule::attestor_list(by_type).len(), 2);
+        let by_type = AttestorFilter {
+            attestation_type: Some(AttestationType::AmdSevSnp),
+            ..Default::default()
+        };
+        assert!(AttestorModule::attestor_list(by_type).is_empty());
+    });
+}
+
+#[test]
+fn migrate_attestors_to_v1() {
+    use codec::Encode;
+    use frame_support::traits::StorageVersion;
+
+    #[derive(Encode)]
+    struct OldAttestor {
+        url: Vec<u8>,
+        pubkey: Vec<u8>,
+        geodes: sp_std::collections::btree_set::BTreeSet<u64>,
+    }
+
+    new_test_ext().execute_with(|| {
+        let mut geodes = sp_std::collections::btree_set::BTreeSet::new();
+        geodes.insert(5);
+        let old = OldAttestor {
+            url: vec![1],
+            pubkey: vec![2],
+            geodes: geodes.clone(),
+        };
+        frame_support::storage::unhashed::put_raw(
+            &Attestors::<Test>::hashed_key_for(1),
+            &old.encode(),
+        );
+
+        migrations::v1::migrate::<Test>();
+
+        assert_eq!(
+            AttestorModule::attestors(1),
+            Attestor {
+                url: vec![1],
+                pubkey: vec![2],
+                geodes,
+                metadata: Default::default(),
+            }
+        );
+        assert_eq!(
+            StorageVersion::get::<AttestorModule>(),
+            StorageVersion::new(1)
+        );
+    });
+}
+
+#[test]
+fn attestor_register_in_permissioned_mode() {
+    new_test_ext().execute_with(|| {
+        let attestor_account = 1;
+        let register = || {
+            AttestorModule::attestor_register(
+                Origin::signed(attestor_account),
+                vec![1],
+                vec![2],
+                attestation_report(MR_ENCLAVE, &[2]),
+            )
+        };
+
+        assert_noop!(
+            AttestorModule::set_permissioned(Origin::signed(attestor_account), true),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_ok!(AttestorModule::set_permissioned(Origin::root(), true));
+        assert_noop!(register(), Error::<Test>::NotApproved);
+
+        assert_ok!(AttestorModule::approve_attestor(
+            Origin::root(),
+            attestor_account
+        ));
+        assert_noop!(
+            AttestorModule::approve_attestor(Origin::root(), attestor_account),
+            Error::<Test>::AlreadyApproved
+        );
+        assert_ok!(register());
+
+        // re-registration after revocation is rejected
+        assert_ok!(AttestorModule::revoke_attestor_approval(
+            Origin::root(),
+            attestor_account
+        ));
+        AttestorModule::attestor_remove(attestor_account);
+        assert_noop!(register(), Error::<Test>::NotApproved);
+
+        // open mode lets anybody register again
+        assert_ok!(AttestorModule::set_permissioned(Origin::root(), false));
+        assert_ok!(register());
+
+        System::assert_has_event(crate::Event::AttestorApproved(attestor_account).into());
+        System::assert_has_event(crate::Event::AttestorApprovalRevoked(attestor_account).into());
+        System::assert_last_event(crate::Event::AttestorRegister(attestor_account).into());
+    });
+}
+
+#[test]
+fn attestor_register_rejects_untrusted_measurement() {
+    new_test_ext().execute_with(|| {
+        let url = vec![1];
+        let pubkey = vec![2];
+        let attestor_account = 1;
+
+        assert_noop!(
+            AttestorModule::attestor_register(
+                Origin::signed(attestor_account),
+                url.clone(),
+                pubkey.clone(),
+                attestation_report([8u8; 32], &pubkey)
+            ),
+            Error::<Test>::UntrustedMeasurement
+        );
+
+        // allowed by root afterwards
+        assert_ok!(AttestorModule::allow_measurement(Origin::root(), [8u8; 32]));
+        assert_ok!(AttestorModule::attestor_register(
+            Origin::signed(attestor_account),
+            url,
+            pubkey.clone(),
+            attestation_report([8u8; 32], &pubkey)
+        ));
+        assert_eq!(
+            AttestorModule::attestor_enclaves(&attestor_account),
+            ([8u8; 32], 1)
+        );
+    });
+}
+
+#[test]
+fn attestor_register_rejects_invalid_report() {
+    new_test_ext().execute_with(|| {
+        let url = vec![1];
+        let pubkey = vec![2];
+        let attestor_account = 1;
+
+        // report binding another pubkey
+        assert_noop!(
+            AttestorModule::attestor_register(
+                Origin::signed(attestor_account),
+                url.clone(),
+                pubkey.clone(),
+                attestation_report(MR_ENCLAVE, &[3])
+            ),
+            Error::<Test>::PubkeyNotBound
+        );
+
+        // report failing verification
+        assert_noop!(
+            AttestorModule::attestor_register(
+                Origin::signed(attestor_account),
+                url.clone(),
+                pubkey.clone(),
+                vec![0u8; 10]
+            ),
+            Error::<Test>::InvalidAttestationReport
+        );
+
+        // no trusted root configured
+        AttestationRoot::<Test>::kill();
+        assert_noop!(
+            AttestorModule::attestor_register(
+                Origin::signed(attestor_account),
+                url,
+                pubkey.clone(),
+                attestation_report(MR_ENCLAVE, &pubkey)
+            ),
+            Error::<Test>::NoAttestationRoot
+        );
+    });
+}
+
+#[test]
+fn it_works_for_attestor_reattest() {
+    new_test_ext().execute_with(|| {
+        let url = vec![1];
+        let pubkey = vec![2];
+        let attestor_account = 1;
+
+        assert_ok!(AttestorModule::attestor_register(
+            Origin::signed(attestor_account),
+            url,
+            pubkey.clone(),
+            attestation_report(MR_ENCLAVE, &pubkey)
+        ));
+
+        // move to a new enclave build and retire the old one
+        let new_measurement = [9u8; 32];
+        assert_ok!(AttestorModule::allow_measurement(
+            Origin::root(),
+            new_measurement
+        ));
+        assert_ok!(AttestorModule::revoke_measurement(
+            Origin::root(),
+            MR_ENCLAVE
+        ));
+        assert_noop!(
+            AttestorModule::attestor_reattest(
+                Origin::signed(attestor_account),
+                attestation_report(MR_ENCLAVE, &pubkey)
+            ),
+            Error::<Test>::UntrustedMeasurement
+        );
+
+        System::set_block_number(5);
+        assert_ok!(AttestorModule::attestor_reattest(
+            Origin::signed(attestor_account),
+            attestation_report(new_measurement, &pubkey)
+        ));
+        assert_eq!(
+            AttestorModule::attestor_enclaves(&attestor_account),
+            (new_measurement, 5)
+        );
+    });
+}
+
+#[test]
+fn attestor_notify_chain_rejects_revoked_measurement() {
+    use sp_core::Pair;
+    new_test_ext().execute_with(|| {
+        let pair = sp_core::sr25519::Pair::from_seed(&[1u8; 32]);
+        let public = pair.public().0;
+        let account = u64::from_le_bytes([
+            public[0], public[1], public[2], public[3], public[4], public[5], public[6], public[7],
+        ]);
+        assert_ok!(AttestorModule::attestor_register(
+            Origin::signed(account),
+            vec![1],
+            vec![2],
+            attestation_report(MR_ENCLAVE, &[2])
+        ));
+        assert_ok!(AttestorModule::revoke_measurement(
+            Origin::root(),
+            MR_ENCLAVE
+        ));
+
+        let mut message = public.to_vec();
+        message.extend_from_slice(&1u64.to_le_bytes());
+        let signature = pair.sign(&message).0;
+        assert_noop!(
+            AttestorModule::attestor_notify_chain(Origin::none(), message, signature),
+            Error::<Test>::UntrustedMeasurement
+        );
+        assert!(!AttestorModule::is_trusted(&account));
+    });
+}
+
+#[test]
+fn verify_dcap_quote_rejects_malformed_quote() {
+    assert_eq!(
+        crate::attestation::verify_dcap_quote(&[0u8; 100], &[4u8; 65]),
+        Err(crate::attestation::AttestationError::Malformed)
+    );
+
+    let mut quote = vec![0u8; 436];
+    quote[0] = 3;
+    quote[2] = 1;
+    assert_eq!(
+        crate::attestation::verify_dcap_quote(&quote, &[4u8; 65]),
+        Err(crate::attestation::AttestationError::Unsupported)
+    );
+}
+
+fn p256_key(seed: u8) -> (p256::ecdsa::SigningKey, Vec<u8>) {
+    let key = p256::ecdsa::SigningKey::from_bytes(&[seed; 32]).unwrap();
+    let pubkey = p256::ecdsa::VerifyingKey::from(&key)
+        .to_encoded_point(false)
+        .as_bytes()
+        .to_vec();
+    (key, pubkey)
+}
+
+fn sign(key: &p256::ecdsa::SigningKey, message: &[u8]) -> Vec<u8> {
+    use p256::ecdsa::signature::Signer;
+    let signature: p256::ecdsa::Signature = key.sign(message);
+    signature.as_ref().to_vec()
+}
+
+#[test]
+fn it_works_for_rotate_attestor_key() {
+    new_test_ext().execute_with(|| {
+        let attestor_account = 1;
+        let geode_account = 2;
+        let (old_key, old_pubkey) = p256_key(1);
+        let (_, new_pubkey) = p256_key(2);
+
+        assert_ok!(AttestorModule::attestor_register(
+            Origin::signed(attestor_account),
+            vec![1],
+            old_pubkey.clone(),
+            attestation_report(MR_ENCLAVE, &old_pubkey)
+        ));
+        // link a geode to the attestor
+        Attestors::<Test>::mutate(&attestor_account, |attestor| {
+            attestor.geodes.insert(geode_account);
+        });
+        GeodeAttestors::<Test>::mutate(&geode_account, |attestors| {
+            attestors.insert(attestor_account);
+        });
+
+        // handover signed by the wrong key
+        let message = AttestorModule::key_rotation_message(&attestor_account, 1, &new_pubkey);
+        assert_noop!(
+            AttestorModule::rotate_attestor_key(
+                Origin::signed(attestor_account),
+                new_pubkey.clone(),
+                sign(&p256_key(3).0, &message),
+                attestation_report(MR_ENCLAVE, &new_pubkey)
+            ),
+            Error::<Test>::InvalidKeySignature
+        );
+
+        System::set_block_number(10);
+        let signature = sign(&old_key, &message);
+        assert_ok!(AttestorModule::rotate_attestor_key(
+            Origin::signed(attestor_account),
+            new_pubkey.clone(),
+            signature.clone(),
+            attestation_report(MR_ENCLAVE, &new_pubkey)
+        ));
+
+        let attestor = AttestorModule::attestors(&attestor_account);
+        assert_eq!(attestor.pubkey, new_pubkey);
+        assert!(attestor.geodes.contains(&geode_account));
+        assert!(AttestorModule::geode_attestors(&geode_account).contains(&attestor_account));
+        assert_eq!(
+            AttestorModule::attestor_key_history(&attestor_account),
+            vec![(old_pubkey.clone(), 1), (new_pubkey.clone(), 10)]
+        );
+        assert_eq!(
+            AttestorModule::attestor_key_at(&attestor_account, 9),
+            Some(old_pubkey)
+        );
+        assert_eq!(
+            AttestorModule::attestor_key_at(&attestor_account, 10),
+            Some(new_pubkey.clone())
+        );
+
+        // the same key can't be submitted again
+        assert_noop!(
+            AttestorModule::rotate_attestor_key(
+                Origin::signed(attestor_account),
+                new_pubkey.clone(),
+                signature,
+                attestation_report(MR_ENCLAVE, &new_pubkey)
+            ),
+            Error::<Test>::SameKey
+        );
+    });
+}
+
+/// Register the attestor of a sr25519 key, return its account and a signer of
+/// heartbeats referencing a block.
+fn register_notifying_attestor() -> (u64, impl Fn(u64) -> (Vec<u8>, [u8; 64])) {
+    use sp_core::Pair;
+    let pair = sp_core::sr25519::Pair::from_seed(&[1u8; 32]);
+    let public = pair.public().0;
+    let account = u64::from_le_bytes([
+        public[0], public[1], public[2], public[3], public[4], public[5], public[6], public[7],
+    ]);
+    assert_ok!(AttestorModule::attestor_register(
+        Origin::signed(account),
+        vec![1],
+        vec![2],
+        attestation_report(MR_ENCLAVE, &[2])
+    ));
+
+    let notify = move |block: u64| {
+        let mut message = public.to_vec();
+        message.extend_from_slice(&block.to_le_bytes());
+        let signature = pair.sign(&message).0;
+        (message, signature)
+    };
+    (account, notify)
+}
+
+#[test]
+fn it_works_for_attestor_notify_chain() {
+    new_test_ext().execute_with(|| {
+        let (account, notify) = register_notifying_attestor();
+
+        System::set_block_number(4);
+        let (message, signature) = notify(3);
+        assert_ok!(AttestorModule::attestor_notify_chain(
+            Origin::none(),
+            message,
+            signature
+        ));
+        assert_eq!(AttestorModule::attestor_last_notification(&account), 4);
+        assert_eq!(AttestorModule::attestor_last_nonce(&account), Some(3));
+    });
+}
+
+#[test]
+fn attestor_notify_chain_updates_reputation() {
+    new_test_ext().execute_with(|| {
+        let (account, notify) = register_notifying_attestor();
+        assert_eq!(
+            AttestorModule::attestor_reputation(account),
+            REPUTATION_NEUTRAL
+        );
+
+        // in time
+        System::set_block_number(4);
+        let (message, signature) = notify(3);
+        assert_ok!(AttestorModule::attestor_notify_chain(
+            Origin::none(),
+            message,
+            signature
+        ));
+        assert_eq!(
+            AttestorModule::attestor_reputation(account),
+            REPUTATION_NEUTRAL + 1
+        );
+
+        // late, after the previous reward decayed
+        System::set_block_number(10);
+        let (message, signature) = notify(9);
+        assert_ok!(AttestorModule::attestor_notify_chain(
+            Origin::none(),
+            message,
+            signature
+        ));
+        assert_eq!(
+            AttestorModule::attestor_reputation(account),
+            REPUTATION_NEUTRAL - 20
+        );
+        assert_eq!(
+            AttestorModule::attestor_reputations(),
+            vec![(account, REPUTATION_NEUTRAL - 20)]
+        );
+    });
+}
+
+#[test]
+fn reputation_decays_towards_neutral() {
+    let decay = sp_runtime::Percent::from_percent(50);
+    let mut reputation = Reputation {
+        score: REPUTATION_NEUTRAL + 100,
+        updated: 0,
+    };
+    reputation.decay(25, 10, decay);
+    assert_eq!(
+        reputation,
+        Reputation {
+            score: REPUTATION_NEUTRAL + 25,
+            updated: 20,
+        }
+    );
+
+    let mut reputation = Reputation {
+        score: REPUTATION_NEUTRAL - 100,
+        updated: 0,
+    };
+    reputation.decay(25, 10, decay);
+    assert_eq!(reputation.score, REPUTATION_NEUTRAL - 25);
+
+    // scores are bounded
+    reputation.score = 10;
+    reputation.apply(ReputationChange::GeodeSlashed);
+    assert_eq!(reputation.score, 0);
+    reputation.score = REPUTATION_MAX;
+    reputation.apply(ReputationChange::ReportApproved);
+    assert_eq!(reputation.score, REPUTATION_MAX);
+}
+
+#[test]
+fn attestor_notify_chain_rejects_replay() {
+    new_test_ext().execute_with(|| {
+        let (account, notify) = register_notifying_attestor();
+
+        System::set_block_number(4);
+        let (message, signature) = notify(3);
+        assert_ok!(AttestorModule::attestor_notify_chain(
+            Origin::none(),
+            message.clone(),
+            signature
+        ));
+
+        // the captured heartbeat can't be submitted again
+        System::set_block_number(6);
+        assert_noop!(
+            AttestorModule::attestor_notify_chain(Origin::none(), message, signature),
+            Error::<Test>::StaleNotification
+        );
+        assert_eq!(AttestorModule::attestor_last_notification(&account), 4);
+    });
+}
+
+#[test]
+fn attestor_notify_chain_rejects_out_of_window() {
+    new_test_ext().execute_with(|| {
+        let (_account, notify) = register_notifying_attestor();
+
+        System::set_block_number(20);
+        let (message, signature) = notify(14);
+        assert_noop!(
+            AttestorModule::attestor_notify_chain(Origin::none(), message, signature),
+            Error::<Test>::StaleNotification
+        );
+
+        let (message, signature) = notify(21);
+        assert_noop!(
+            AttestorModule::attestor_notify_chain(Origin::none(), message, signature),
+            Error::<Test>::FutureNotification
+        );
+
+        // at the edge of the window
+        let (message, signature) = notify(15);
+        assert_ok!(AttestorModule::attestor_notify_chain(
+            Origin::none(),
+            message,
+            signature
+        ));
+    });
+}
+
+#[test]
+fn offchain_worker_submits_heartbeats() {
+    use codec::Decode;
+    use frame_support::traits::Hooks;
+    use sp_core::offchain::{
+        testing::{TestOffchainExt, TestTransactionPoolExt},
+        OffchainDbExt, OffchainWorkerExt, TransactionPoolExt,
+    };
+    use sp_keystore::{testing::KeyStore, KeystoreExt, SyncCryptoStore};
+    use std::sync::Arc;
+
+    let keystore = KeyStore::new();
+    let public = SyncCryptoStore::sr25519_generate_new(&keystore, KEY_TYPE, None).unwrap();
+    let account = u64::decode(&mut &public.0[..]).unwrap();
+
+    let mut ext = new_test_ext();
+    let (offchain, _) = TestOffchainExt::new();
+    let (pool, pool_state) = TestTransactionPoolExt::new();
+    ext.register_extension(OffchainDbExt::new(offchain.clone()));
+    ext.register_extension(OffchainWorkerExt::new(offchain));
+    ext.register_extension(TransactionPoolExt::new(pool));
+    ext.register_extension(KeystoreExt(Arc::new(keystore)));
+
+    ext.execute_with(|| {
+        // not registered yet, nothing is submitted
+        AttestorModule::offchain_worker(1);
+        assert!(pool_state.read().transactions.is_empty());
+
+        assert_ok!(AttestorModule::attestor_register(
+            Origin::signed(account),
+            vec![1],
+            vec![2],
+            attestation_report(MR_ENCLAVE, &[2])
+        ));
+
+        // registration counts as a heartbeat
+        AttestorModule::offchain_worker(2);
+        assert!(pool_state.read().transactions.is_empty());
+
+        System::set_block_number(3);
+        AttestorModule::offchain_worker(3);
+        let tx = pool_state.write().transactions.pop().unwrap();
+        assert!(pool_state.read().transactions.is_empty());
+        let tx = UncheckedExtrinsic::decode(&mut &*tx).unwrap();
+        assert_eq!(tx.signature, None);
+        let (message, signature) = match tx.call {
+            Call::AttestorModule(crate::Call::attestor_notify_chain(message, signature)) => {
+                (message, signature)
+            }
+            _ => panic!("unexpected call"),
+        };
+        let mut expected = public.0.to_vec();
+        expected.extend_from_slice(&3u64.to_le_bytes());
+        assert_eq!(message, expected);
+
+        // rate limited while the submitted heartbeat can still be included
+        AttestorModule::offchain_worker(4);
+        assert!(pool_state.read().transactions.is_empty());
+
+        System::set_block_number(4);
+        assert_ok!(AttestorModule::attestor_notify_chain(
+            Origin::none(),
+            message,
+            signature
+        ));
+        assert_eq!(AttestorModule::attestor_last_notification(&account), 4);
+    });
+}
@@ -0,0 +1,21 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+pub use pallet_geode::{Geode, GeodeState, StateTransition};
+use sp_runtime::traits::MaybeDisplay;
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+    pub trait GeodeRuntimeApi<AccountId, Hash> where
+        AccountId: Codec + MaybeDisplay,
+        Hash: Codec
+    {
+        fn registered_geodes() -> Vec<Geode<AccountId, Hash>>;
+        fn attested_geodes() -> Vec<Geode<AccountId, Hash>>;
+        fn attestor_attested_geodes(attestor: AccountId) -> Vec<Geode<AccountId, Hash>>;
+        fn attested_geodes_with(requirements: Vec<(Vec<u8>, u64)>) -> Vec<Geode<AccountId, Hash>>;
+        fn geodes_of_provider(provider: AccountId) -> Vec<Geode<AccountId, Hash>>;
+        fn geode_state(geode: AccountId) -> Option<GeodeState>;
+        fn geode_history(geode: AccountId) -> Vec<StateTransition>;
+    }
+}
@@ -0,0 +1,174 @@
+use codec::Codec;
+use jsonrpc_core::{Error, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use pallet_geode::{Geode, GeodeState, StateTransition};
+pub use pallet_geode_rpc_runtime_api::GeodeRuntimeApi;
+use sp_api::BlockId;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::Block as BlockT;
+use sp_runtime::traits::MaybeDisplay;
+use std::sync::Arc;
+
+const RUNTIME_ERROR: i64 = 1;
+
+#[rpc]
+pub trait GeodeApi<BlockHash, AccountId, Hash> {
+    /// Return geodes in registered state.
+    #[rpc(name = "geode_registeredGeodes")]
+    fn registered_geodes(&self, at: Option<BlockHash>) -> Result<Vec<Geode<AccountId, Hash>>>;
+
+    /// Return geodes in attested state.
+    #[rpc(name = "geode_attestedGeodes")]
+    fn attested_geodes(&self, at: Option<BlockHash>) -> Result<Vec<Geode<AccountId, Hash>>>;
+
+    /// Return geodes an attestor is attesting.
+    #[rpc(name = "geode_attestorAttestedGeodes")]
+    fn attestor_attested_geodes(
+        &self,
+        attestor: AccountId,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<Geode<AccountId, Hash>>>;
+
+    /// Return attested geodes whose number capabilities are at least the required values.
+    #[rpc(name = "geode_attestedGeodesWith")]
+    fn attested_geodes_with(
+        &self,
+        requirements: Vec<(Vec<u8>, u64)>,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<Geode<AccountId, Hash>>>;
+
+    /// Return geodes owned by a provider.
+    #[rpc(name = "geode_geodesOfProvider")]
+    fn geodes_of_provider(
+        &self,
+        provider: AccountId,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<Geode<AccountId, Hash>>>;
+
+    /// Return the state of a geode.
+    #[rpc(name = "geode_state")]
+    fn geode_state(&self, geode: AccountId, at: Option<BlockHash>) -> Result<Option<GeodeState>>;
+
+    /// Return the latest state transitions of a geode, oldest first.
+    #[rpc(name = "geode_history")]
+    fn geode_history(
+        &self,
+        geode: AccountId,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<StateTransition>>;
+}
+
+pub struct GeodeClient<C, P> {
+    client: Arc<C>,
+    _marker: std::marker::PhantomData<P>,
+}
+
+impl<C, P> GeodeClient<C, P> {
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            _marker: Default::default(),
+        }
+    }
+}
+
+fn runtime_error(message: &str, e: impl std::fmt::Debug) -> Error {
+    Error {
+        code: ErrorCode::ServerError(RUNTIME_ERROR),
+        message: message.into(),
+        data: Some(format!("{:?}", e).into()),
+    }
+}
+
+impl<C, Block, AccountId, Hash> GeodeApi<<Block as BlockT>::Hash, AccountId, Hash>
+    for GeodeClient<C, Block>
+where
+    Block: BlockT,
+    C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+    C::Api: GeodeRuntimeApi<Block, AccountId, Hash>,
+    AccountId: Codec + MaybeDisplay,
+    Hash: Codec,
+{
+    fn registered_geodes(
+        &self,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<Geode<AccountId, Hash>>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.registered_geodes(&at)
+            .map_err(|e| runtime_error("Runtime unable to get registered geodes.", e))
+    }
+
+    fn attested_geodes(
+        &self,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<Geode<AccountId, Hash>>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.attested_geodes(&at)
+            .map_err(|e| runtime_error("Runtime unable to get attested geodes.", e))
+    }
+
+    fn attestor_attested_geodes(
+        &self,
+        attestor: AccountId,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<Geode<AccountId, Hash>>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.attestor_attested_geodes(&at, attestor)
+            .map_err(|e| runtime_error("Runtime unable to get attestor attested geodes.", e))
+    }
+
+    fn attested_geodes_with(
+        &self,
+        requirements: Vec<(Vec<u8>, u64)>,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<Geode<AccountId, Hash>>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.attested_geodes_with(&at, requirements)
+            .map_err(|e| runtime_error("Runtime unable to get attested geodes.", e))
+    }
+
+    fn geodes_of_provider(
+        &self,
+        provider: AccountId,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<Geode<AccountId, Hash>>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.geodes_of_provider(&at, provider)
+            .map_err(|e| runtime_error("Runtime unable to get geodes of provider.", e))
+    }
+
+    fn geode_state(
+        &self,
+        geode: AccountId,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<GeodeState>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.geode_state(&at, geode)
+            .map_err(|e| runtime_error("Runtime unable to get geode state.", e))
+    }
+
+    fn geode_history(
+        &self,
+        geode: AccountId,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<StateTransition>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.geode_history(&at, geode)
+            .map_err(|e| runtime_error("Runtime unable to get geode history.", e))
+    }
+}
@@ -0,0 +1,58 @@
+use codec::{Decode, Encode};
+use sp_runtime::RuntimeDebug;
+use sp_std::prelude::*;
+
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+/// Number of CPU cores.
+pub const CPU_CORES: &[u8] = b"cpu_cores";
+/// Memory size in MiB.
+pub const MEMORY: &[u8] = b"memory";
+/// Enclave page cache size in MiB.
+pub const EPC_SIZE: &[u8] = b"epc_size";
+/// ISO 3166-1 alpha-2 region code.
+pub const REGION: &[u8] = b"region";
+/// Trusted execution environment, e.g. `sgx` or `sev-snp`.
+pub const TEE_TYPE: &[u8] = b"tee_type";
+
+/// Max length of a text capability value.
+pub const MAX_TEXT_LEN: usize = 64;
+
+/// Type of the value a capability key carries.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug)]
+pub enum CapabilityType {
+    /// SCALE encoded `u64`, indexed for minimum queries.
+    Number,
+    /// Non-empty bytes of at most `MAX_TEXT_LEN`.
+    Text,
+}
+
+/// A typed capability value.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub enum CapabilityValue {
+    Number(u64),
+    Text(Vec<u8>),
+}
+
+impl CapabilityType {
+    /// Parse a raw prop value, `None` if it doesn't fit the type.
+    pub fn parse(&self, raw: &[u8]) -> Option<CapabilityValue> {
+        match self {
+            CapabilityType::Number => {
+                if raw.len() != 8 {
+                    return None;
+                }
+                u64::decode(&mut &raw[..]).ok().map(CapabilityValue::Number)
+            }
+            CapabilityType::Text => {
+                if raw.is_empty() || raw.len() > MAX_TEXT_LEN {
+                    return None;
+                }
+                Some(CapabilityValue::Text(raw.to_vec()))
+            }
+        }
+    }
+}
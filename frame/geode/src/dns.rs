@@ -0,0 +1,76 @@
+use codec::{Decode, Encode};
+use primitives::BlockNumber;
+use sp_runtime::RuntimeDebug;
+use sp_std::{
+    collections::{btree_map::BTreeMap, btree_set::BTreeSet},
+    prelude::*,
+};
+
+/// Label the challenge TXT record is published under, e.g. `_automata-challenge.example.com`.
+pub const CHALLENGE_LABEL: &[u8] = b"_automata-challenge";
+
+/// An open DNS ownership challenge of a geode.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct DnsChallenge<AccountId: Ord, Hash> {
+    /// Name being verified.
+    pub dns: Vec<u8>,
+    /// Token the provider has to publish.
+    pub token: Hash,
+    /// Block the challenge was issued at.
+    pub issued: BlockNumber,
+    /// Attestors which found the token.
+    pub approvals: BTreeSet<AccountId>,
+    /// Attestors which didn't.
+    pub rejections: BTreeSet<AccountId>,
+}
+
+/// Name the challenge TXT record of `dns` lives at.
+pub fn challenge_name(dns: &[u8]) -> Vec<u8> {
+    let mut name = CHALLENGE_LABEL.to_vec();
+    name.push(b'.');
+    name.extend_from_slice(dns);
+    name
+}
+
+/// Value of the challenge TXT record, the hex encoded token.
+pub fn challenge_value(token: &[u8]) -> Vec<u8> {
+    let mut value = Vec::with_capacity(token.len() * 2);
+    for &b in token.iter() {
+        for n in [b >> 4, b & 0x0f].iter() {
+            value.push(if *n < 10 { b'0' + n } else { b'a' - 10 + n });
+        }
+    }
+    value
+}
+
+/// Source of TXT records, a real resolver in attestors and offchain workers.
+pub trait TxtResolver {
+    fn txt_records(&self, name: &[u8]) -> Vec<Vec<u8>>;
+}
+
+/// Whether the challenge `token` is published for `dns`.
+pub fn check_challenge<R: TxtResolver>(resolver: &R, dns: &[u8], token: &[u8]) -> bool {
+    let expected = challenge_value(token);
+    resolver
+        .txt_records(&challenge_name(dns))
+        .iter()
+        .any(|record| *record == expected)
+}
+
+/// Resolver answering from an in-memory zone, stands in for a real one locally.
+#[derive(Default)]
+pub struct LocalResolver {
+    records: BTreeMap<Vec<u8>, Vec<Vec<u8>>>,
+}
+
+impl LocalResolver {
+    pub fn insert_txt(&mut self, name: Vec<u8>, value: Vec<u8>) {
+        self.records.entry(name).or_default().push(value);
+    }
+}
+
+impl TxtResolver for LocalResolver {
+    fn txt_records(&self, name: &[u8]) -> Vec<Vec<u8>> {
+        self.records.get(name).cloned().unwrap_or_default()
+    }
+}
@@ -0,0 +1,171 @@
+use codec::{Decode, Encode};
+use sp_runtime::RuntimeDebug;
+use sp_std::prelude::*;
+
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+/// Max number of endpoints of a geode.
+pub const MAX_ENDPOINTS: usize = 8;
+/// Max length of a DNS name.
+pub const MAX_DNS_LEN: usize = 253;
+/// Max length of a DNS label.
+pub const MAX_LABEL_LEN: usize = 63;
+/// Max length of a textual multiaddr.
+pub const MAX_MULTIADDR_LEN: usize = 255;
+
+/// Protocol an endpoint speaks.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "lowercase"))]
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+    Http,
+    Https,
+    Ws,
+    Wss,
+}
+
+/// Network address of an endpoint.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "lowercase"))]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub enum Address {
+    /// Serialised as dotted decimal, e.g. `"10.0.0.1"`.
+    Ipv4(#[cfg_attr(feature = "std", serde(with = "serde_ipv4"))] [u8; 4]),
+    /// Serialised in RFC 5952 form, e.g. `"2001:db8::1"`.
+    Ipv6(#[cfg_attr(feature = "std", serde(with = "serde_ipv6"))] [u8; 16]),
+    /// Textual multiaddr, e.g. `"/ip4/10.0.0.1/tcp/30333"`.
+    Multiaddr(#[cfg_attr(feature = "std", serde(with = "serde_text"))] Vec<u8>),
+    /// DNS name, e.g. `"geode.example.com"`.
+    Dns(#[cfg_attr(feature = "std", serde(with = "serde_text"))] Vec<u8>),
+}
+
+/// A network endpoint of a geode.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct Endpoint {
+    pub address: Address,
+    /// Port, `None` for the protocol default. Multiaddrs carry their own.
+    pub port: Option<u16>,
+    pub protocol: Protocol,
+}
+
+impl Endpoint {
+    pub fn is_valid(&self) -> bool {
+        if self.port == Some(0) {
+            return false;
+        }
+        match &self.address {
+            Address::Ipv4(ip) => *ip != [0u8; 4],
+            Address::Ipv6(ip) => *ip != [0u8; 16],
+            Address::Multiaddr(addr) => self.port.is_none() && is_valid_multiaddr(addr),
+            Address::Dns(name) => is_valid_dns_name(name),
+        }
+    }
+}
+
+/// Letters, digits and hyphens in dot separated labels, no label starting or ending with a
+/// hyphen.
+pub fn is_valid_dns_name(name: &[u8]) -> bool {
+    if name.is_empty() || name.len() > MAX_DNS_LEN {
+        return false;
+    }
+    name.split(|c| *c == b'.').all(|label| {
+        !label.is_empty()
+            && label.len() <= MAX_LABEL_LEN
+            && label.first() != Some(&b'-')
+            && label.last() != Some(&b'-')
+            && label
+                .iter()
+                .all(|c| c.is_ascii_alphanumeric() || *c == b'-')
+    })
+}
+
+/// Slash separated protocol/value pairs of printable ascii.
+fn is_valid_multiaddr(addr: &[u8]) -> bool {
+    if addr.len() > MAX_MULTIADDR_LEN || addr.first() != Some(&b'/') {
+        return false;
+    }
+    let parts = addr[1..].split(|c| *c == b'/').collect::<Vec<_>>();
+    parts.len() >= 2
+        && parts
+            .iter()
+            .all(|part| !part.is_empty() && part.iter().all(|c| c.is_ascii_graphic()))
+}
+
+/// The DNS name a geode is reached under, if any.
+pub fn dns_name(endpoints: &[Endpoint]) -> Option<&[u8]> {
+    endpoints
+        .iter()
+        .find_map(|endpoint| match &endpoint.address {
+            Address::Dns(name) => Some(&name[..]),
+            _ => None,
+        })
+}
+
+/// Every endpoint is valid and all DNS endpoints share one name, which is what attestors
+/// verify.
+pub fn is_valid_list(endpoints: &[Endpoint]) -> bool {
+    let name = dns_name(endpoints);
+    endpoints.iter().all(|endpoint| {
+        endpoint.is_valid()
+            && match &endpoint.address {
+                Address::Dns(other) => Some(&other[..]) == name,
+                _ => true,
+            }
+    })
+}
+
+#[cfg(feature = "std")]
+mod serde_ipv4 {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::net::Ipv4Addr;
+
+    pub fn serialize<S: Serializer>(ip: &[u8; 4], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(&Ipv4Addr::from(*ip))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<[u8; 4], D::Error> {
+        let text = String::deserialize(d)?;
+        text.parse::<Ipv4Addr>()
+            .map(|ip| ip.octets())
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(feature = "std")]
+mod serde_ipv6 {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::net::Ipv6Addr;
+
+    pub fn serialize<S: Serializer>(ip: &[u8; 16], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(&Ipv6Addr::from(*ip))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<[u8; 16], D::Error> {
+        let text = String::deserialize(d)?;
+        text.parse::<Ipv6Addr>()
+            .map(|ip| ip.octets())
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(feature = "std")]
+mod serde_text {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(text: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&String::from_utf8_lossy(text))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(d)?;
+        if !text.is_ascii() {
+            return Err(D::Error::custom("non-ascii address"));
+        }
+        Ok(text.into_bytes())
+    }
+}
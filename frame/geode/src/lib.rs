@@ -2,6 +2,11 @@
 
 pub use pallet::*;
 
+pub mod capability;
+pub mod dns;
+pub mod endpoint;
+pub mod migrations;
+
 #[cfg(test)]
 mod mock;
 
@@ -10,15 +15,26 @@ mod tests;
 
 #[frame_support::pallet]
 pub mod pallet {
-    use automata_traits::GeodeAccounting;
+    use crate::capability::{CapabilityType, CapabilityValue};
+    use crate::dns::DnsChallenge;
+    use crate::endpoint::{self, Address, Endpoint};
+    use automata_traits::{GeodeAccounting, Penalty};
     use codec::{Decode, Encode};
     use core::convert::TryInto;
     use frame_support::ensure;
     use frame_support::pallet_prelude::*;
+    use frame_support::traits::StorageVersion;
+    use frame_support::transactional;
     use frame_system::pallet_prelude::*;
     use primitives::BlockNumber;
-    use sp_runtime::{RuntimeDebug, SaturatedConversion};
-    use sp_std::{collections::btree_map::BTreeMap, prelude::*};
+    use sp_runtime::{
+        traits::{AtLeast32BitUnsigned, Hash as HashT, Saturating, Zero},
+        Percent, RuntimeDebug, SaturatedConversion,
+    };
+    use sp_std::{
+        collections::{btree_map::BTreeMap, btree_set::BTreeSet},
+        prelude::*,
+    };
 
     #[cfg(feature = "std")]
     use serde::{Deserialize, Serialize};
@@ -41,6 +57,39 @@ pub mod pallet {
         Degraded,
         /// Not available
         Null,
+        /// Briefly down for maintenance, keeping its attestors and promise
+        Maintenance,
+    }
+
+    impl GeodeState {
+        /// Whether the state machine allows moving from this state to `to`.
+        pub fn can_transit_to(&self, to: &GeodeState) -> bool {
+            use GeodeState::*;
+            match self {
+                Null => matches!(to, Registered),
+                Registered => matches!(to, Attested | Unknown | Offline | Null),
+                Attested => matches!(
+                    to,
+                    Instantiated | Registered | Unknown | Offline | Null | Maintenance
+                ),
+                Instantiated => matches!(to, Degraded | Attested | Unknown | Offline),
+                Degraded => matches!(to, Instantiated | Registered | Unknown | Offline),
+                Unknown => matches!(to, Null | Registered | Attested),
+                Offline => matches!(to, Registered | Null),
+                Maintenance => matches!(to, Attested | Registered | Unknown | Offline),
+            }
+        }
+
+        /// Whether a geode in this state serves an order.
+        pub fn has_order(&self) -> bool {
+            matches!(self, GeodeState::Instantiated | GeodeState::Degraded)
+        }
+
+        /// Whether a geode in this state can change provider. A geode in `Unknown` may still
+        /// get slashed.
+        pub fn can_transfer(&self) -> bool {
+            !matches!(self, GeodeState::Unknown)
+        }
     }
 
     impl Default for GeodeState {
@@ -49,6 +98,47 @@ pub mod pallet {
         }
     }
 
+    /// What triggered a geode state transition
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug)]
+    pub enum TransitionCause {
+        /// Provider registered, removed or took the geode offline.
+        ProviderAction,
+        /// Attestors attested the geode.
+        Attestation,
+        /// Attestors left and the geode lacks attestation.
+        AttestorExit,
+        /// A misconduct report against the geode got approved.
+        Report,
+        /// A deadline passed.
+        Timeout,
+        /// A service order got dispatched to or released from the geode.
+        Service,
+        /// Governance changed the attestation requirement.
+        Governance,
+        /// A dispute against the slash of the geode got upheld.
+        Dispute,
+    }
+
+    /// Handler for geode state transitions, e.g. to schedule the deadline of the new state.
+    pub trait OnGeodeStateChange<AccountId> {
+        fn on_geode_state_change(geode: &AccountId, to: &GeodeState, when: BlockNumber);
+    }
+
+    impl<AccountId> OnGeodeStateChange<AccountId> for () {
+        fn on_geode_state_change(_: &AccountId, _: &GeodeState, _: BlockNumber) {}
+    }
+
+    /// A recorded geode state transition
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+    pub struct StateTransition {
+        pub from: GeodeState,
+        pub to: GeodeState,
+        pub block: BlockNumber,
+        pub cause: TransitionCause,
+    }
+
     /// The geode struct shows its status
     #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
     #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, Default)]
@@ -59,10 +149,8 @@ pub mod pallet {
         pub provider: AccountId,
         /// Assigned order hash
         pub order: Option<(Hash, Option<BlockNumber>)>,
-        /// Geode's public ip.
-        pub ip: Vec<u8>,
-        /// Geode's dns.
-        pub dns: Vec<u8>,
+        /// Network endpoints the geode serves on.
+        pub endpoints: Vec<Endpoint>,
         /// Geodes' properties
         pub props: BTreeMap<Vec<u8>, Vec<u8>>,
         /// Current state of the geode
@@ -73,11 +161,15 @@ pub mod pallet {
 
     pub type GeodeOf<T> =
         Geode<<T as frame_system::Config>::AccountId, <T as frame_system::Config>::Hash>;
+    pub type DnsChallengeOf<T> =
+        DnsChallenge<<T as frame_system::Config>::AccountId, <T as frame_system::Config>::Hash>;
 
     #[pallet::config]
     pub trait Config: frame_system::Config + pallet_attestor::Config {
         type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
-        type GeodeAccounting: GeodeAccounting<AccountId = Self::AccountId>;
+        /// Balance of geode stakes.
+        type Balance: Parameter + Member + AtLeast32BitUnsigned + Default + Copy;
+        type GeodeAccounting: GeodeAccounting<AccountId = Self::AccountId, Balance = Self::Balance>;
 
         #[pallet::constant]
         type DispatchConfirmationTimeout: Get<BlockNumber>;
@@ -87,10 +179,67 @@ pub mod pallet {
 
         #[pallet::constant]
         type AttestationExpiryBlockNumber: Get<BlockNumber>;
+
+        /// Maximum number of items in a batch call.
+        #[pallet::constant]
+        type MaxBatchSize: Get<u32>;
+
+        /// Origin allowed to manage the capability schema.
+        type CapabilityAdminOrigin: EnsureOrigin<Self::Origin>;
+
+        /// Number of latest state transitions kept for each geode.
+        #[pallet::constant]
+        type MaxHistoryLength: Get<u32>;
+
+        /// Share of a geode's attestors needed to settle a DNS challenge.
+        #[pallet::constant]
+        type DnsApprovalRatio: Get<Percent>;
+
+        /// Number of blocks attestors have to answer a DNS challenge.
+        #[pallet::constant]
+        type DnsChallengeExpiry: Get<BlockNumber>;
+
+        /// Handler for state transitions of geodes.
+        type OnGeodeStateChange: OnGeodeStateChange<Self::AccountId>;
     }
 
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_runtime_upgrade() -> Weight {
+            crate::migrations::v1::migrate::<T>()
+                + crate::migrations::v2::migrate::<T>()
+                + crate::migrations::v3::migrate::<T>()
+                + crate::migrations::v4::migrate::<T>()
+                + crate::migrations::v5::migrate::<T>()
+        }
+
+        /// Drop the DNS challenges which weren't answered in time.
+        fn on_initialize(block_number: T::BlockNumber) -> Weight {
+            let mut weight = T::DbWeight::get().reads_writes(1, 1);
+            if let Ok(now) = TryInto::<BlockNumber>::try_into(block_number) {
+                for geode in <DnsChallengeExpiries<T>>::take(now) {
+                    weight = weight.saturating_add(T::DbWeight::get().reads(1));
+                    // the challenge may have been answered or reissued since
+                    match <DnsChallenges<T>>::get(&geode) {
+                        Some(challenge)
+                            if challenge.issued + T::DnsChallengeExpiry::get() + 1 == now =>
+                        {
+                            <DnsChallenges<T>>::remove(&geode);
+                            weight = weight.saturating_add(T::DbWeight::get().writes(1));
+                            Self::deposit_event(Event::DnsChallengeDropped(geode));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            weight
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade() -> Result<(), &'static str> {
+            Self::check_invariants()
+        }
+
         // /// 1. At every block, check if any promise already expired
         // fn on_initialize(block_number: T::BlockNumber) -> Weight {
         //     if let Ok(now) = TryInto::<BlockNumber>::try_into(block_number) {
@@ -118,7 +267,7 @@ pub mod pallet {
 
     #[pallet::event]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
-    #[pallet::metadata(T::AccountId = "AccountId")]
+    #[pallet::metadata(T::AccountId = "AccountId", T::Balance = "Balance")]
     pub enum Event<T: Config> {
         /// Provider register geode. \[provider_id, geode_id\]
         GeodeRegister(T::AccountId, T::AccountId),
@@ -128,8 +277,8 @@ pub mod pallet {
         GeodeUpdate(T::AccountId),
         /// Geode's props updated. \[geode_id\]
         PropsUpdate(T::AccountId),
-        /// Geode's props updated. \[geode_id\]
-        DnsUpdate(T::AccountId),
+        /// Geode's endpoints updated. \[geode_id\]
+        EndpointsUpdate(T::AccountId),
         /// Event documentation should end with an array that provides descriptive names for event
         /// parameters. [something, who]
         SomethingStored(u32, T::AccountId),
@@ -137,6 +286,38 @@ pub mod pallet {
         GeodeStateUpdate(T::AccountId, GeodeState),
         /// Geode's promise updated
         GeodePromiseUpdate(T::AccountId, BlockNumber),
+        /// Stake bonded for a geode. \[geode_id, amount\]
+        GeodeStaked(T::AccountId, T::Balance),
+        /// Geode's stake started unbonding. \[geode_id, amount\]
+        GeodeStakeReleased(T::AccountId, T::Balance),
+        /// Geode's stake slashed. \[geode_id, amount\]
+        GeodeStakeSlashed(T::AccountId, T::Balance),
+        /// An item of a batch call failed. \[geode_id, error\]
+        BatchItemFailed(T::AccountId, DispatchError),
+        /// A batch call finished. \[succeeded, failed\]
+        BatchCompleted(u32, u32),
+        /// Provider offered a geode to another provider. \[geode_id, from, to\]
+        GeodeTransferOffered(T::AccountId, T::AccountId, T::AccountId),
+        /// A pending geode transfer got cancelled. \[geode_id\]
+        GeodeTransferCancelled(T::AccountId),
+        /// Geode moved to a new provider. \[geode_id, from, to\]
+        GeodeTransferred(T::AccountId, T::AccountId, T::AccountId),
+        /// Capability key added to the schema. \[key, type\]
+        CapabilityRegistered(Vec<u8>, CapabilityType),
+        /// Capability key removed from the schema. \[key\]
+        CapabilityRemoved(Vec<u8>),
+        /// Provider set the per-block price of a geode. \[geode_id, price\]
+        GeodePriceSet(T::AccountId, T::Balance),
+        /// Geode has to publish a token to prove it controls a name. \[geode_id, dns, token\]
+        DnsChallengeIssued(T::AccountId, Vec<u8>, T::Hash),
+        /// Attestor checked a DNS challenge. \[attestor_id, geode_id, found\]
+        DnsResultSubmitted(T::AccountId, T::AccountId, bool),
+        /// Attestors confirmed the geode controls the name. \[geode_id, dns\]
+        DnsVerified(T::AccountId, Vec<u8>),
+        /// Attestors couldn't find the challenge token. \[geode_id\]
+        DnsRejected(T::AccountId),
+        /// DNS challenge wasn't answered in time. \[geode_id\]
+        DnsChallengeDropped(T::AccountId),
     }
 
     #[pallet::error]
@@ -159,10 +340,44 @@ pub mod pallet {
         InvalidPromise,
         /// Invalid state transition
         InvalidTransition,
+        /// Too many items in a batch call.
+        BatchTooLarge,
+        /// No transfer of the geode is offered to you.
+        NoPendingTransfer,
+        /// The geode awaits the outcome of a misconduct penalty.
+        GeodeUnderPenalty,
+        /// Prop value doesn't match the type of its capability.
+        InvalidCapability,
+        /// Capability key isn't in the schema.
+        UnknownCapability,
+        /// More geodes are registered than the given geode count.
+        InvalidGeodeCount,
+        /// No open DNS challenge with this token.
+        NoDnsChallenge,
+        /// DNS challenge wasn't answered in time.
+        DnsChallengeExpired,
+        /// Attestor isn't attesting the geode.
+        NotAttestingGeode,
+        /// Attestor already answered the DNS challenge.
+        DuplicateDnsResult,
+        /// Endpoint is malformed, or DNS endpoints use different names.
+        InvalidEndpoint,
+        /// More endpoints than `endpoint::MAX_ENDPOINTS`.
+        TooManyEndpoints,
+        /// Geode has no DNS name.
+        NoDnsName,
+        /// DNS name of the geode is already verified.
+        DnsAlreadyVerified,
+        /// DNS challenge of the geode is still open.
+        DnsChallengePending,
     }
 
+    /// The current storage version.
+    pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(5);
+
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(_);
 
     #[pallet::storage]
@@ -170,40 +385,95 @@ pub mod pallet {
     pub type Geodes<T: Config> =
         StorageMap<_, Blake2_128Concat, T::AccountId, GeodeOf<T>, ValueQuery>;
 
+    /// Geodes owned by each provider.
     #[pallet::storage]
-    #[pallet::getter(fn promised_geodes)]
-    pub type PromisedGeodes<T: Config> =
-        StorageMap<_, Blake2_128Concat, BlockNumber, Vec<T::AccountId>, ValueQuery>;
+    #[pallet::getter(fn provider_geodes)]
+    pub type ProviderGeodes<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, BTreeSet<T::AccountId>, ValueQuery>;
+
+    /// Schema of well-known geode props, props outside of it stay free-form.
+    #[pallet::storage]
+    #[pallet::getter(fn capability)]
+    pub type Capabilities<T: Config> =
+        StorageMap<_, Blake2_128Concat, Vec<u8>, CapabilityType, OptionQuery>;
+
+    /// Values of `Number` capabilities by key and geode.
+    #[pallet::storage]
+    #[pallet::getter(fn capability_value)]
+    pub type CapabilityIndex<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        Vec<u8>,
+        Blake2_128Concat,
+        T::AccountId,
+        u64,
+        OptionQuery,
+    >;
+
+    /// Latest state transitions of each geode, oldest first. Kept after the geode is removed.
+    #[pallet::storage]
+    #[pallet::getter(fn geode_history)]
+    pub type GeodeHistory<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, Vec<StateTransition>, ValueQuery>;
+
+    /// Geodes offered to a new provider, waiting for the new provider to accept.
+    #[pallet::storage]
+    #[pallet::getter(fn pending_transfer)]
+    pub type PendingTransfers<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, T::AccountId, OptionQuery>;
 
+    /// Stake bonded by the provider for each geode.
     #[pallet::storage]
-    #[pallet::getter(fn registered_geode_ids)]
-    pub type RegisteredGeodes<T: Config> =
-        StorageMap<_, Blake2_128Concat, T::AccountId, BlockNumber, ValueQuery>;
+    #[pallet::getter(fn geode_stake)]
+    pub type GeodeStakes<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, T::Balance, ValueQuery>;
 
+    /// Misconduct penalties set aside from geode stakes until settled or restored.
     #[pallet::storage]
-    #[pallet::getter(fn attested_geodes_ids)]
-    pub type AttestedGeodes<T: Config> =
-        StorageMap<_, Blake2_128Concat, T::AccountId, BlockNumber, ValueQuery>;
+    #[pallet::getter(fn held_penalty)]
+    pub type HeldPenalties<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, T::Balance, ValueQuery>;
 
+    /// Open DNS ownership challenges.
     #[pallet::storage]
-    #[pallet::getter(fn instantiated_geodes_ids)]
-    pub type InstantiatedGeodes<T: Config> =
-        StorageMap<_, Blake2_128Concat, T::AccountId, BlockNumber, ValueQuery>;
+    #[pallet::getter(fn dns_challenge)]
+    pub type DnsChallenges<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, DnsChallengeOf<T>, OptionQuery>;
 
+    /// Geodes whose DNS challenge expires at a block.
     #[pallet::storage]
-    #[pallet::getter(fn degraded_instantiated_geodes_ids)]
-    pub type DegradedGeodes<T: Config> =
-        StorageMap<_, Blake2_128Concat, T::AccountId, BlockNumber, ValueQuery>;
+    pub type DnsChallengeExpiries<T: Config> =
+        StorageMap<_, Blake2_128Concat, BlockNumber, Vec<T::AccountId>, ValueQuery>;
 
+    /// Name attestors verified a geode controls.
     #[pallet::storage]
-    #[pallet::getter(fn offline_geodes_ids)]
-    pub type OfflineGeodes<T: Config> =
-        StorageMap<_, Blake2_128Concat, T::AccountId, BlockNumber, ValueQuery>;
+    #[pallet::getter(fn verified_dns)]
+    pub type VerifiedDns<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, Vec<u8>, OptionQuery>;
 
+    /// Per-block price the provider asks for a geode, geodes without one are free.
     #[pallet::storage]
-    #[pallet::getter(fn unknown_geodes_ids)]
-    pub type UnknownGeodes<T: Config> =
-        StorageMap<_, Blake2_128Concat, T::AccountId, BlockNumber, ValueQuery>;
+    #[pallet::getter(fn geode_price)]
+    pub type GeodePrices<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, T::Balance, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn promised_geodes)]
+    pub type PromisedGeodes<T: Config> =
+        StorageMap<_, Blake2_128Concat, BlockNumber, Vec<T::AccountId>, ValueQuery>;
+
+    /// Geodes by state, with the block they entered it.
+    #[pallet::storage]
+    #[pallet::getter(fn geode_state_since)]
+    pub type GeodeStates<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        GeodeState,
+        Blake2_128Concat,
+        T::AccountId,
+        BlockNumber,
+        ValueQuery,
+    >;
 
     #[pallet::call]
     impl<T: Config> Pallet<T> {
@@ -215,60 +485,207 @@ pub mod pallet {
             geode_record: GeodeOf<T>,
         ) -> DispatchResultWithPostInfo {
             let who = ensure_signed(origin)?;
+            Self::do_register_geode(who, geode_record)
+        }
 
-            if <Geodes<T>>::contains_key(&geode_record.id) {
-                let mut geode = <Geodes<T>>::get(geode_record.id);
-                ensure!(geode.provider == who, Error::<T>::NoRight);
-                ensure!(
-                    geode.state == GeodeState::Offline,
-                    Error::<T>::InvalidGeodeState
-                );
-                geode.order = None;
-                match Self::transit_state(&geode, GeodeState::Registered) {
-                    true => {
-                        return Ok(().into());
-                    }
-                    false => {
-                        return Err(Error::<T>::InvalidTransition.into());
-                    }
-                }
-            } else {
-                let mut geode_record = geode_record;
-                let geode = geode_record.id.clone();
-
-                let block_number =
-                    <frame_system::Module<T>>::block_number().saturated_into::<BlockNumber>();
-                geode_record.state = GeodeState::Registered;
-                geode_record.provider = who.clone();
-                T::GeodeAccounting::geode_staking(who.clone())?;
+        /// Called by provider to register many geodes at once, failed geodes are reported by
+        /// `BatchItemFailed`.
+        #[pallet::weight(0)]
+        pub fn provider_register_geodes(
+            origin: OriginFor<T>,
+            geode_records: Vec<GeodeOf<T>>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            Self::do_batch(
+                geode_records,
+                |record| record.id.clone(),
+                |record| Self::do_register_geode(who.clone(), record),
+            )
+        }
 
-                <Geodes<T>>::insert(&geode, &geode_record);
-                <RegisteredGeodes<T>>::insert(&geode, &block_number);
+        /// Called by provider to remove geode .
+        #[pallet::weight(0)]
+        pub fn geode_remove(
+            origin: OriginFor<T>,
+            geode: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            ensure!(<Geodes<T>>::contains_key(&geode), Error::<T>::InvalidGeode);
+            let geode = <Geodes<T>>::get(geode);
+            ensure!(geode.provider == who, Error::<T>::NoRight);
 
-                Self::deposit_event(Event::GeodeRegister(who, geode));
+            match Self::transit_state(&geode, GeodeState::Null, TransitionCause::ProviderAction) {
+                true => Ok(().into()),
+                false => Err(Error::<T>::InvalidTransition.into()),
             }
+        }
 
+        /// Called by provider to bond more stake for a geode on top of the minimum.
+        #[pallet::weight(0)]
+        pub fn provider_stake_geode(
+            origin: OriginFor<T>,
+            geode: T::AccountId,
+            value: T::Balance,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            ensure!(<Geodes<T>>::contains_key(&geode), Error::<T>::InvalidGeode);
+            ensure!(
+                <Geodes<T>>::get(&geode).provider == who,
+                Error::<T>::NoRight
+            );
+            ensure!(!value.is_zero(), Error::<T>::InvalidInput);
+            Self::bond_stake(&who, &geode, value)?;
             Ok(().into())
         }
 
-        /// Called by provider to remove geode .
+        /// Called by provider to set the per-block price of a geode.
         #[pallet::weight(0)]
-        pub fn geode_remove(
+        pub fn provider_set_geode_price(
             origin: OriginFor<T>,
             geode: T::AccountId,
+            price: T::Balance,
         ) -> DispatchResultWithPostInfo {
             let who = ensure_signed(origin)?;
             ensure!(<Geodes<T>>::contains_key(&geode), Error::<T>::InvalidGeode);
-            let geode = <Geodes<T>>::get(geode);
-            ensure!(geode.provider == who, Error::<T>::NoRight);
+            ensure!(
+                <Geodes<T>>::get(&geode).provider == who,
+                Error::<T>::NoRight
+            );
+            <GeodePrices<T>>::insert(&geode, price);
+            Self::deposit_event(Event::GeodePriceSet(geode, price));
+            Ok(().into())
+        }
+
+        /// Called by provider to offer a geode to another provider, replacing any earlier offer.
+        #[pallet::weight(0)]
+        pub fn offer_geode_transfer(
+            origin: OriginFor<T>,
+            geode: T::AccountId,
+            to: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            ensure!(<Geodes<T>>::contains_key(&geode), Error::<T>::InvalidGeode);
+            ensure!(
+                <Geodes<T>>::get(&geode).provider == who,
+                Error::<T>::NoRight
+            );
+            ensure!(to != who, Error::<T>::InvalidInput);
+            <PendingTransfers<T>>::insert(&geode, &to);
+            Self::deposit_event(Event::GeodeTransferOffered(geode, who, to));
+            Ok(().into())
+        }
+
+        /// Called by provider to withdraw a pending transfer offer.
+        #[pallet::weight(0)]
+        pub fn cancel_geode_transfer(
+            origin: OriginFor<T>,
+            geode: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            ensure!(
+                <PendingTransfers<T>>::contains_key(&geode),
+                Error::<T>::NoPendingTransfer
+            );
+            ensure!(
+                <Geodes<T>>::get(&geode).provider == who,
+                Error::<T>::NoRight
+            );
+            <PendingTransfers<T>>::remove(&geode);
+            Self::deposit_event(Event::GeodeTransferCancelled(geode));
+            Ok(().into())
+        }
 
-            match Self::transit_state(&geode, GeodeState::Null) {
-                true => {
-                    T::GeodeAccounting::geode_unreserve(who)?;
-                    Ok(().into())
+        /// Called by the new provider to take over an offered geode. The new provider bonds the
+        /// geode's stake and the old provider's stake starts unbonding, the geode keeps its
+        /// state, attestors and order.
+        #[pallet::weight(0)]
+        #[transactional]
+        pub fn accept_geode_transfer(
+            origin: OriginFor<T>,
+            geode: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            ensure!(
+                <PendingTransfers<T>>::get(&geode) == Some(who.clone()),
+                Error::<T>::NoPendingTransfer
+            );
+            let mut geode_record = <Geodes<T>>::get(&geode);
+            // the penalty would be settled with the old provider
+            ensure!(
+                geode_record.state.can_transfer() && !<HeldPenalties<T>>::contains_key(&geode),
+                Error::<T>::GeodeUnderPenalty
+            );
+            let from = geode_record.provider.clone();
+
+            let stake = <GeodeStakes<T>>::get(&geode);
+            if !stake.is_zero() {
+                T::GeodeAccounting::geode_staking(who.clone(), stake)?;
+                T::GeodeAccounting::geode_unreserve(from.clone(), stake)?;
+            }
+
+            Self::remove_from_provider(&from, &geode);
+            <ProviderGeodes<T>>::mutate(&who, |geodes| geodes.insert(geode.clone()));
+            geode_record.provider = who.clone();
+            <Geodes<T>>::insert(&geode, geode_record);
+            <PendingTransfers<T>>::remove(&geode);
+
+            Self::deposit_event(Event::GeodeTransferred(geode, from, who));
+            Ok(().into())
+        }
+
+        /// Add or retype a capability key of the schema, existing geode props of the key get
+        /// indexed if they fit. `geode_count` is the number of registered geodes at most, the
+        /// weight is charged for it.
+        #[pallet::weight(T::DbWeight::get().reads_writes(
+            2 * (*geode_count as Weight) + 1,
+            2 * (*geode_count as Weight) + 1,
+        ))]
+        pub fn register_capability(
+            origin: OriginFor<T>,
+            key: Vec<u8>,
+            capability_type: CapabilityType,
+            geode_count: u32,
+        ) -> DispatchResultWithPostInfo {
+            T::CapabilityAdminOrigin::ensure_origin(origin)?;
+            ensure!(!key.is_empty(), Error::<T>::InvalidInput);
+
+            // read no more geodes than paid for
+            let mut geodes = 0u32;
+            let mut indexed = Vec::new();
+            for (id, geode) in <Geodes<T>>::iter().take(geode_count as usize + 1) {
+                geodes += 1;
+                if let Some(raw) = geode.props.get(&key) {
+                    if let Some(CapabilityValue::Number(value)) = capability_type.parse(raw) {
+                        indexed.push((id, value));
+                    }
                 }
-                false => Err(Error::<T>::InvalidTransition.into()),
             }
+            ensure!(geodes <= geode_count, Error::<T>::InvalidGeodeCount);
+
+            Self::clear_capability_index(&key);
+            <Capabilities<T>>::insert(&key, capability_type);
+            for (id, value) in indexed.iter() {
+                <CapabilityIndex<T>>::insert(&key, id, value);
+            }
+
+            Self::deposit_event(Event::CapabilityRegistered(key, capability_type));
+            Ok(().into())
+        }
+
+        /// Remove a capability key from the schema, its props become free-form.
+        #[pallet::weight(0)]
+        pub fn remove_capability(origin: OriginFor<T>, key: Vec<u8>) -> DispatchResultWithPostInfo {
+            T::CapabilityAdminOrigin::ensure_origin(origin)?;
+            ensure!(
+                <Capabilities<T>>::contains_key(&key),
+                Error::<T>::UnknownCapability
+            );
+
+            <Capabilities<T>>::remove(&key);
+            Self::clear_capability_index(&key);
+
+            Self::deposit_event(Event::CapabilityRemoved(key));
+            Ok(().into())
         }
 
         /// Called by provider to update geode properties
@@ -278,29 +695,145 @@ pub mod pallet {
             geode: T::AccountId,
             prop_name: Vec<u8>,
             prop_value: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            Self::do_update_geode_props(&who, geode, prop_name, prop_value)
+        }
+
+        /// Called by provider to update properties of many geodes at once, failed geodes are
+        /// reported by `BatchItemFailed`.
+        #[pallet::weight(0)]
+        pub fn update_geodes_props(
+            origin: OriginFor<T>,
+            updates: Vec<(T::AccountId, Vec<u8>, Vec<u8>)>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            Self::do_batch(
+                updates,
+                |(geode, _, _)| geode.clone(),
+                |(geode, prop_name, prop_value)| {
+                    Self::do_update_geode_props(&who, geode, prop_name, prop_value)
+                },
+            )
+        }
+
+        /// Called by provider to take many idle geodes offline at once, failed geodes are
+        /// reported by `BatchItemFailed`.
+        #[pallet::weight(0)]
+        pub fn provider_offline_geodes(
+            origin: OriginFor<T>,
+            geodes: Vec<T::AccountId>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            Self::do_batch(
+                geodes,
+                |geode| geode.clone(),
+                |geode| Self::do_offline_geode(&who, geode),
+            )
+        }
+
+        /// Called by provider to replace the endpoints of a geode. A new DNS name stays
+        /// unverified until attestors find the issued challenge token in its TXT record.
+        #[pallet::weight(0)]
+        pub fn update_geode_endpoints(
+            origin: OriginFor<T>,
+            geode: T::AccountId,
+            endpoints: Vec<Endpoint>,
         ) -> DispatchResultWithPostInfo {
             let who = ensure_signed(origin)?;
             let mut geode_use = <Geodes<T>>::get(&geode);
             ensure!(geode_use.provider == who, Error::<T>::NoRight);
-            geode_use.props.insert(prop_name, prop_value);
+            Self::check_endpoints(&endpoints)?;
+            let name = endpoint::dns_name(&endpoints);
+            if endpoint::dns_name(&geode_use.endpoints) != name {
+                <VerifiedDns<T>>::remove(&geode);
+                Self::issue_dns_challenge(&geode, name.unwrap_or_default());
+            }
+            geode_use.endpoints = endpoints;
             <Geodes<T>>::insert(&geode, geode_use);
-            Self::deposit_event(Event::PropsUpdate(geode));
+            Self::deposit_event(Event::EndpointsUpdate(geode));
             Ok(().into())
         }
 
-        /// Called by provider to bound dns to geode's ip.
+        /// Called by an attestor of the geode with whether it found the challenge token, see
+        /// `dns::check_challenge`. The name is verified or rejected once `DnsApprovalRatio`
+        /// of the geode's attestors agree.
         #[pallet::weight(0)]
-        pub fn update_geode_dns(
+        pub fn attestor_submit_dns_result(
             origin: OriginFor<T>,
             geode: T::AccountId,
-            dns: Vec<u8>,
+            token: T::Hash,
+            found: bool,
         ) -> DispatchResultWithPostInfo {
             let who = ensure_signed(origin)?;
-            let mut geode_use = <Geodes<T>>::get(&geode);
+            let mut challenge = match <DnsChallenges<T>>::get(&geode) {
+                Some(challenge) if challenge.token == token => challenge,
+                _ => return Err(Error::<T>::NoDnsChallenge.into()),
+            };
+            let now = <frame_system::Module<T>>::block_number().saturated_into::<BlockNumber>();
+            ensure!(
+                now <= challenge.issued + T::DnsChallengeExpiry::get(),
+                Error::<T>::DnsChallengeExpired
+            );
+            let attestors = pallet_attestor::GeodeAttestors::<T>::get(&geode);
+            ensure!(attestors.contains(&who), Error::<T>::NotAttestingGeode);
+            ensure!(
+                !challenge.approvals.contains(&who) && !challenge.rejections.contains(&who),
+                Error::<T>::DuplicateDnsResult
+            );
+
+            if found {
+                challenge.approvals.insert(who.clone());
+            } else {
+                challenge.rejections.insert(who.clone());
+            }
+            Self::deposit_event(Event::DnsResultSubmitted(who, geode.clone(), found));
+
+            let ratio = T::DnsApprovalRatio::get();
+            if Percent::from_rational_approximation(challenge.approvals.len(), attestors.len())
+                >= ratio
+            {
+                <DnsChallenges<T>>::remove(&geode);
+                <VerifiedDns<T>>::insert(&geode, &challenge.dns);
+                Self::deposit_event(Event::DnsVerified(geode, challenge.dns));
+            } else if Percent::from_rational_approximation(
+                challenge.rejections.len(),
+                attestors.len(),
+            ) >= ratio
+            {
+                <DnsChallenges<T>>::remove(&geode);
+                Self::deposit_event(Event::DnsRejected(geode));
+            } else {
+                <DnsChallenges<T>>::insert(&geode, challenge);
+            }
+            Ok(().into())
+        }
+
+        /// Called by provider to issue a new challenge for the DNS name of a geode, once the
+        /// last one expired or got rejected.
+        #[pallet::weight(0)]
+        pub fn provider_renew_dns_challenge(
+            origin: OriginFor<T>,
+            geode: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            ensure!(<Geodes<T>>::contains_key(&geode), Error::<T>::InvalidGeode);
+            let geode_use = <Geodes<T>>::get(&geode);
             ensure!(geode_use.provider == who, Error::<T>::NoRight);
-            geode_use.dns = dns;
-            <Geodes<T>>::insert(&geode, geode_use);
-            Self::deposit_event(Event::DnsUpdate(geode));
+            let name = endpoint::dns_name(&geode_use.endpoints).ok_or(Error::<T>::NoDnsName)?;
+            ensure!(
+                <VerifiedDns<T>>::get(&geode).as_deref() != Some(name),
+                Error::<T>::DnsAlreadyVerified
+            );
+            let now = <frame_system::Module<T>>::block_number().saturated_into::<BlockNumber>();
+            ensure!(
+                <DnsChallenges<T>>::get(&geode).map_or(true, |challenge| {
+                    now > challenge.issued + T::DnsChallengeExpiry::get()
+                }),
+                Error::<T>::DnsChallengePending
+            );
+
+            Self::issue_dns_challenge(&geode, name);
             Ok(().into())
         }
 
@@ -374,26 +907,70 @@ pub mod pallet {
     }
 
     impl<T: Config> Pallet<T> {
+        /// Return geodes in a state
+        pub fn geodes_in(state: GeodeState) -> Vec<GeodeOf<T>> {
+            <GeodeStates<T>>::iter_prefix(state)
+                .map(|(id, _)| Self::routable(<Geodes<T>>::get(id)))
+                .collect()
+        }
+
+        /// Hide the DNS endpoints of a geode unless attestors verified the name, so queries
+        /// never route users to an unproven name.
+        fn routable(mut geode: GeodeOf<T>) -> GeodeOf<T> {
+            if <VerifiedDns<T>>::get(&geode.id).as_deref() != endpoint::dns_name(&geode.endpoints) {
+                geode
+                    .endpoints
+                    .retain(|endpoint| !matches!(endpoint.address, Address::Dns(_)));
+            }
+            geode
+        }
+
+        fn check_endpoints(endpoints: &[Endpoint]) -> DispatchResult {
+            ensure!(
+                endpoints.len() <= endpoint::MAX_ENDPOINTS,
+                Error::<T>::TooManyEndpoints
+            );
+            ensure!(
+                endpoint::is_valid_list(endpoints),
+                Error::<T>::InvalidEndpoint
+            );
+            Ok(())
+        }
+
+        /// Open a new DNS challenge for `dns`, replacing any earlier one.
+        pub(crate) fn issue_dns_challenge(geode: &T::AccountId, dns: &[u8]) {
+            if dns.is_empty() {
+                <DnsChallenges<T>>::remove(geode);
+                return;
+            }
+            let now = <frame_system::Module<T>>::block_number().saturated_into::<BlockNumber>();
+            let token = T::Hashing::hash_of(&(geode, dns, now));
+            <DnsChallenges<T>>::insert(
+                geode,
+                DnsChallenge {
+                    dns: dns.to_vec(),
+                    token,
+                    issued: now,
+                    approvals: BTreeSet::new(),
+                    rejections: BTreeSet::new(),
+                },
+            );
+            <DnsChallengeExpiries<T>>::append(now + T::DnsChallengeExpiry::get() + 1, geode);
+            Self::deposit_event(Event::DnsChallengeIssued(
+                geode.clone(),
+                dns.to_vec(),
+                token,
+            ));
+        }
+
         /// Return geodes in registered state
         pub fn registered_geodes() -> Vec<GeodeOf<T>> {
-            let mut res = Vec::new();
-            <RegisteredGeodes<T>>::iter()
-                .map(|(id, _)| {
-                    res.push(<Geodes<T>>::get(id));
-                })
-                .all(|_| true);
-            res
+            Self::geodes_in(GeodeState::Registered)
         }
 
         /// Return geodes in attested state
         pub fn attested_geodes() -> Vec<GeodeOf<T>> {
-            let mut res = Vec::new();
-            <AttestedGeodes<T>>::iter()
-                .map(|(id, _)| {
-                    res.push(<Geodes<T>>::get(id));
-                })
-                .all(|_| true);
-            res
+            Self::geodes_in(GeodeState::Attested)
         }
 
         /// Return list geode an attestor is attesting
@@ -401,61 +978,319 @@ pub mod pallet {
             let mut res = Vec::new();
             if pallet_attestor::Attestors::<T>::contains_key(&attestor) {
                 for geode in pallet_attestor::Attestors::<T>::get(&attestor).geodes {
-                    res.push(Geodes::<T>::get(&geode));
+                    res.push(Self::routable(Geodes::<T>::get(&geode)));
                 }
             }
             res
         }
 
-        pub fn geode_state(geode: T::AccountId) -> Option<GeodeState> {
-            if <Geodes<T>>::contains_key(&geode) {
-                Some(<Geodes<T>>::get(&geode).state)
+        fn do_register_geode(
+            who: T::AccountId,
+            geode_record: GeodeOf<T>,
+        ) -> DispatchResultWithPostInfo {
+            if <Geodes<T>>::contains_key(&geode_record.id) {
+                let mut geode = <Geodes<T>>::get(geode_record.id);
+                ensure!(geode.provider == who, Error::<T>::NoRight);
+                ensure!(
+                    geode.state == GeodeState::Offline,
+                    Error::<T>::InvalidGeodeState
+                );
+                geode.order = None;
+                // top up whatever got slashed since the last registration
+                let shortfall =
+                    T::GeodeAccounting::geode_min_stake()
+                        .saturating_sub(<GeodeStakes<T>>::get(&geode.id));
+                Self::bond_stake(&who, &geode.id, shortfall)?;
+                match Self::transit_state(
+                    &geode,
+                    GeodeState::Registered,
+                    TransitionCause::ProviderAction,
+                ) {
+                    true => {
+                        return Ok(().into());
+                    }
+                    false => {
+                        return Err(Error::<T>::InvalidTransition.into());
+                    }
+                }
             } else {
-                None
+                let mut geode_record = geode_record;
+                let geode = geode_record.id.clone();
+
+                geode_record.state = GeodeState::Null;
+                geode_record.provider = who.clone();
+                for (name, value) in geode_record.props.iter() {
+                    Self::check_capability(name, value)?;
+                }
+                Self::check_endpoints(&geode_record.endpoints)?;
+                Self::bond_stake(&who, &geode, T::GeodeAccounting::geode_min_stake())?;
+
+                Self::transit_state(
+                    &geode_record,
+                    GeodeState::Registered,
+                    TransitionCause::ProviderAction,
+                );
+                Self::issue_dns_challenge(
+                    &geode,
+                    endpoint::dns_name(&geode_record.endpoints).unwrap_or_default(),
+                );
+
+                Self::deposit_event(Event::GeodeRegister(who, geode));
             }
+
+            Ok(().into())
         }
 
-        pub fn reset_degraded_block_num() {
-            let block_number =
-                <frame_system::Module<T>>::block_number().saturated_into::<BlockNumber>();
-            // reset Registered
-            let mut registered_geodes = Vec::new();
-            <RegisteredGeodes<T>>::iter()
-                .map(|(id, _)| {
-                    registered_geodes.push(id);
-                })
-                .all(|_| true);
-            for id in registered_geodes {
-                <RegisteredGeodes<T>>::insert(id, block_number);
-            }
-            // reset DeprecatedInstantiated
-            let mut degraded_instantiated_geodes = Vec::new();
-            <DegradedGeodes<T>>::iter()
-                .map(|(id, _)| {
-                    degraded_instantiated_geodes.push(id);
+        fn do_update_geode_props(
+            who: &T::AccountId,
+            geode: T::AccountId,
+            prop_name: Vec<u8>,
+            prop_value: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            let mut geode_use = <Geodes<T>>::get(&geode);
+            ensure!(geode_use.provider == *who, Error::<T>::NoRight);
+            if let Some(CapabilityValue::Number(value)) =
+                Self::check_capability(&prop_name, &prop_value)?
+            {
+                <CapabilityIndex<T>>::insert(&prop_name, &geode, value);
+            }
+            geode_use.props.insert(prop_name, prop_value);
+            <Geodes<T>>::insert(&geode, geode_use);
+            Self::deposit_event(Event::PropsUpdate(geode));
+            Ok(().into())
+        }
+
+        fn do_offline_geode(who: &T::AccountId, geode: T::AccountId) -> DispatchResultWithPostInfo {
+            ensure!(<Geodes<T>>::contains_key(&geode), Error::<T>::InvalidGeode);
+            let geode = <Geodes<T>>::get(geode);
+            ensure!(geode.provider == *who, Error::<T>::NoRight);
+            match geode.state {
+                GeodeState::Registered | GeodeState::Attested => {}
+                GeodeState::Instantiated | GeodeState::Degraded => {
+                    return Err(Error::<T>::GeodeInWork.into());
+                }
+                _ => {
+                    return Err(Error::<T>::InvalidGeodeState.into());
+                }
+            }
+            match Self::transit_state(&geode, GeodeState::Offline, TransitionCause::ProviderAction)
+            {
+                true => Ok(().into()),
+                false => Err(Error::<T>::InvalidTransition.into()),
+            }
+        }
+
+        /// Run `call` on every item, reporting failed items instead of aborting.
+        fn do_batch<I>(
+            items: Vec<I>,
+            geode_of: impl Fn(&I) -> T::AccountId,
+            call: impl Fn(I) -> DispatchResultWithPostInfo,
+        ) -> DispatchResultWithPostInfo {
+            ensure!(
+                items.len() as u32 <= T::MaxBatchSize::get(),
+                Error::<T>::BatchTooLarge
+            );
+            let mut succeeded = 0u32;
+            let mut failed = 0u32;
+            for item in items {
+                let geode = geode_of(&item);
+                match call(item) {
+                    Ok(_) => succeeded += 1,
+                    Err(e) => {
+                        failed += 1;
+                        Self::deposit_event(Event::BatchItemFailed(geode, e.error));
+                    }
+                }
+            }
+            Self::deposit_event(Event::BatchCompleted(succeeded, failed));
+            Ok(().into())
+        }
+
+        /// Check a prop against the schema, return the typed value of a capability.
+        fn check_capability(
+            name: &[u8],
+            value: &[u8],
+        ) -> Result<Option<CapabilityValue>, DispatchError> {
+            match <Capabilities<T>>::get(name) {
+                Some(capability_type) => capability_type
+                    .parse(value)
+                    .map(Some)
+                    .ok_or_else(|| Error::<T>::InvalidCapability.into()),
+                None => Ok(None),
+            }
+        }
+
+        /// Index the `Number` capabilities of a geode.
+        fn index_capabilities(geode: &GeodeOf<T>) {
+            for (name, value) in geode.props.iter() {
+                if let Ok(Some(CapabilityValue::Number(value))) =
+                    Self::check_capability(name, value)
+                {
+                    <CapabilityIndex<T>>::insert(name, &geode.id, value);
+                }
+            }
+        }
+
+        /// Drop the indexed capabilities of a geode.
+        fn unindex_capabilities(geode: &GeodeOf<T>) {
+            for name in geode.props.keys() {
+                <CapabilityIndex<T>>::remove(name, &geode.id);
+            }
+        }
+
+        fn clear_capability_index(key: &[u8]) {
+            let mut geodes = Vec::new();
+            <CapabilityIndex<T>>::iter_prefix(key)
+                .map(|(geode, _)| {
+                    geodes.push(geode);
                 })
                 .all(|_| true);
-            for id in degraded_instantiated_geodes {
-                <DegradedGeodes<T>>::insert(id, block_number);
+            for geode in geodes.iter() {
+                <CapabilityIndex<T>>::remove(key, geode);
             }
         }
 
-        // called by geode provider
-        pub fn dismiss_geode_from_service(geode: T::AccountId, when: BlockNumber) {
-            // reset geode order
-            let mut geode_record = <Geodes<T>>::get(&geode);
-            match geode_record.state {
-                GeodeState::Degraded => {
-                    <DegradedGeodes<T>>::remove(&geode);
-                }
-                GeodeState::Instantiated => {
-                    <InstantiatedGeodes<T>>::remove(&geode);
+        /// Drop a geode from the provider's index.
+        fn remove_from_provider(provider: &T::AccountId, geode: &T::AccountId) {
+            <ProviderGeodes<T>>::mutate_exists(provider, |geodes| {
+                if let Some(ids) = geodes {
+                    ids.remove(geode);
+                    if ids.is_empty() {
+                        *geodes = None;
+                    }
                 }
-                _ => {}
+            });
+        }
+
+        /// Bond `value` of the provider's funds for the geode.
+        fn bond_stake(
+            provider: &T::AccountId,
+            geode: &T::AccountId,
+            value: T::Balance,
+        ) -> DispatchResultWithPostInfo {
+            if value.is_zero() {
+                return Ok(().into());
+            }
+            T::GeodeAccounting::geode_staking(provider.clone(), value)?;
+            <GeodeStakes<T>>::mutate(geode, |stake| *stake = stake.saturating_add(value));
+            Self::deposit_event(Event::GeodeStaked(geode.clone(), value));
+            Ok(().into())
+        }
+
+        /// Start unbonding all the stake of a geode.
+        fn release_stake(geode: &GeodeOf<T>) {
+            let stake = <GeodeStakes<T>>::take(&geode.id);
+            if stake.is_zero() {
+                return;
+            }
+            let _ = T::GeodeAccounting::geode_unreserve(geode.provider.clone(), stake);
+            Self::deposit_event(Event::GeodeStakeReleased(geode.id.clone(), stake));
+        }
+
+        /// Slash up to `value` of the geode's own stake, return the slashed amount.
+        pub fn slash_stake(geode: &T::AccountId, value: T::Balance) -> T::Balance {
+            let stake = <GeodeStakes<T>>::get(geode);
+            let value = value.min(stake);
+            if value.is_zero() {
+                return value;
+            }
+            let provider = <Geodes<T>>::get(geode).provider;
+            let slashed = T::GeodeAccounting::geode_slash(provider, value);
+            <GeodeStakes<T>>::insert(geode, stake.saturating_sub(slashed));
+            Self::deposit_event(Event::GeodeStakeSlashed(geode.clone(), slashed));
+            slashed
+        }
+
+        /// Set the misconduct penalty aside from the geode's own stake. It stays reserved on
+        /// the provider, out of reach of stake releases, until settled or restored.
+        pub fn hold_penalty(geode: &T::AccountId) -> T::Balance {
+            let stake = <GeodeStakes<T>>::get(geode);
+            let value = T::GeodeAccounting::geode_penalty().min(stake);
+            if !value.is_zero() {
+                <GeodeStakes<T>>::insert(geode, stake - value);
+                <HeldPenalties<T>>::insert(geode, value);
+            }
+            value
+        }
+
+        /// Slash a held penalty off the provider and share it out among the reporters, the
+        /// affected service owner and the treasury.
+        pub fn settle_penalty(
+            geode: &T::AccountId,
+            provider: &T::AccountId,
+            value: T::Balance,
+            reporters: Vec<T::AccountId>,
+            service_owner: Option<T::AccountId>,
+        ) -> Penalty<T::AccountId, T::Balance> {
+            <HeldPenalties<T>>::remove(geode);
+            let penalty = T::GeodeAccounting::geode_penalize(
+                provider.clone(),
+                value,
+                reporters,
+                service_owner,
+            );
+            if !penalty.slashed.is_zero() {
+                Self::deposit_event(Event::GeodeStakeSlashed(geode.clone(), penalty.slashed));
+            }
+            penalty
+        }
+
+        /// Give a held penalty back, to the geode's stake if the provider still runs the
+        /// geode, otherwise unbonded.
+        pub fn restore_penalty(geode: &T::AccountId, provider: &T::AccountId, value: T::Balance) {
+            <HeldPenalties<T>>::remove(geode);
+            if value.is_zero() {
+                return;
             }
-            geode_record.state = GeodeState::Offline;
-            geode_record.order = None;
-            <OfflineGeodes<T>>::insert(geode, when);
+            if <Geodes<T>>::contains_key(geode) && <Geodes<T>>::get(geode).provider == *provider {
+                <GeodeStakes<T>>::mutate(geode, |stake| *stake = stake.saturating_add(value));
+            } else {
+                let _ = T::GeodeAccounting::geode_unreserve(provider.clone(), value);
+            }
+        }
+
+        /// Return attested geodes whose `Number` capabilities are at least the required values
+        pub fn attested_geodes_with(requirements: Vec<(Vec<u8>, u64)>) -> Vec<GeodeOf<T>> {
+            let ((key, min), rest) = match requirements.split_first() {
+                Some(split) => split,
+                None => return Self::attested_geodes(),
+            };
+            <CapabilityIndex<T>>::iter_prefix(key)
+                .filter(|(id, value)| {
+                    *value >= *min
+                        && <GeodeStates<T>>::contains_key(GeodeState::Attested, id)
+                        && rest.iter().all(|(key, min)| {
+                            <CapabilityIndex<T>>::get(key, id).map_or(false, |value| value >= *min)
+                        })
+                })
+                .map(|(id, _)| Self::routable(<Geodes<T>>::get(id)))
+                .collect()
+        }
+
+        /// Return geodes owned by a provider
+        pub fn geodes_of_provider(provider: T::AccountId) -> Vec<GeodeOf<T>> {
+            <ProviderGeodes<T>>::get(&provider)
+                .iter()
+                .map(|id| Self::routable(<Geodes<T>>::get(id)))
+                .collect()
+        }
+
+        pub fn geode_state(geode: T::AccountId) -> Option<GeodeState> {
+            if <Geodes<T>>::contains_key(&geode) {
+                Some(<Geodes<T>>::get(&geode).state)
+            } else {
+                None
+            }
+        }
+
+        // called by geode provider
+        pub fn dismiss_geode_from_service(geode: T::AccountId) {
+            let geode_record = <Geodes<T>>::get(&geode);
+            Self::transit_state(
+                &geode_record,
+                GeodeState::Offline,
+                TransitionCause::ProviderAction,
+            );
         }
 
         fn clean_from_promises(geode: &GeodeOf<T>, when: &BlockNumber) {
@@ -489,138 +1324,118 @@ pub mod pallet {
             }
         }
 
-        pub fn transit_state(geode: &GeodeOf<T>, to: GeodeState) -> bool {
+        pub fn transit_state(geode: &GeodeOf<T>, to: GeodeState, cause: TransitionCause) -> bool {
+            let from = geode.state.clone();
+            if !from.can_transit_to(&to) {
+                return false;
+            }
             let when = <frame_system::Module<T>>::block_number().saturated_into::<BlockNumber>();
-            match geode.state {
-                GeodeState::Registered => {
-                    match to {
-                        GeodeState::Attested => {
-                            Self::add_to_promises(&geode, &when);
-                        }
-                        GeodeState::Unknown => {}
-                        GeodeState::Offline => {}
-                        GeodeState::Null => {}
-                        _ => {
-                            return false;
-                        }
-                    }
-                    <RegisteredGeodes<T>>::remove(&geode.id);
+
+            // side effects of leaving the old state
+            match (&from, &to) {
+                (GeodeState::Null, _) => {
+                    <ProviderGeodes<T>>::mutate(&geode.provider, |geodes| {
+                        geodes.insert(geode.id.clone())
+                    });
+                    Self::index_capabilities(&geode);
                 }
-                GeodeState::Attested => {
-                    match to {
-                        GeodeState::Instantiated => {}
-                        GeodeState::Registered => {
-                            Self::clean_from_promises(&geode, &when);
-                        }
-                        GeodeState::Unknown => {
-                            Self::clean_from_promises(&geode, &when);
-                            pallet_attestor::Module::<T>::detach_geode_from_attestors(&geode.id);
-                        }
-                        GeodeState::Offline => {
-                            Self::clean_from_promises(&geode, &when);
-                            pallet_attestor::Module::<T>::detach_geode_from_attestors(&geode.id);
-                        }
-                        GeodeState::Null => {
-                            Self::clean_from_promises(&geode, &when);
-                            pallet_attestor::Module::<T>::detach_geode_from_attestors(&geode.id);
-                        }
-                        _ => {
-                            return false;
-                        }
-                    }
-                    <AttestedGeodes<T>>::remove(&geode.id);
+                (GeodeState::Registered, GeodeState::Attested) => {
+                    Self::add_to_promises(&geode, &when);
                 }
-                GeodeState::Unknown => {
-                    match to {
-                        GeodeState::Null => {}
-                        _ => {
-                            return false;
-                        }
-                    }
-                    <UnknownGeodes<T>>::remove(&geode.id);
-                }
-                GeodeState::Instantiated => {
-                    match to {
-                        GeodeState::Degraded => {}
-                        GeodeState::Attested => {}
-                        GeodeState::Unknown => {
-                            pallet_attestor::Module::<T>::detach_geode_from_attestors(&geode.id);
-                        }
-                        GeodeState::Offline => {
-                            pallet_attestor::Module::<T>::detach_geode_from_attestors(&geode.id);
-                        }
-                        _ => {
-                            return false;
-                        }
-                    }
-                    <InstantiatedGeodes<T>>::remove(&geode.id);
-                }
-                GeodeState::Degraded => {
-                    match to {
-                        GeodeState::Instantiated => {}
-                        GeodeState::Registered => {}
-                        GeodeState::Unknown => {
-                            pallet_attestor::Module::<T>::detach_geode_from_attestors(&geode.id);
-                        }
-                        GeodeState::Offline => {
-                            pallet_attestor::Module::<T>::detach_geode_from_attestors(&geode.id);
-                        }
-                        _ => {
-                            return false;
-                        }
-                    }
-                    <DegradedGeodes<T>>::remove(&geode.id);
-                }
-                GeodeState::Offline => {
-                    match to {
-                        GeodeState::Registered => {}
-                        GeodeState::Null => {}
-                        _ => {
-                            return false;
-                        }
-                    }
-                    <OfflineGeodes<T>>::remove(&geode.id);
+                (GeodeState::Attested, GeodeState::Registered)
+                | (GeodeState::Attested, GeodeState::Maintenance) => {
+                    Self::clean_from_promises(&geode, &when);
+                }
+                (GeodeState::Maintenance, GeodeState::Attested)
+                | (GeodeState::Unknown, GeodeState::Attested) => {
+                    Self::add_to_promises(&geode, &when);
                 }
-                GeodeState::Null => {
-                    // shouldn't happen
+                (GeodeState::Attested, GeodeState::Unknown)
+                | (GeodeState::Attested, GeodeState::Offline)
+                | (GeodeState::Attested, GeodeState::Null) => {
+                    Self::clean_from_promises(&geode, &when);
+                    pallet_attestor::Module::<T>::detach_geode_from_attestors(&geode.id);
                 }
+                (GeodeState::Instantiated, GeodeState::Unknown)
+                | (GeodeState::Instantiated, GeodeState::Offline)
+                | (GeodeState::Degraded, GeodeState::Unknown)
+                | (GeodeState::Degraded, GeodeState::Offline)
+                | (GeodeState::Maintenance, GeodeState::Unknown)
+                | (GeodeState::Maintenance, GeodeState::Offline) => {
+                    pallet_attestor::Module::<T>::detach_geode_from_attestors(&geode.id);
+                }
+                _ => {}
             }
+            <GeodeStates<T>>::remove(&from, &geode.id);
+
+            <GeodeHistory<T>>::mutate(&geode.id, |history| {
+                history.push(StateTransition {
+                    from,
+                    to: to.clone(),
+                    block: when,
+                    cause,
+                });
+                let max = T::MaxHistoryLength::get() as usize;
+                if history.len() > max {
+                    history.drain(..history.len() - max);
+                }
+            });
 
             // change geode state and deposit event
             if to != GeodeState::Null {
                 let mut geode = geode.clone();
                 geode.state = to.clone();
+                if !to.has_order() {
+                    geode.order = None;
+                }
                 <Geodes<T>>::insert(&geode.id, &geode);
-                Self::deposit_event(Event::GeodeStateUpdate(geode.id, to.clone()));
+                <GeodeStates<T>>::insert(&to, &geode.id, &when);
+                T::OnGeodeStateChange::on_geode_state_change(&geode.id, &to, when);
+                Self::deposit_event(Event::GeodeStateUpdate(geode.id, to));
             } else {
+                Self::release_stake(&geode);
+                <PendingTransfers<T>>::remove(&geode.id);
+                <GeodePrices<T>>::remove(&geode.id);
+                <DnsChallenges<T>>::remove(&geode.id);
+                <VerifiedDns<T>>::remove(&geode.id);
+                Self::remove_from_provider(&geode.provider, &geode.id);
+                Self::unindex_capabilities(&geode);
                 <Geodes<T>>::remove(&geode.id);
                 Self::deposit_event(Event::GeodeRemove(geode.id.clone()));
             }
 
-            // populate new state map
-            match to {
-                GeodeState::Registered => {
-                    <RegisteredGeodes<T>>::insert(&geode.id, &when);
+            true
+        }
+
+        /// Check the geode records agree with the state index and the provider index.
+        pub fn check_invariants() -> Result<(), &'static str> {
+            for (id, geode) in <Geodes<T>>::iter() {
+                if geode.id != id {
+                    return Err("geode stored under another id");
                 }
-                GeodeState::Attested => {
-                    <AttestedGeodes<T>>::insert(&geode.id, &when);
+                if !<GeodeStates<T>>::contains_key(&geode.state, &id) {
+                    return Err("geode missing from the state index");
                 }
-                GeodeState::Unknown => {
-                    <UnknownGeodes<T>>::insert(&geode.id, &when);
+                if geode.order.is_some() != geode.state.has_order() {
+                    return Err("geode order doesn't match its state");
                 }
-                GeodeState::Instantiated => {
-                    <InstantiatedGeodes<T>>::insert(&geode.id, &when);
+                if !<ProviderGeodes<T>>::get(&geode.provider).contains(&id) {
+                    return Err("geode missing from the provider index");
                 }
-                GeodeState::Degraded => {
-                    <DegradedGeodes<T>>::insert(&geode.id, &when);
+            }
+            for (state, id, _) in <GeodeStates<T>>::iter() {
+                if !<Geodes<T>>::contains_key(&id) || <Geodes<T>>::get(&id).state != state {
+                    return Err("state index entry without a matching geode");
                 }
-                GeodeState::Offline => {
-                    <OfflineGeodes<T>>::insert(&geode.id, &when);
+            }
+            for (provider, geodes) in <ProviderGeodes<T>>::iter() {
+                for id in geodes.iter() {
+                    if !<Geodes<T>>::contains_key(id) || <Geodes<T>>::get(id).provider != provider {
+                        return Err("provider index entry without a matching geode");
+                    }
                 }
-                _ => {}
             }
-
-            true
+            Ok(())
         }
 
         /// clean all the storage, USE WITH CARE!
@@ -638,81 +1453,145 @@ pub mod pallet {
                 }
             }
 
-            // clean RegisteredGeodes
+            // clean GeodeStates
+            {
+                let mut states = Vec::new();
+                <GeodeStates<T>>::iter()
+                    .map(|(state, key, _)| {
+                        states.push((state, key));
+                    })
+                    .all(|_| true);
+                for (state, geode) in states.iter() {
+                    <GeodeStates<T>>::remove(state, geode);
+                }
+            }
+
+            // clean ProviderGeodes
             {
-                let mut registered_geodes = Vec::new();
-                <RegisteredGeodes<T>>::iter()
+                let mut providers = Vec::new();
+                <ProviderGeodes<T>>::iter()
                     .map(|(key, _)| {
-                        registered_geodes.push(key);
+                        providers.push(key);
                     })
                     .all(|_| true);
-                for registered_geode in registered_geodes.iter() {
-                    <RegisteredGeodes<T>>::remove(registered_geode);
+                for provider in providers.iter() {
+                    <ProviderGeodes<T>>::remove(provider);
                 }
             }
 
-            // clean AttestedGeodes
+            // clean Capabilities and CapabilityIndex
             {
-                let mut attested_geodes = Vec::new();
-                <AttestedGeodes<T>>::iter()
+                let mut capabilities = Vec::new();
+                <Capabilities<T>>::iter()
                     .map(|(key, _)| {
-                        attested_geodes.push(key);
+                        capabilities.push(key);
                     })
                     .all(|_| true);
-                for attested_geode in attested_geodes.iter() {
-                    <AttestedGeodes<T>>::remove(attested_geode);
+                for capability in capabilities.iter() {
+                    <Capabilities<T>>::remove(capability);
+                }
+
+                let mut indexed = Vec::new();
+                <CapabilityIndex<T>>::iter()
+                    .map(|(key, geode, _)| {
+                        indexed.push((key, geode));
+                    })
+                    .all(|_| true);
+                for (key, geode) in indexed.iter() {
+                    <CapabilityIndex<T>>::remove(key, geode);
                 }
             }
 
-            // clean InstantiatedGeodes
+            // clean GeodeHistory
             {
-                let mut instantiated_geodes = Vec::new();
-                <InstantiatedGeodes<T>>::iter()
+                let mut histories = Vec::new();
+                <GeodeHistory<T>>::iter()
                     .map(|(key, _)| {
-                        instantiated_geodes.push(key);
+                        histories.push(key);
                     })
                     .all(|_| true);
-                for instantiated_geode in instantiated_geodes.iter() {
-                    <InstantiatedGeodes<T>>::remove(instantiated_geode);
+                for history in histories.iter() {
+                    <GeodeHistory<T>>::remove(history);
                 }
             }
 
-            // clean DegradedGeodes
+            // clean PendingTransfers
             {
-                let mut degraded_instantiated_geodes = Vec::new();
-                <DegradedGeodes<T>>::iter()
+                let mut pending_transfers = Vec::new();
+                <PendingTransfers<T>>::iter()
                     .map(|(key, _)| {
-                        degraded_instantiated_geodes.push(key);
+                        pending_transfers.push(key);
                     })
                     .all(|_| true);
-                for degraded_instantiated_geode in degraded_instantiated_geodes.iter() {
-                    <DegradedGeodes<T>>::remove(degraded_instantiated_geode);
+                for pending_transfer in pending_transfers.iter() {
+                    <PendingTransfers<T>>::remove(pending_transfer);
                 }
             }
 
-            // clean OfflineGeodes
+            // clean GeodeStakes
             {
-                let mut offline_geodes = Vec::new();
-                <OfflineGeodes<T>>::iter()
+                let mut staked_geodes = Vec::new();
+                <GeodeStakes<T>>::iter()
                     .map(|(key, _)| {
-                        offline_geodes.push(key);
+                        staked_geodes.push(key);
                     })
                     .all(|_| true);
-                for offline_geode in offline_geodes.iter() {
-                    <OfflineGeodes<T>>::remove(offline_geode);
+                for staked_geode in staked_geodes.iter() {
+                    <GeodeStakes<T>>::remove(staked_geode);
                 }
             }
 
-            // clean UnknownGeodes
+            // clean HeldPenalties
             {
-                let mut unknown_geodes = Vec::new();
-                <UnknownGeodes<T>>::iter()
+                let mut penalized_geodes = Vec::new();
+                <HeldPenalties<T>>::iter()
                     .map(|(key, _)| {
-                        unknown_geodes.push(key);
+                        penalized_geodes.push(key);
                     })
                     .all(|_| true);
-                for unknown_geode in unknown_geodes.iter() {
-                    <UnknownGeodes<T>>::remove(unknown_geode);
+                for penalized_geode in penalized_geodes.iter() {
+                    <HeldPenalties<T>>::remove(penalized_geode);
+                }
+            }
+
+            // clean DnsChallenges and VerifiedDns
+            {
+                let mut dns_geodes = Vec::new();
+                <DnsChallenges<T>>::iter()
+                    .map(|(key, _)| {
+                        dns_geodes.push(key);
+                    })
+                    .all(|_| true);
+                <VerifiedDns<T>>::iter()
+                    .map(|(key, _)| {
+                        dns_geodes.push(key);
+                    })
+                    .all(|_| true);
+                for dns_geode in dns_geodes.iter() {
+                    <DnsChallenges<T>>::remove(dns_geode);
+                    <VerifiedDns<T>>::remove(dns_geode);
+                }
+                let mut blocks = Vec::new();
+                <DnsChallengeExpiries<T>>::iter()
+                    .map(|(key, _)| {
+                        blocks.push(key);
+                    })
+                    .all(|_| true);
+                for block in blocks.iter() {
+                    <DnsChallengeExpiries<T>>::remove(block);
+                }
+            }
+
+            // clean GeodePrices
+            {
+                let mut priced_geodes = Vec::new();
+                <GeodePrices<T>>::iter()
+                    .map(|(key, _)| {
+                        priced_geodes.push(key);
+                    })
+                    .all(|_| true);
+                for priced_geode in priced_geodes.iter() {
+                    <GeodePrices<T>>::remove(priced_geode);
                 }
             }
         }
@@ -720,13 +1599,9 @@ pub mod pallet {
 
     impl<T: Config> Get<Vec<T::AccountId>> for Pallet<T> {
         fn get() -> Vec<T::AccountId> {
-            let mut res = Vec::new();
-            <RegisteredGeodes<T>>::iter()
-                .map(|(id, _)| {
-                    res.push(id);
-                })
-                .all(|_| true);
-            res
+            <GeodeStates<T>>::iter_prefix(GeodeState::Registered)
+                .map(|(id, _)| id)
+                .collect()
         }
     }
 }
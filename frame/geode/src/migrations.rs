@@ -0,0 +1,291 @@
+use crate::endpoint::{self, Address, Endpoint, Protocol};
+use crate::{
+    Config, DnsChallenges, Geode, GeodeStakes, GeodeState, GeodeStates, Geodes, Pallet,
+    ProviderGeodes, VerifiedDns,
+};
+use automata_traits::GeodeAccounting;
+use codec::{Decode, Encode};
+use frame_support::{
+    storage::migration::{put_storage_value, remove_storage_prefix, storage_key_iter},
+    traits::{Get, PalletInfoAccess, StorageVersion},
+    weights::Weight,
+    Blake2_128Concat, StorageHasher,
+};
+use primitives::BlockNumber;
+use sp_std::{collections::btree_map::BTreeMap, prelude::*};
+
+/// Backfill the stake of geodes registered before it was recorded per geode.
+pub mod v1 {
+    use super::*;
+
+    pub fn migrate<T: Config>() -> Weight {
+        if StorageVersion::get::<Pallet<T>>() >= 1 {
+            return T::DbWeight::get().reads(1);
+        }
+
+        let mut reads = 1u64;
+        let mut writes = 1u64;
+        for id in <Geodes<T>>::iter_keys() {
+            reads += 2;
+            // legacy geodes bonded the minimum stake on registration
+            if !<GeodeStakes<T>>::contains_key(&id) {
+                <GeodeStakes<T>>::insert(&id, T::GeodeAccounting::geode_min_stake());
+                writes += 1;
+            }
+        }
+        StorageVersion::new(1).put::<Pallet<T>>();
+
+        T::DbWeight::get().reads_writes(reads, writes)
+    }
+}
+
+/// Index the geodes registered before `ProviderGeodes` was introduced by their provider.
+pub mod v2 {
+    use super::*;
+
+    pub fn migrate<T: Config>() -> Weight {
+        if StorageVersion::get::<Pallet<T>>() >= 2 {
+            return T::DbWeight::get().reads(1);
+        }
+
+        let pallet = <Pallet<T>>::name().as_bytes();
+        let mut reads = 1u64;
+        let mut writes = 1u64;
+        // geodes are still in the layout before endpoints
+        for (id, geode) in storage_key_iter::<
+            T::AccountId,
+            super::v5::OldGeode<T::AccountId, T::Hash>,
+            Blake2_128Concat,
+        >(pallet, b"Geodes")
+        {
+            reads += 2;
+            <ProviderGeodes<T>>::mutate(&geode.provider, |geodes| geodes.insert(id));
+            writes += 1;
+        }
+        StorageVersion::new(2).put::<Pallet<T>>();
+
+        T::DbWeight::get().reads_writes(reads, writes)
+    }
+}
+
+/// Move the per-state geode maps into `GeodeStates` and drop the orders geodes kept after
+/// leaving the order states.
+pub mod v3 {
+    use super::*;
+
+    /// Per-state maps before `GeodeStates` was introduced.
+    pub const OLD_STATE_MAPS: [(&[u8], GeodeState); 6] = [
+        (b"RegisteredGeodes", GeodeState::Registered),
+        (b"AttestedGeodes", GeodeState::Attested),
+        (b"InstantiatedGeodes", GeodeState::Instantiated),
+        (b"DegradedGeodes", GeodeState::Degraded),
+        (b"OfflineGeodes", GeodeState::Offline),
+        (b"UnknownGeodes", GeodeState::Unknown),
+    ];
+
+    pub fn migrate<T: Config>() -> Weight {
+        if StorageVersion::get::<Pallet<T>>() >= 3 {
+            return T::DbWeight::get().reads(1);
+        }
+
+        let pallet = <Pallet<T>>::name().as_bytes();
+        let mut reads = 1u64;
+        let mut writes = 1u64;
+        for (item, state) in OLD_STATE_MAPS.iter() {
+            for (geode, since) in
+                storage_key_iter::<T::AccountId, BlockNumber, Blake2_128Concat>(pallet, item)
+            {
+                <GeodeStates<T>>::insert(state, geode, since);
+                reads += 1;
+                writes += 1;
+            }
+            remove_storage_prefix(pallet, item, &[]);
+        }
+
+        // geodes are still in the layout before endpoints, only instantiated and degraded
+        // geodes serve an order
+        let geodes = storage_key_iter::<
+            T::AccountId,
+            super::v5::OldGeode<T::AccountId, T::Hash>,
+            Blake2_128Concat,
+        >(pallet, b"Geodes")
+        .collect::<Vec<_>>();
+        for (id, mut geode) in geodes {
+            reads += 1;
+            if geode.order.is_some() && !geode.state.has_order() {
+                geode.order = None;
+                put_storage_value(
+                    pallet,
+                    b"Geodes",
+                    &Blake2_128Concat::hash(&id.encode()),
+                    geode,
+                );
+                writes += 1;
+            }
+        }
+        StorageVersion::new(3).put::<Pallet<T>>();
+
+        T::DbWeight::get().reads_writes(reads, writes)
+    }
+}
+
+/// Challenge the DNS names of geodes registered before names were verified.
+pub mod v4 {
+    use super::*;
+
+    pub fn migrate<T: Config>() -> Weight {
+        if StorageVersion::get::<Pallet<T>>() >= 4 {
+            return T::DbWeight::get().reads(1);
+        }
+
+        let mut reads = 1u64;
+        let mut writes = 1u64;
+        // geodes are still in the layout before endpoints
+        let pallet = <Pallet<T>>::name().as_bytes();
+        let geodes = storage_key_iter::<
+            T::AccountId,
+            super::v5::OldGeode<T::AccountId, T::Hash>,
+            Blake2_128Concat,
+        >(pallet, b"Geodes")
+        .collect::<Vec<_>>();
+        for (id, geode) in geodes {
+            reads += 3;
+            let endpoints = super::v5::legacy_endpoints(&geode.ip, &geode.dns);
+            if let Some(name) = endpoint::dns_name(&endpoints) {
+                if !<VerifiedDns<T>>::contains_key(&id) && !<DnsChallenges<T>>::contains_key(&id) {
+                    <Pallet<T>>::issue_dns_challenge(&id, name);
+                    writes += 2;
+                }
+            }
+        }
+        StorageVersion::new(4).put::<Pallet<T>>();
+
+        T::DbWeight::get().reads_writes(reads, writes)
+    }
+}
+
+/// Replace the raw `ip` and `dns` bytes of geodes with typed endpoints.
+pub mod v5 {
+    use super::*;
+
+    /// Geode before endpoints were introduced.
+    #[derive(Encode, Decode)]
+    pub struct OldGeode<AccountId, Hash> {
+        pub id: AccountId,
+        pub provider: AccountId,
+        pub order: Option<(Hash, Option<BlockNumber>)>,
+        pub ip: Vec<u8>,
+        pub dns: Vec<u8>,
+        pub props: BTreeMap<Vec<u8>, Vec<u8>>,
+        pub state: GeodeState,
+        pub promise: BlockNumber,
+    }
+
+    /// Best effort parse of the legacy fields. `ip` may be a textual multiaddr, dotted
+    /// IPv4 with an optional port, a DNS name, or raw IPv4/IPv6 octets. The protocol was
+    /// never recorded so TCP is assumed. Anything else is dropped.
+    pub fn legacy_endpoints(ip: &[u8], dns: &[u8]) -> Vec<Endpoint> {
+        let tcp = |address, port| Endpoint {
+            address,
+            port,
+            protocol: Protocol::Tcp,
+        };
+        let mut endpoints = Vec::new();
+        if ip.first() == Some(&b'/') {
+            endpoints.push(tcp(Address::Multiaddr(ip.to_vec()), None));
+        } else if let Some((octets, port)) = parse_ipv4(ip) {
+            endpoints.push(tcp(Address::Ipv4(octets), port));
+        } else if endpoint::is_valid_dns_name(ip) {
+            endpoints.push(tcp(Address::Dns(ip.to_vec()), None));
+        } else if ip.len() == 4 {
+            let mut octets = [0u8; 4];
+            octets.copy_from_slice(ip);
+            endpoints.push(tcp(Address::Ipv4(octets), None));
+        } else if ip.len() == 16 {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(ip);
+            endpoints.push(tcp(Address::Ipv6(octets), None));
+        }
+        // the dns field wins over a name found in ip
+        if endpoint::is_valid_dns_name(dns) {
+            endpoints.retain(|endpoint| !matches!(endpoint.address, Address::Dns(_)));
+            endpoints.push(tcp(Address::Dns(dns.to_vec()), None));
+        }
+        endpoints.retain(|endpoint| endpoint.is_valid());
+        endpoints
+    }
+
+    /// Parse `a.b.c.d` or `a.b.c.d:port`.
+    fn parse_ipv4(text: &[u8]) -> Option<([u8; 4], Option<u16>)> {
+        let mut split = text.splitn(2, |c| *c == b':');
+        let host = split.next()?;
+        let port = match split.next() {
+            Some(port) => {
+                let port = parse_decimal(port)?;
+                if port > u16::MAX as u64 {
+                    return None;
+                }
+                Some(port as u16)
+            }
+            None => None,
+        };
+        let mut octets = [0u8; 4];
+        let mut parts = 0;
+        for part in host.split(|c| *c == b'.') {
+            if parts == 4 {
+                return None;
+            }
+            let value = parse_decimal(part)?;
+            if value > 255 {
+                return None;
+            }
+            octets[parts] = value as u8;
+            parts += 1;
+        }
+        if parts != 4 {
+            return None;
+        }
+        Some((octets, port))
+    }
+
+    fn parse_decimal(digits: &[u8]) -> Option<u64> {
+        if digits.is_empty() || digits.len() > 5 {
+            return None;
+        }
+        digits.iter().try_fold(0u64, |value, c| {
+            if c.is_ascii_digit() {
+                Some(value * 10 + (c - b'0') as u64)
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn migrate<T: Config>() -> Weight {
+        if StorageVersion::get::<Pallet<T>>() >= 5 {
+            return T::DbWeight::get().reads(1);
+        }
+
+        let mut translated = 0u64;
+        <Geodes<T>>::translate::<OldGeode<T::AccountId, T::Hash>, _>(|_, old| {
+            translated += 1;
+            Some(Geode {
+                endpoints: legacy_endpoints(&old.ip, &old.dns),
+                id: old.id,
+                provider: old.provider,
+                // only instantiated and degraded geodes serve an order
+                order: if old.state.has_order() {
+                    old.order
+                } else {
+                    None
+                },
+                props: old.props,
+                state: old.state,
+                promise: old.promise,
+            })
+        });
+        StorageVersion::new(5).put::<Pallet<T>>();
+
+        T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+    }
+}
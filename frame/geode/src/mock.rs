@@ -1,5 +1,5 @@
 use crate as pallet_geode;
-use frame_support::parameter_types;
+use frame_support::{parameter_types, traits::ReservableCurrency};
 use frame_system as system;
 use primitives::*;
 use sp_core::H256;
@@ -11,7 +11,8 @@ use sp_runtime::{
 use frame_support::dispatch::DispatchResultWithPostInfo;
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
 type Block = frame_system::mocking::MockBlock<Test>;
-use automata_traits::{AttestorAccounting, GeodeAccounting};
+use automata_traits::{distribute_penalty, AttestorAccounting, GeodeAccounting, Penalty};
+use pallet_attestor::attestation::{sha256, AttestationError, AttestationVerifier, EnclaveReport};
 
 pub const INIT_BALANCE: u64 = 100_100_100;
 
@@ -90,12 +91,33 @@ impl AttestorAccounting for Test {
 
 impl GeodeAccounting for Test {
     type AccountId = u64;
-    fn geode_staking(who: Self::AccountId) -> DispatchResultWithPostInfo {
+    type Balance = u64;
+    fn geode_min_stake() -> Self::Balance {
+        GeodeMinStake::get()
+    }
+    fn geode_penalty() -> Self::Balance {
+        GeodeMinStake::get()
+    }
+    fn geode_staking(who: Self::AccountId, value: Self::Balance) -> DispatchResultWithPostInfo {
+        Balances::reserve(&who, value)?;
         Ok(().into())
     }
-    fn geode_unreserve(who: Self::AccountId) -> DispatchResultWithPostInfo {
+    fn geode_unreserve(who: Self::AccountId, value: Self::Balance) -> DispatchResultWithPostInfo {
+        Balances::unreserve(&who, value);
         Ok(().into())
     }
+    fn geode_slash(who: Self::AccountId, value: Self::Balance) -> Self::Balance {
+        value - Balances::slash_reserved(&who, value).1
+    }
+    fn geode_penalize(
+        who: Self::AccountId,
+        value: Self::Balance,
+        reporters: Vec<Self::AccountId>,
+        service_owner: Option<Self::AccountId>,
+    ) -> Penalty<Self::AccountId, Self::Balance> {
+        let (slashed, _) = Balances::slash_reserved(&who, value);
+        distribute_penalty::<_, Balances, ()>(slashed, reporters, 0, service_owner, 0)
+    }
 }
 
 impl<C> frame_system::offchain::SendTransactionTypes<C> for Test
@@ -106,25 +128,80 @@ where
     type OverarchingCall = Call;
 }
 
+pub const MR_ENCLAVE: [u8; 32] = [7u8; 32];
+
+/// Mock reports are `mr_enclave || report_data`.
+impl AttestationVerifier for Test {
+    fn verify(report: &[u8], _root: &[u8]) -> Result<EnclaveReport, AttestationError> {
+        if report.len() != 96 {
+            return Err(AttestationError::Malformed);
+        }
+        let mut enclave = EnclaveReport {
+            mr_enclave: [0u8; 32],
+            mr_signer: [0u8; 32],
+            report_data: [0u8; 64],
+        };
+        enclave.mr_enclave.copy_from_slice(&report[..32]);
+        enclave.report_data.copy_from_slice(&report[32..]);
+        Ok(enclave)
+    }
+}
+
+/// Build a mock attestation report of `mr_enclave` binding `pubkey`.
+pub fn attestation_report(mr_enclave: [u8; 32], pubkey: &[u8]) -> Vec<u8> {
+    let mut report = mr_enclave.to_vec();
+    report.extend_from_slice(&sha256(pubkey));
+    report.extend_from_slice(&[0u8; 32]);
+    report
+}
+
+parameter_types! {
+    pub const NotifyFreshnessBlockNumber: BlockNumber = 5;
+    pub const NotifyInterval: BlockNumber = 2;
+    pub const ReputationDecayPeriod: BlockNumber = 10;
+    pub const ReputationDecay: sp_runtime::Percent = sp_runtime::Percent::from_percent(50);
+    pub const MaxGeodesPerAttestor: u32 = 3;
+}
+
 impl pallet_attestor::Config for Test {
     type Event = Event;
     type Currency = Balances;
     type Call = Call;
     type AttestorAccounting = Test;
+    type AttestationVerifier = Test;
+    type NotifyFreshnessBlockNumber = NotifyFreshnessBlockNumber;
+    type NotifyInterval = NotifyInterval;
+    type MaxGeodesPerAttestor = MaxGeodesPerAttestor;
+    type ReputationDecayPeriod = ReputationDecayPeriod;
+    type ReputationDecay = ReputationDecay;
+    type AttestorAdminOrigin = frame_system::EnsureRoot<Self::AccountId>;
+    type OnAttestorRegistered = ();
 }
 
 parameter_types! {
     pub const DispatchConfirmationTimeout: BlockNumber = 12;
     pub const PutOnlineTimeout: BlockNumber = 40;
     pub const AttestationExpiryBlockNumber: BlockNumber = 30;
+    pub const MaxBatchSize: u32 = 4;
+    pub const MaxHistoryLength: u32 = 3;
+    pub const DnsApprovalRatio: sp_runtime::Percent = sp_runtime::Percent::from_percent(50);
+    pub const DnsChallengeExpiry: BlockNumber = 10;
+    pub const GeodeMinStake: u64 = 100;
 }
 
 impl pallet_geode::Config for Test {
     type Event = Event;
+    type Balance = u64;
     type GeodeAccounting = Test;
     type DispatchConfirmationTimeout = DispatchConfirmationTimeout;
     type PutOnlineTimeout = PutOnlineTimeout;
     type AttestationExpiryBlockNumber = AttestationExpiryBlockNumber;
+    type MaxBatchSize = MaxBatchSize;
+    type MaxHistoryLength = MaxHistoryLength;
+    type DnsApprovalRatio = DnsApprovalRatio;
+    type DnsChallengeExpiry = DnsChallengeExpiry;
+    type CapabilityAdminOrigin = frame_system::EnsureRoot<Self::AccountId>;
+    type OnGeodeStateChange = ();
 }
 
 // Build genesis storage according to the mock runtime.
@@ -133,13 +210,17 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
         .build_storage::<Test>()
         .unwrap();
     pallet_balances::GenesisConfig::<Test> {
-        balances: vec![(1, INIT_BALANCE), (2, INIT_BALANCE)],
+        balances: vec![(1, INIT_BALANCE), (2, INIT_BALANCE), (4, INIT_BALANCE)],
     }
     .assimilate_storage(&mut t)
     .unwrap();
 
     let mut ext = sp_io::TestExternalities::new(t);
-    ext.execute_with(|| System::set_block_number(1));
+    ext.execute_with(|| {
+        System::set_block_number(1);
+        pallet_attestor::AttestationRoot::<Test>::put(vec![1]);
+        pallet_attestor::AllowedMeasurements::<Test>::insert(MR_ENCLAVE, 0);
+    });
     ext
 }
 
@@ -165,6 +246,7 @@ pub fn register_attestor(attestor_account: <Test as system::Config>::AccountId)
         Origin::signed(attestor_account),
         url.clone(),
         pubkey.clone(),
+        attestation_report(MR_ENCLAVE, &pubkey),
     );
 }
 
@@ -179,8 +261,7 @@ pub fn provider_register_geode(
         id: geode_id,
         provider: provider,
         order: None,
-        ip: vec![],
-        dns: vec![],
+        endpoints: vec![],
         props: Default::default(),
         state: Default::default(),
         promise: Default::default(),
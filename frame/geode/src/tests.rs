@@ -1,6 +1,16 @@
 use crate as pallet_geode;
-use crate::{mock::*, Error};
-use frame_support::{assert_noop, assert_ok};
+use crate::{
+    capability::{self, CapabilityType},
+    endpoint::{self, Address, Endpoint, Protocol},
+    migrations,
+    mock::*,
+    Error,
+};
+use codec::Encode;
+use frame_support::{
+    assert_noop, assert_ok,
+    traits::{OnInitialize, ReservableCurrency},
+};
 
 #[test]
 fn it_works_provider_register_geode() {
@@ -19,8 +29,7 @@ fn it_works_provider_register_geode() {
             id: geode_id,
             provider: provider,
             order: None,
-            ip: vec![],
-            dns: vec![],
+            endpoints: vec![],
             props: Default::default(),
             state: Default::default(),
             promise: Default::default(),
@@ -50,8 +59,7 @@ fn it_works_geode_remove() {
             id: geode_id,
             provider: provider,
             order: None,
-            ip: vec![],
-            dns: vec![],
+            endpoints: vec![],
             props: Default::default(),
             state: Default::default(),
             promise: Default::default(),
@@ -86,8 +94,7 @@ fn it_works_update_geode_props() {
             id: geode_id,
             provider: provider,
             order: None,
-            ip: vec![],
-            dns: vec![],
+            endpoints: vec![],
             props: Default::default(),
             state: Default::default(),
             promise: Default::default(),
@@ -110,7 +117,7 @@ fn it_works_update_geode_props() {
 }
 
 #[test]
-fn it_works_update_geode_dns() {
+fn it_works_update_geode_endpoints() {
     new_test_ext().execute_with(|| {
         let attestor_account = 1;
         let geode_account = 2;
@@ -126,8 +133,7 @@ fn it_works_update_geode_dns() {
             id: geode_id,
             provider: provider,
             order: None,
-            ip: vec![],
-            dns: vec![],
+            endpoints: vec![],
             props: Default::default(),
             state: Default::default(),
             promise: Default::default(),
@@ -138,11 +144,828 @@ fn it_works_update_geode_dns() {
             geode
         ));
 
-        let dns = vec![1_u8];
-        assert_ok!(GeodeModule::update_geode_dns(
+        let endpoints = vec![
+            Endpoint {
+                address: Address::Ipv4([10, 0, 0, 1]),
+                port: Some(8080),
+                protocol: Protocol::Http,
+            },
+            Endpoint {
+                address: Address::Multiaddr(b"/ip4/10.0.0.1/tcp/30333".to_vec()),
+                port: None,
+                protocol: Protocol::Tcp,
+            },
+        ];
+        assert_ok!(GeodeModule::update_geode_endpoints(
             Origin::signed(provider),
             geode_id,
-            dns
+            endpoints.clone()
         ));
+        assert_eq!(GeodeModule::geodes(geode_id).endpoints, endpoints);
+        assert_eq!(
+            serde_json::to_string(&endpoints[0]).unwrap(),
+            r#"{"address":{"ipv4":"10.0.0.1"},"port":8080,"protocol":"http"}"#
+        );
+
+        let invalid = |address, port| {
+            GeodeModule::update_geode_endpoints(
+                Origin::signed(provider),
+                geode_id,
+                vec![Endpoint {
+                    address,
+                    port,
+                    protocol: Protocol::Https,
+                }],
+            )
+        };
+        assert_noop!(
+            invalid(Address::Ipv4([10, 0, 0, 1]), Some(0)),
+            Error::<Test>::InvalidEndpoint
+        );
+        assert_noop!(
+            invalid(Address::Dns(b"-bad.example.com".to_vec()), None),
+            Error::<Test>::InvalidEndpoint
+        );
+        assert_noop!(
+            invalid(Address::Multiaddr(b"/ip4/10.0.0.1".to_vec()), Some(1)),
+            Error::<Test>::InvalidEndpoint
+        );
+        assert_noop!(
+            GeodeModule::update_geode_endpoints(
+                Origin::signed(provider),
+                geode_id,
+                vec![endpoints[0].clone(); endpoint::MAX_ENDPOINTS + 1]
+            ),
+            Error::<Test>::TooManyEndpoints
+        );
+    });
+}
+
+#[test]
+fn it_works_for_geode_dns_verification() {
+    new_test_ext().execute_with(|| {
+        use crate::dns::{challenge_name, challenge_value, check_challenge, LocalResolver};
+        let geode_id = 3;
+        let provider = 4;
+        let dns = b"geode.example.com".to_vec();
+        let dns_endpoint = |name: &[u8]| Endpoint {
+            address: Address::Dns(name.to_vec()),
+            port: Some(443),
+            protocol: Protocol::Https,
+        };
+
+        provider_register_geode(provider, geode_id);
+        assert_ok!(GeodeModule::update_geode_endpoints(
+            Origin::signed(provider),
+            geode_id,
+            vec![dns_endpoint(&dns)]
+        ));
+        let token = GeodeModule::dns_challenge(geode_id).unwrap().token;
+        // unverified names are hidden
+        assert_eq!(GeodeModule::registered_geodes()[0].endpoints, vec![]);
+
+        // provider publishes the token, attestors look it up
+        let mut resolver = LocalResolver::default();
+        assert!(!check_challenge(&resolver, &dns, token.as_ref()));
+        resolver.insert_txt(challenge_name(&dns), challenge_value(token.as_ref()));
+        assert!(check_challenge(&resolver, &dns, token.as_ref()));
+
+        pallet_attestor::GeodeAttestors::<Test>::insert(
+            geode_id,
+            [1u64, 2, 5]
+                .iter()
+                .cloned()
+                .collect::<std::collections::BTreeSet<_>>(),
+        );
+        assert_noop!(
+            GeodeModule::attestor_submit_dns_result(Origin::signed(6), geode_id, token, true),
+            Error::<Test>::NotAttestingGeode
+        );
+        assert_noop!(
+            GeodeModule::attestor_submit_dns_result(
+                Origin::signed(1),
+                geode_id,
+                Default::default(),
+                true
+            ),
+            Error::<Test>::NoDnsChallenge
+        );
+        assert_ok!(GeodeModule::attestor_submit_dns_result(
+            Origin::signed(1),
+            geode_id,
+            token,
+            true
+        ));
+        assert_noop!(
+            GeodeModule::attestor_submit_dns_result(Origin::signed(1), geode_id, token, true),
+            Error::<Test>::DuplicateDnsResult
+        );
+        assert_eq!(GeodeModule::verified_dns(geode_id), None);
+
+        // quorum of the attestors verifies the name
+        assert_ok!(GeodeModule::attestor_submit_dns_result(
+            Origin::signed(2),
+            geode_id,
+            token,
+            true
+        ));
+        System::assert_last_event(crate::Event::DnsVerified(geode_id, dns.clone()).into());
+        assert_eq!(GeodeModule::verified_dns(geode_id), Some(dns.clone()));
+        assert_eq!(
+            GeodeModule::registered_geodes()[0].endpoints,
+            vec![dns_endpoint(&dns)]
+        );
+
+        // a new name needs a new challenge, which expires unanswered
+        assert_ok!(GeodeModule::update_geode_endpoints(
+            Origin::signed(provider),
+            geode_id,
+            vec![dns_endpoint(b"other.example.com")]
+        ));
+        assert_eq!(GeodeModule::verified_dns(geode_id), None);
+        assert_eq!(GeodeModule::registered_geodes()[0].endpoints, vec![]);
+        let token = GeodeModule::dns_challenge(geode_id).unwrap().token;
+        System::set_block_number(1 + DnsChallengeExpiry::get() as u64 + 1);
+        assert_noop!(
+            GeodeModule::attestor_submit_dns_result(Origin::signed(1), geode_id, token, true),
+            Error::<Test>::DnsChallengeExpired
+        );
+        GeodeModule::on_initialize(System::block_number());
+        System::assert_last_event(crate::Event::DnsChallengeDropped(geode_id).into());
+        assert!(GeodeModule::dns_challenge(geode_id).is_none());
+
+        // provider asks for a fresh challenge
+        assert_noop!(
+            GeodeModule::provider_renew_dns_challenge(Origin::signed(5), geode_id),
+            Error::<Test>::NoRight
+        );
+        assert_ok!(GeodeModule::provider_renew_dns_challenge(
+            Origin::signed(provider),
+            geode_id
+        ));
+        assert_ne!(GeodeModule::dns_challenge(geode_id).unwrap().token, token);
+        assert_noop!(
+            GeodeModule::provider_renew_dns_challenge(Origin::signed(provider), geode_id),
+            Error::<Test>::DnsChallengePending
+        );
+        pallet_geode::VerifiedDns::<Test>::insert(geode_id, b"other.example.com".to_vec());
+        assert_noop!(
+            GeodeModule::provider_renew_dns_challenge(Origin::signed(provider), geode_id),
+            Error::<Test>::DnsAlreadyVerified
+        );
+    });
+}
+
+#[test]
+fn it_works_for_geode_stake() {
+    new_test_ext().execute_with(|| {
+        let geode_id = 3;
+        let provider = 4;
+
+        provider_register_geode(provider, geode_id);
+        assert_eq!(GeodeModule::geode_stake(geode_id), 100);
+        assert_eq!(Balances::reserved_balance(provider), 100);
+
+        assert_noop!(
+            GeodeModule::provider_stake_geode(Origin::signed(1), geode_id, 50),
+            Error::<Test>::NoRight
+        );
+        assert_ok!(GeodeModule::provider_stake_geode(
+            Origin::signed(provider),
+            geode_id,
+            50
+        ));
+        assert_eq!(GeodeModule::geode_stake(geode_id), 150);
+        assert_eq!(Balances::reserved_balance(provider), 150);
+
+        // another geode of the same provider keeps its own stake
+        provider_register_geode(provider, 5);
+        assert_eq!(GeodeModule::slash_stake(&geode_id, 120), 120);
+        assert_eq!(GeodeModule::geode_stake(geode_id), 30);
+        assert_eq!(GeodeModule::geode_stake(5), 100);
+        assert_eq!(Balances::reserved_balance(provider), 130);
+
+        // re-registering an offline geode tops its stake up to the minimum
+        let geode = pallet_geode::Geodes::<Test>::get(geode_id);
+        assert!(GeodeModule::transit_state(
+            &geode,
+            pallet_geode::GeodeState::Offline,
+            pallet_geode::TransitionCause::ProviderAction
+        ));
+        provider_register_geode(provider, geode_id);
+        assert_eq!(GeodeModule::geode_stake(geode_id), 100);
+        assert_eq!(Balances::reserved_balance(provider), 200);
+
+        assert_ok!(GeodeModule::geode_remove(
+            Origin::signed(provider),
+            geode_id
+        ));
+        assert_eq!(GeodeModule::geode_stake(geode_id), 0);
+        assert_eq!(Balances::reserved_balance(provider), 100);
+    });
+}
+
+#[test]
+fn it_works_for_geode_price() {
+    new_test_ext().execute_with(|| {
+        let geode_id = 3;
+        let provider = 4;
+
+        assert_noop!(
+            GeodeModule::provider_set_geode_price(Origin::signed(provider), geode_id, 10),
+            Error::<Test>::InvalidGeode
+        );
+        provider_register_geode(provider, geode_id);
+        // unpriced geodes are free
+        assert_eq!(GeodeModule::geode_price(geode_id), 0);
+
+        assert_noop!(
+            GeodeModule::provider_set_geode_price(Origin::signed(1), geode_id, 10),
+            Error::<Test>::NoRight
+        );
+        assert_ok!(GeodeModule::provider_set_geode_price(
+            Origin::signed(provider),
+            geode_id,
+            10
+        ));
+        System::assert_last_event(crate::Event::GeodePriceSet(geode_id, 10).into());
+        assert_eq!(GeodeModule::geode_price(geode_id), 10);
+
+        // price goes away with the geode
+        assert_ok!(GeodeModule::geode_remove(
+            Origin::signed(provider),
+            geode_id
+        ));
+        assert_eq!(GeodeModule::geode_price(geode_id), 0);
+    });
+}
+
+#[test]
+fn it_works_for_provider_batch_calls() {
+    new_test_ext().execute_with(|| {
+        let provider = 4;
+        let geode = |id| pallet_geode::Geode {
+            id,
+            provider,
+            order: None,
+            endpoints: vec![],
+            props: Default::default(),
+            state: Default::default(),
+            promise: Default::default(),
+        };
+
+        provider_register_geode(provider, 3);
+        assert_noop!(
+            GeodeModule::provider_register_geodes(
+                Origin::signed(provider),
+                (5..10).map(geode).collect()
+            ),
+            Error::<Test>::BatchTooLarge
+        );
+
+        // geode 3 is already registered
+        assert_ok!(GeodeModule::provider_register_geodes(
+            Origin::signed(provider),
+            vec![geode(3), geode(5), geode(6)]
+        ));
+        System::assert_has_event(
+            crate::Event::BatchItemFailed(3, Error::<Test>::InvalidGeodeState.into()).into(),
+        );
+        System::assert_last_event(crate::Event::BatchCompleted(2, 1).into());
+        assert_eq!(
+            GeodeModule::provider_geodes(provider),
+            vec![3, 5, 6].into_iter().collect()
+        );
+        assert_eq!(GeodeModule::geodes_of_provider(provider).len(), 3);
+
+        assert_ok!(GeodeModule::update_geodes_props(
+            Origin::signed(provider),
+            vec![(5, vec![1], vec![2]), (7, vec![1], vec![2])]
+        ));
+        assert_eq!(
+            GeodeModule::geodes(5).props.get(&vec![1u8]),
+            Some(&vec![2u8])
+        );
+        System::assert_has_event(
+            crate::Event::BatchItemFailed(7, Error::<Test>::NoRight.into()).into(),
+        );
+        System::assert_last_event(crate::Event::BatchCompleted(1, 1).into());
+
+        assert_ok!(GeodeModule::provider_offline_geodes(
+            Origin::signed(provider),
+            vec![5, 6]
+        ));
+        assert_eq!(
+            GeodeModule::geode_state(5),
+            Some(pallet_geode::GeodeState::Offline)
+        );
+        System::assert_last_event(crate::Event::BatchCompleted(2, 0).into());
+
+        assert_ok!(GeodeModule::geode_remove(Origin::signed(provider), 5));
+        assert_eq!(
+            GeodeModule::provider_geodes(provider),
+            vec![3, 6].into_iter().collect()
+        );
+        assert_ok!(GeodeModule::check_invariants());
+    });
+}
+
+#[test]
+fn it_works_for_geode_transfer() {
+    new_test_ext().execute_with(|| {
+        let geode_id = 3;
+        let provider = 4;
+        let new_provider = 2;
+
+        provider_register_geode(provider, geode_id);
+        assert_ok!(GeodeModule::provider_stake_geode(
+            Origin::signed(provider),
+            geode_id,
+            50
+        ));
+
+        assert_noop!(
+            GeodeModule::offer_geode_transfer(Origin::signed(new_provider), geode_id, 1),
+            Error::<Test>::NoRight
+        );
+        assert_ok!(GeodeModule::offer_geode_transfer(
+            Origin::signed(provider),
+            geode_id,
+            new_provider
+        ));
+        assert_noop!(
+            GeodeModule::accept_geode_transfer(Origin::signed(1), geode_id),
+            Error::<Test>::NoPendingTransfer
+        );
+
+        assert_ok!(GeodeModule::accept_geode_transfer(
+            Origin::signed(new_provider),
+            geode_id
+        ));
+        let geode = GeodeModule::geodes(geode_id);
+        assert_eq!(geode.provider, new_provider);
+        assert_eq!(geode.state, pallet_geode::GeodeState::Registered);
+        assert_eq!(GeodeModule::geode_stake(geode_id), 150);
+        assert_eq!(Balances::reserved_balance(new_provider), 150);
+        assert_eq!(Balances::reserved_balance(provider), 0);
+        assert!(GeodeModule::provider_geodes(provider).is_empty());
+        assert!(GeodeModule::provider_geodes(new_provider).contains(&geode_id));
+        assert_eq!(GeodeModule::pending_transfer(geode_id), None);
+        System::assert_last_event(
+            crate::Event::GeodeTransferred(geode_id, provider, new_provider).into(),
+        );
+        assert_ok!(GeodeModule::check_invariants());
+    });
+}
+
+#[test]
+fn geode_transfer_waits_for_penalty() {
+    new_test_ext().execute_with(|| {
+        let geode_id = 3;
+        let provider = 4;
+        let new_provider = 2;
+
+        provider_register_geode(provider, geode_id);
+        assert_ok!(GeodeModule::offer_geode_transfer(
+            Origin::signed(provider),
+            geode_id,
+            new_provider
+        ));
+
+        // a slash of the geode awaits its dispute window
+        assert_eq!(GeodeModule::hold_penalty(&geode_id), 100);
+        let geode = pallet_geode::Geodes::<Test>::get(geode_id);
+        assert!(GeodeModule::transit_state(
+            &geode,
+            pallet_geode::GeodeState::Unknown,
+            pallet_geode::TransitionCause::Report
+        ));
+        assert_noop!(
+            GeodeModule::accept_geode_transfer(Origin::signed(new_provider), geode_id),
+            Error::<Test>::GeodeUnderPenalty
+        );
+
+        // the state alone holds the transfer back too
+        GeodeModule::restore_penalty(&geode_id, &provider, 100);
+        assert_eq!(GeodeModule::held_penalty(geode_id), 0);
+        assert_noop!(
+            GeodeModule::accept_geode_transfer(Origin::signed(new_provider), geode_id),
+            Error::<Test>::GeodeUnderPenalty
+        );
+
+        let geode = pallet_geode::Geodes::<Test>::get(geode_id);
+        assert!(GeodeModule::transit_state(
+            &geode,
+            pallet_geode::GeodeState::Registered,
+            pallet_geode::TransitionCause::Dispute
+        ));
+        assert_ok!(GeodeModule::accept_geode_transfer(
+            Origin::signed(new_provider),
+            geode_id
+        ));
+        assert_eq!(GeodeModule::geode_stake(geode_id), 100);
+        assert_eq!(Balances::reserved_balance(new_provider), 100);
+        assert_eq!(Balances::reserved_balance(provider), 0);
+    });
+}
+
+#[test]
+fn it_works_for_geode_capabilities() {
+    new_test_ext().execute_with(|| {
+        let provider = 4;
+        let memory = capability::MEMORY.to_vec();
+        let geode = |id, mib: u64| {
+            let mut geode = pallet_geode::Geode {
+                id,
+                provider,
+                order: None,
+                endpoints: vec![],
+                props: Default::default(),
+                state: Default::default(),
+                promise: Default::default(),
+            };
+            geode
+                .props
+                .insert(capability::MEMORY.to_vec(), mib.encode());
+            geode
+        };
+
+        assert_noop!(
+            GeodeModule::register_capability(
+                Origin::signed(provider),
+                memory.clone(),
+                CapabilityType::Number,
+                0
+            ),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_ok!(GeodeModule::register_capability(
+            Origin::root(),
+            memory.clone(),
+            CapabilityType::Number,
+            0
+        ));
+
+        let mut invalid = geode(3, 0);
+        invalid.props.insert(memory.clone(), vec![1]);
+        assert_noop!(
+            GeodeModule::provider_register_geode(Origin::signed(provider), invalid),
+            Error::<Test>::InvalidCapability
+        );
+
+        assert_ok!(GeodeModule::provider_register_geode(
+            Origin::signed(provider),
+            geode(3, 4096)
+        ));
+        assert_ok!(GeodeModule::provider_register_geode(
+            Origin::signed(provider),
+            geode(5, 1024)
+        ));
+        assert_eq!(GeodeModule::capability_value(&memory, 3), Some(4096));
+        for id in [3u64, 5].iter() {
+            let geode = GeodeModule::geodes(id);
+            assert!(GeodeModule::transit_state(
+                &geode,
+                pallet_geode::GeodeState::Attested,
+                pallet_geode::TransitionCause::Attestation
+            ));
+        }
+
+        let ids = |requirements| {
+            GeodeModule::attested_geodes_with(requirements)
+                .into_iter()
+                .map(|geode| geode.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(vec![(memory.clone(), 2048)]), vec![3]);
+        assert!(ids(vec![(capability::CPU_CORES.to_vec(), 1)]).is_empty());
+
+        // existing props get indexed for as many geodes as paid for
+        let cores = capability::CPU_CORES.to_vec();
+        for id in [3u64, 5].iter() {
+            let mut geode = GeodeModule::geodes(id);
+            geode.props.insert(cores.clone(), 8u64.encode());
+            pallet_geode::Geodes::<Test>::insert(id, geode);
+        }
+        assert_noop!(
+            GeodeModule::register_capability(
+                Origin::root(),
+                cores.clone(),
+                CapabilityType::Number,
+                1
+            ),
+            Error::<Test>::InvalidGeodeCount
+        );
+        assert_ok!(GeodeModule::register_capability(
+            Origin::root(),
+            cores.clone(),
+            CapabilityType::Number,
+            2
+        ));
+        let mut found = ids(vec![(cores.clone(), 8)]);
+        found.sort();
+        assert_eq!(found, vec![3, 5]);
+
+        assert_noop!(
+            GeodeModule::update_geode_props(
+                Origin::signed(provider),
+                3,
+                memory.clone(),
+                vec![1, 2]
+            ),
+            Error::<Test>::InvalidCapability
+        );
+        assert_ok!(GeodeModule::update_geode_props(
+            Origin::signed(provider),
+            3,
+            memory.clone(),
+            1000u64.encode()
+        ));
+        assert!(ids(vec![(memory.clone(), 2048)]).is_empty());
+
+        assert_ok!(GeodeModule::remove_capability(
+            Origin::root(),
+            memory.clone()
+        ));
+        assert_eq!(GeodeModule::capability_value(&memory, 3), None);
+    });
+}
+
+#[test]
+fn it_works_for_geode_history() {
+    new_test_ext().execute_with(|| {
+        use pallet_geode::{GeodeState, StateTransition, TransitionCause};
+        let geode_id = 3;
+        let provider = 4;
+
+        provider_register_geode(provider, geode_id);
+        System::set_block_number(5);
+        let geode = GeodeModule::geodes(geode_id);
+        assert!(GeodeModule::transit_state(
+            &geode,
+            GeodeState::Attested,
+            TransitionCause::Attestation
+        ));
+        System::set_block_number(7);
+        assert_ok!(GeodeModule::provider_offline_geodes(
+            Origin::signed(provider),
+            vec![geode_id]
+        ));
+        assert_eq!(
+            GeodeModule::geode_history(geode_id),
+            vec![
+                StateTransition {
+                    from: GeodeState::Null,
+                    to: GeodeState::Registered,
+                    block: 1,
+                    cause: TransitionCause::ProviderAction,
+                },
+                StateTransition {
+                    from: GeodeState::Registered,
+                    to: GeodeState::Attested,
+                    block: 5,
+                    cause: TransitionCause::Attestation,
+                },
+                StateTransition {
+                    from: GeodeState::Attested,
+                    to: GeodeState::Offline,
+                    block: 7,
+                    cause: TransitionCause::ProviderAction,
+                },
+            ]
+        );
+
+        // only the latest transitions are kept, also after removal
+        assert_ok!(GeodeModule::geode_remove(
+            Origin::signed(provider),
+            geode_id
+        ));
+        let history = GeodeModule::geode_history(geode_id);
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].to, GeodeState::Attested);
+        assert_eq!(history[2].to, GeodeState::Null);
+        assert_ok!(GeodeModule::check_invariants());
+    });
+}
+
+#[test]
+fn migrate_geode_stakes_to_v1() {
+    use frame_support::traits::StorageVersion;
+
+    new_test_ext().execute_with(|| {
+        let provider = 4;
+        provider_register_geode(provider, 3);
+
+        // rewind geode 3 to a geode registered before its stake was recorded
+        pallet_geode::GeodeStakes::<Test>::remove(3);
+        StorageVersion::new(0).put::<GeodeModule>();
+
+        migrations::v1::migrate::<Test>();
+
+        assert_eq!(GeodeModule::geode_stake(3), 100);
+        assert_eq!(StorageVersion::get::<GeodeModule>(), StorageVersion::new(1));
+        assert_ok!(GeodeModule::check_invariants());
+
+        // the bond of the registration gets released in full
+        assert_ok!(GeodeModule::geode_remove(Origin::signed(provider), 3));
+        assert_eq!(Balances::reserved_balance(provider), 0);
+    });
+}
+
+#[test]
+fn migrate_provider_index_to_v2() {
+    use frame_support::{
+        storage::migration::put_storage_value, traits::StorageVersion, Blake2_128Concat,
+        StorageHasher,
+    };
+    use migrations::v5::OldGeode;
+
+    new_test_ext().execute_with(|| {
+        let provider = 4;
+        provider_register_geode(provider, 3);
+
+        // rewind to a geode of the old layout registered before the provider index
+        put_storage_value(
+            b"GeodeModule",
+            b"Geodes",
+            &Blake2_128Concat::hash(&3u64.encode()),
+            OldGeode::<u64, sp_core::H256> {
+                id: 3,
+                provider,
+                order: None,
+                ip: vec![],
+                dns: vec![],
+                props: Default::default(),
+                state: pallet_geode::GeodeState::Registered,
+                promise: 0,
+            },
+        );
+        pallet_geode::ProviderGeodes::<Test>::remove(provider);
+        StorageVersion::new(1).put::<GeodeModule>();
+
+        migrations::v2::migrate::<Test>();
+
+        assert!(GeodeModule::provider_geodes(provider).contains(&3));
+        assert_eq!(StorageVersion::get::<GeodeModule>(), StorageVersion::new(2));
+    });
+}
+
+#[test]
+fn migrate_state_maps_to_v3() {
+    use frame_support::{
+        storage::migration::{get_storage_value, put_storage_value},
+        traits::StorageVersion,
+        Blake2_128Concat, StorageHasher,
+    };
+    use migrations::v5::OldGeode;
+    use pallet_geode::GeodeState;
+
+    new_test_ext().execute_with(|| {
+        provider_register_geode(4, 3);
+
+        // rewind geode 3 to an attested geode of the old layout with a stale order
+        let key = Blake2_128Concat::hash(&3u64.encode());
+        put_storage_value(
+            b"GeodeModule",
+            b"Geodes",
+            &key,
+            OldGeode::<u64, sp_core::H256> {
+                id: 3,
+                provider: 4,
+                order: Some((Default::default(), None)),
+                ip: b"10.0.0.1".to_vec(),
+                dns: vec![],
+                props: Default::default(),
+                state: GeodeState::Attested,
+                promise: 0,
+            },
+        );
+        pallet_geode::GeodeStates::<Test>::remove(GeodeState::Registered, 3);
+        put_storage_value(
+            b"GeodeModule",
+            b"AttestedGeodes",
+            &Blake2_128Concat::hash(&3u64.encode()),
+            9u32,
+        );
+        StorageVersion::new(2).put::<GeodeModule>();
+
+        migrations::v3::migrate::<Test>();
+
+        assert_eq!(GeodeModule::geode_state_since(GeodeState::Attested, 3), 9);
+        assert_eq!(StorageVersion::get::<GeodeModule>(), StorageVersion::new(3));
+        let geode: OldGeode<u64, sp_core::H256> =
+            get_storage_value(b"GeodeModule", b"Geodes", &key).unwrap();
+        assert_eq!(geode.order, None);
+
+        migrations::v5::migrate::<Test>();
+        assert_ok!(GeodeModule::check_invariants());
+    });
+}
+
+#[test]
+fn migrate_dns_challenges_to_v4() {
+    use frame_support::{
+        storage::migration::put_storage_value, traits::StorageVersion, Blake2_128Concat,
+        StorageHasher,
+    };
+    use migrations::v5::OldGeode;
+
+    new_test_ext().execute_with(|| {
+        let provider = 4;
+        provider_register_geode(provider, 3);
+
+        // rewind geode 3 to a geode of the old layout registered before names were verified
+        put_storage_value(
+            b"GeodeModule",
+            b"Geodes",
+            &Blake2_128Concat::hash(&3u64.encode()),
+            OldGeode::<u64, sp_core::H256> {
+                id: 3,
+                provider,
+                order: None,
+                ip: b"10.0.0.1".to_vec(),
+                dns: b"geode.example.com".to_vec(),
+                props: Default::default(),
+                state: pallet_geode::GeodeState::Registered,
+                promise: 0,
+            },
+        );
+        StorageVersion::new(3).put::<GeodeModule>();
+
+        migrations::v4::migrate::<Test>();
+
+        assert_eq!(
+            GeodeModule::dns_challenge(3).unwrap().dns,
+            b"geode.example.com".to_vec()
+        );
+        assert_eq!(StorageVersion::get::<GeodeModule>(), StorageVersion::new(4));
+    });
+}
+
+#[test]
+fn migrate_legacy_endpoints_to_v5() {
+    use frame_support::{
+        storage::migration::put_storage_value, traits::StorageVersion, Blake2_128Concat,
+        StorageHasher,
+    };
+    use migrations::v5::{legacy_endpoints, OldGeode};
+    use pallet_geode::GeodeState;
+
+    let tcp = |address, port| Endpoint {
+        address,
+        port,
+        protocol: Protocol::Tcp,
+    };
+    assert_eq!(
+        legacy_endpoints(b"10.0.0.1:8080", b"geode.example.com"),
+        vec![
+            tcp(Address::Ipv4([10, 0, 0, 1]), Some(8080)),
+            tcp(Address::Dns(b"geode.example.com".to_vec()), None),
+        ]
+    );
+    assert_eq!(
+        legacy_endpoints(&[10, 0, 0, 2], b""),
+        vec![tcp(Address::Ipv4([10, 0, 0, 2]), None)]
+    );
+    assert_eq!(
+        legacy_endpoints(b"/ip4/10.0.0.1/tcp/30333", b"not a name"),
+        vec![tcp(
+            Address::Multiaddr(b"/ip4/10.0.0.1/tcp/30333".to_vec()),
+            None
+        )]
+    );
+    assert_eq!(legacy_endpoints(b"10.0.0.1:99999", b""), vec![]);
+
+    new_test_ext().execute_with(|| {
+        provider_register_geode(4, 3);
+
+        // rewind geode 3 to the old layout with a stale order
+        put_storage_value(
+            b"GeodeModule",
+            b"Geodes",
+            &Blake2_128Concat::hash(&3u64.encode()),
+            OldGeode::<u64, sp_core::H256> {
+                id: 3,
+                provider: 4,
+                order: Some((Default::default(), None)),
+                ip: b"10.0.0.1:8080".to_vec(),
+                dns: vec![],
+                props: Default::default(),
+                state: GeodeState::Registered,
+                promise: 0,
+            },
+        );
+        StorageVersion::new(4).put::<GeodeModule>();
+
+        migrations::v5::migrate::<Test>();
+
+        let geode = GeodeModule::geodes(3);
+        assert_eq!(
+            geode.endpoints,
+            vec![tcp(Address::Ipv4([10, 0, 0, 1]), Some(8080))]
+        );
+        assert_eq!(geode.order, None);
+        assert_eq!(StorageVersion::get::<GeodeModule>(), StorageVersion::new(5));
+        assert_ok!(GeodeModule::check_invariants());
     });
 }
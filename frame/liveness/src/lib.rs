@@ -2,6 +2,9 @@
 
 pub use pallet::*;
 
+pub mod migrations;
+pub mod proof;
+
 #[cfg(test)]
 mod mock;
 
@@ -10,15 +13,21 @@ mod tests;
 
 #[frame_support::pallet]
 pub mod pallet {
+    use crate::proof::{self, MisconductProof, SignedProof};
     use automata_traits::AttestorAccounting;
     use core::convert::{TryFrom, TryInto};
     use frame_support::ensure;
+    use frame_support::traits::{
+        Currency, Imbalance, OnUnbalanced, ReservableCurrency, StorageVersion,
+    };
     use frame_support::{dispatch::DispatchResultWithPostInfo, pallet_prelude::*};
     use frame_system::pallet_prelude::*;
+    use pallet_attestor::attestation::verify_p256_signature;
+    use pallet_attestor::reputation::ReputationChange;
     use primitives::BlockNumber;
-    use sp_runtime::{Percent, RuntimeDebug, SaturatedConversion};
+    use sp_runtime::{traits::Saturating, Percent, RuntimeDebug, SaturatedConversion};
     use sp_std::borrow::ToOwned;
-    use sp_std::collections::btree_set::BTreeSet;
+    use sp_std::collections::{btree_map::BTreeMap, btree_set::BTreeSet};
     use sp_std::prelude::*;
 
     #[cfg(feature = "std")]
@@ -58,12 +67,97 @@ pub mod pallet {
     /// The geode struct shows its status
     #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
     #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, Default)]
-    pub struct Report<AccountId: Ord> {
+    pub struct Report<AccountId: Ord, Hash, Balance> {
         pub start: BlockNumber,
         pub attestors: BTreeSet<AccountId>,
+        /// Proof each attestor backed the report with.
+        pub proofs: BTreeMap<AccountId, SignedProof<Hash>>,
+        /// Bond each attestor posted with its report.
+        pub bonds: BTreeMap<AccountId, Balance>,
     }
 
-    pub type ReportOf<T> = Report<<T as frame_system::Config>::AccountId>;
+    /// How the misconduct reports of an attestor ended.
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, Default)]
+    pub struct ReportRecord<Balance> {
+        /// Reports whose slash stood, bond returned.
+        pub approved: u32,
+        /// Reports which expired without approval, bond forfeited.
+        pub expired: u32,
+        /// Reports overturned by a dispute of the provider, bond forfeited.
+        pub disputed: u32,
+        /// Total bond forfeited.
+        pub forfeited: Balance,
+    }
+
+    /// Final outcome of a misconduct report for its reporters.
+    #[derive(PartialEq, Eq, Clone, Copy, RuntimeDebug)]
+    pub enum ReportOutcome {
+        Approved,
+        Expired,
+        Disputed,
+    }
+
+    /// Slash of an approved report waiting out the dispute window. The geode is already
+    /// Unknown, the penalty and the reporter bonds stay reserved until it is settled.
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+    pub struct PendingSlash<AccountId: Ord, Hash, Bond, Stake> {
+        /// Approved report behind the slash.
+        pub report: Report<AccountId, Hash, Bond>,
+        /// Provider of the geode when it got slashed.
+        pub provider: AccountId,
+        /// Stake held back for the penalty.
+        pub penalty: Stake,
+        /// Attestors of the geode before the slash.
+        pub attestors: BTreeSet<AccountId>,
+        /// Owner of the service the geode was serving.
+        pub service_owner: Option<AccountId>,
+        /// Last block the provider can dispute at.
+        pub dispute_until: BlockNumber,
+        /// Counter-evidence of the provider, once disputed.
+        pub dispute: Option<Vec<u8>>,
+    }
+
+    /// Work due at a block. Deadlines are checked against the current state once due, a
+    /// deadline whose subject moved on meanwhile is dropped.
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+    pub enum Deadline<AccountId> {
+        /// Misconduct report expires. \[geode_id, report_type\]
+        Report(AccountId, u8),
+        /// Registered geode didn't get attested in time. \[geode_id, since\]
+        Attestation(AccountId, BlockNumber),
+        /// Unknown geode gets removed. \[geode_id, since\]
+        Unknown(AccountId, BlockNumber),
+        /// Degraded geode didn't get attested in time. \[geode_id, since\]
+        Degraded(AccountId, BlockNumber),
+        /// Attestor didn't notify in time. \[attestor_id\]
+        Heartbeat(AccountId),
+        /// Maintenance window of a geode ends. \[geode_id\]
+        Maintenance(AccountId),
+        /// Undisputed slash gets paid out. \[geode_id\]
+        Slash(AccountId),
+    }
+
+    pub type BalanceOf<T> = <<T as pallet_attestor::Config>::Currency as Currency<
+        <T as frame_system::Config>::AccountId,
+    >>::Balance;
+    pub type NegativeImbalanceOf<T> = <<T as pallet_attestor::Config>::Currency as Currency<
+        <T as frame_system::Config>::AccountId,
+    >>::NegativeImbalance;
+    pub type ReportOf<T> = Report<
+        <T as frame_system::Config>::AccountId,
+        <T as frame_system::Config>::Hash,
+        BalanceOf<T>,
+    >;
+    pub type PendingSlashOf<T> = PendingSlash<
+        <T as frame_system::Config>::AccountId,
+        <T as frame_system::Config>::Hash,
+        BalanceOf<T>,
+        <T as pallet_geode::Config>::Balance,
+    >;
+    pub type SignedProofOf<T> = SignedProof<<T as frame_system::Config>::Hash>;
 
     /// Configure the pallet by specifying the parameters and types on which it depends.
     #[pallet::config]
@@ -93,10 +187,43 @@ pub mod pallet {
 
         #[pallet::constant]
         type DefaultMinAttestorNum: Get<u32>;
+
+        /// Max number of blocks a single maintenance window can last.
+        #[pallet::constant]
+        type MaxMaintenanceBlocks: Get<BlockNumber>;
+
+        /// Number of maintenance blocks a geode can use within one `MaintenancePeriod`.
+        #[pallet::constant]
+        type MaintenanceAllowance: Get<BlockNumber>;
+
+        #[pallet::constant]
+        type MaintenancePeriod: Get<BlockNumber>;
+
+        /// Bond an attestor reserves with each misconduct report.
+        #[pallet::constant]
+        type ReportBond: Get<BalanceOf<Self>>;
+
+        /// Handler for the bonds of reports which expired or got overturned.
+        type ForfeitedBond: OnUnbalanced<NegativeImbalanceOf<Self>>;
+
+        /// Number of blocks a provider has to dispute the slash of its geode.
+        #[pallet::constant]
+        type DisputeWindow: Get<BlockNumber>;
+
+        /// Origin which resolves slash disputes.
+        type SlashDisputeOrigin: EnsureOrigin<Self::Origin>;
+
+        /// Max number of deadlines and promises looked at per block, the rest carries over.
+        #[pallet::constant]
+        type MaxExpiriesPerBlock: Get<u32>;
     }
 
+    /// The current storage version.
+    pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(_);
 
     // The pallet's runtime storage items.
@@ -123,10 +250,87 @@ pub mod pallet {
     #[pallet::getter(fn degrade_mode)]
     pub type DegradeMode<T: Config> = StorageValue<_, bool, ValueQuery, DefaultDegradeMode<T>>;
 
+    /// Latest approved report of a geode, kept to audit its slash.
+    #[pallet::storage]
+    #[pallet::getter(fn slash_evidence)]
+    pub type SlashEvidence<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, ReportOf<T>, OptionQuery>;
+
+    /// Slashes which can still be disputed or wait for their dispute to be resolved.
+    #[pallet::storage]
+    #[pallet::getter(fn pending_slash)]
+    pub type PendingSlashes<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, PendingSlashOf<T>, OptionQuery>;
+
+    /// Outcomes of the misconduct reports of each attestor.
+    #[pallet::storage]
+    #[pallet::getter(fn report_record)]
+    pub type ReportRecords<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, ReportRecord<BalanceOf<T>>, ValueQuery>;
+
+    /// Maintenance blocks used by a geode. \[period, used\]
+    #[pallet::storage]
+    #[pallet::getter(fn maintenance_usage)]
+    pub type MaintenanceUsage<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, (BlockNumber, BlockNumber), ValueQuery>;
+
+    /// Block at which the maintenance window of a geode ends.
+    #[pallet::storage]
+    #[pallet::getter(fn maintenance_end)]
+    pub type MaintenanceEnds<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, BlockNumber, OptionQuery>;
+
+    /// Deadlines due at a block, by the order they were queued in.
+    #[pallet::storage]
+    #[pallet::getter(fn deadline)]
+    pub type Deadlines<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        BlockNumber,
+        Blake2_128Concat,
+        u32,
+        Deadline<T::AccountId>,
+        OptionQuery,
+    >;
+
+    /// Number of deadlines queued for a block.
+    #[pallet::storage]
+    pub type DeadlineCount<T: Config> =
+        StorageMap<_, Blake2_128Concat, BlockNumber, u32, ValueQuery>;
+
+    /// Blocks with deadlines left to act on.
+    #[pallet::storage]
+    #[pallet::getter(fn deadline_blocks)]
+    pub type DeadlineBlocks<T: Config> = StorageValue<_, BTreeSet<BlockNumber>, ValueQuery>;
+
+    /// Next deadline to act on of a block only acted on in part. \[block, index\]
+    #[pallet::storage]
+    #[pallet::getter(fn deadline_cursor)]
+    pub type DeadlineCursor<T: Config> = StorageValue<_, (BlockNumber, u32), ValueQuery>;
+
+    /// Next promise block whose geodes get taken off their jobs.
+    #[pallet::storage]
+    #[pallet::getter(fn promise_cursor)]
+    pub type PromiseCursor<T: Config> = StorageValue<_, BlockNumber, ValueQuery>;
+
+    /// Block the heartbeat deadline of an attestor is queued for.
+    #[pallet::storage]
+    pub type HeartbeatDeadlines<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, BlockNumber, OptionQuery>;
+
+    /// Block degrade mode ended at.
+    #[pallet::storage]
+    #[pallet::getter(fn degrade_mode_ended)]
+    pub type DegradeModeEnded<T: Config> = StorageValue<_, BlockNumber, ValueQuery>;
+
     // Pallets use events to inform users when important changes are made.
     // https://substrate.dev/docs/en/knowledgebase/runtime/events
     #[pallet::event]
-    #[pallet::metadata(T::AccountId = "AccountId")]
+    #[pallet::metadata(
+        T::AccountId = "AccountId",
+        T::Balance = "Balance",
+        BalanceOf<T> = "Balance"
+    )]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
         /// Attestor attested a geode. \[attestor_id, geode_id\]
@@ -145,6 +349,26 @@ pub mod pallet {
         AttestorExited(T::AccountId),
         /// Storage cleaned
         StorageCleaned,
+        /// Geode entered maintenance. \[geode_id, until\]
+        MaintenanceStarted(T::AccountId, BlockNumber),
+        /// Geode left maintenance. \[geode_id\]
+        MaintenanceEnded(T::AccountId),
+        /// Geode lost stake for a misconduct. \[geode_id, slashed, to_treasury\]
+        GeodePenalized(T::AccountId, T::Balance, T::Balance),
+        /// Reporter paid from a geode penalty. \[geode_id, attestor_id, amount\]
+        ReporterRewarded(T::AccountId, T::AccountId, T::Balance),
+        /// Service owner compensated from a geode penalty. \[geode_id, owner, amount\]
+        ServiceOwnerCompensated(T::AccountId, T::AccountId, T::Balance),
+        /// Report bond returned on approval. \[attestor_id, geode_id, amount\]
+        ReportBondReturned(T::AccountId, T::AccountId, BalanceOf<T>),
+        /// Report bond forfeited on expiry or dispute. \[attestor_id, geode_id, amount\]
+        ReportBondForfeited(T::AccountId, T::AccountId, BalanceOf<T>),
+        /// Provider disputed the slash of its geode. \[geode_id, provider\]
+        SlashDisputed(T::AccountId, T::AccountId),
+        /// Dispute rejected, the slash stands. \[geode_id\]
+        SlashDisputeRejected(T::AccountId),
+        /// Dispute upheld, the slash got reversed. \[geode_id\]
+        SlashReversed(T::AccountId),
     }
 
     // Errors inform users that something went wrong.
@@ -158,152 +382,55 @@ pub mod pallet {
         InvalidReportType,
         /// Invalid Input
         InvalidInput,
+        /// Maintenance window is empty or longer than allowed.
+        MaintenanceTooLong,
+        /// Maintenance allowance of the period is used up.
+        MaintenanceAllowanceExceeded,
+        /// Proof doesn't match the report type or doesn't show a misconduct.
+        InvalidProof,
+        /// Proof isn't signed by the attestor's enclave key.
+        InvalidProofSignature,
+        /// Proof was collected too long ago.
+        StaleProof,
+        /// Geode has no slash which can be disputed.
+        NoPendingSlash,
+        /// Dispute window of the slash is over.
+        DisputeWindowClosed,
+        /// Slash is already disputed.
+        AlreadyDisputed,
+        /// Slash isn't disputed.
+        NotDisputed,
     }
 
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
-        /// 1. At every block, check if a misconduct report has expired or not,
-        /// if expired, clean the report.
-        /// 2. At every block, check if any geode haven't get attested after an expiring block,
-        /// if expired, clean the report.
+        fn on_runtime_upgrade() -> Weight {
+            crate::migrations::v1::migrate::<T>()
+        }
+
+        /// 1. Leave degrade mode once enough attestors are registered.
+        /// 2. Act on the deadlines due by now, see `Deadline`.
+        /// 3. Take geodes whose promise runs out off their jobs.
+        /// At most `MaxExpiriesPerBlock` deadlines and promises are looked at per block, the
+        /// rest carries over to the next blocks.
         fn on_initialize(block_number: T::BlockNumber) -> Weight {
+            let mut weight = T::DbWeight::get().reads(3);
             if let Ok(now) = TryInto::<BlockNumber>::try_into(block_number) {
                 // check is there a need to cancel degrade mode
+                if <DegradeMode<T>>::get()
+                    && pallet_attestor::AttestorNum::<T>::get() >= <MinAttestorNum<T>>::get()
                 {
-                    if <DegradeMode<T>>::get()
-                        && pallet_attestor::AttestorNum::<T>::get() >= <MinAttestorNum<T>>::get()
-                    {
-                        // reset all the start block num for degraded geode
-                        <pallet_geode::Module<T>>::reset_degraded_block_num();
-                        <DegradeMode<T>>::put(false);
-                    }
-                }
-
-                // clean expired reports
-                {
-                    let mut expired = Vec::<(T::AccountId, u8)>::new();
-                    <Reports<T>>::iter()
-                        .map(|(key, report)| {
-                            if (report.start + T::ReportExpiryBlockNumber::get()) < now {
-                                expired.push(key);
-                            }
-                        })
-                        .all(|_| true);
-                    for key in expired {
-                        <Reports<T>>::remove(key);
-                    }
-                }
-
-                // clean expired geodes
-                {
-                    let mut expired_geodes = Vec::<T::AccountId>::new();
-                    if !<DegradeMode<T>>::get() {
-                        pallet_geode::RegisteredGeodes::<T>::iter()
-                            .map(|(key, start)| {
-                                if start + T::AttestationExpiryBlockNumber::get() < now {
-                                    expired_geodes.push(key);
-                                }
-                            })
-                            .all(|_| true);
-                    }
-
-                    // clean expired unknown geode
-                    pallet_geode::UnknownGeodes::<T>::iter()
-                        .map(|(key, start)| {
-                            if start + T::UnknownExpiryBlockNumber::get() < now {
-                                expired_geodes.push(key);
-                            }
-                        })
-                        .all(|_| true);
-
-                    for key in expired_geodes {
-                        let geode = pallet_geode::Geodes::<T>::get(key);
-                        <pallet_geode::Module<T>>::transit_state(
-                            &geode,
-                            pallet_geode::GeodeState::Null,
-                        );
-                    }
-                }
-
-                // detach Degraded geodes
-                {
-                    if !<DegradeMode<T>>::get() {
-                        let mut expired_degraded_geodes = Vec::<T::AccountId>::new();
-                        pallet_geode::DegradedGeodes::<T>::iter()
-                            .map(|(key, start)| {
-                                if start + T::DegradedInstantiatedExpiryBlockNumber::get() < now {
-                                    expired_degraded_geodes.push(key);
-                                }
-                            })
-                            .all(|_| true);
-
-                        for key in expired_degraded_geodes {
-                            Self::slash_geode(&key)
-                        }
-                    }
-                }
-
-                // clean expired attestors
-                {
-                    let mut expired_attestors = Vec::<T::AccountId>::new();
-                    pallet_attestor::AttestorLastNotify::<T>::iter()
-                        .map(|(key, notify)| {
-                            if notify + T::AttestorNotifyTimeoutBlockNumber::get() < now {
-                                expired_attestors.push(key);
-                            }
-                        })
-                        .all(|_| true);
-
-                    for key in expired_attestors {
-                        Self::do_attestor_exit(&key);
-                    }
+                    // geodes waiting for attestors get their time counted from now
+                    <DegradeModeEnded<T>>::put(now);
+                    <DegradeMode<T>>::put(false);
+                    weight = weight.saturating_add(T::DbWeight::get().writes(2));
                 }
 
-                {
-                    // clean expired promised geodes
-                    let mut expired = Vec::<BlockNumber>::new();
-                    for (promise, geodes) in pallet_geode::PromisedGeodes::<T>::iter() {
-                        if promise != 0
-                            && promise
-                                <= now
-                                    + T::DispatchConfirmationTimeout::get()
-                                    + T::PutOnlineTimeout::get()
-                        {
-                            expired.push(promise);
-                            // remove geode from service if there is
-                            for geode in geodes {
-                                let geode_record = pallet_geode::Geodes::<T>::get(geode);
-
-                                match geode_record.state {
-                                    pallet_geode::GeodeState::Instantiated => {
-                                        Self::detach_geode_services_dispatches(&geode_record);
-                                        <pallet_geode::Module<T>>::transit_state(
-                                            &geode_record,
-                                            pallet_geode::GeodeState::Attested,
-                                        );
-                                    }
-                                    pallet_geode::GeodeState::Degraded => {
-                                        Self::detach_geode_services_dispatches(&geode_record);
-                                        <pallet_geode::Module<T>>::transit_state(
-                                            &geode_record,
-                                            pallet_geode::GeodeState::Registered,
-                                        );
-                                    }
-                                    _ => {
-                                        // do nothing
-                                    }
-                                }
-                            }
-                        } else {
-                            break;
-                        }
-                    }
-                    for promise in expired {
-                        pallet_geode::PromisedGeodes::<T>::remove(promise);
-                    }
-                }
+                let mut budget = T::MaxExpiriesPerBlock::get();
+                weight = weight.saturating_add(Self::process_deadlines(now, &mut budget));
+                weight = weight.saturating_add(Self::process_promises(now, &mut budget));
             }
-            0
+            weight
         }
     }
 
@@ -312,13 +439,14 @@ pub mod pallet {
     // Dispatchable functions must be annotated with a weight and must return a DispatchResult.
     #[pallet::call]
     impl<T: Config> Pallet<T> {
-        /// Report that somebody did a misconduct. The actual usage is being considered.
+        /// Report that a geode did a misconduct, backed by a proof matching `report_type`.
+        /// The geode gets slashed once `ReportApprovalRatio` of its attestors reported it.
         #[pallet::weight(0)]
         pub fn report_misconduct(
             origin: OriginFor<T>,
             geode_id: T::AccountId,
             report_type: u8,
-            _proof: Vec<u8>,
+            proof: SignedProofOf<T>,
         ) -> DispatchResultWithPostInfo {
             let who = ensure_signed(origin)?;
             // check attestor existance and whether attested
@@ -326,6 +454,10 @@ pub mod pallet {
                 pallet_attestor::Attestors::<T>::contains_key(&who),
                 pallet_attestor::Error::<T>::InvalidAttestor
             );
+            ensure!(
+                <pallet_attestor::Module<T>>::is_trusted(&who),
+                pallet_attestor::Error::<T>::UntrustedMeasurement
+            );
             ensure!(
                 pallet_attestor::Attestors::<T>::get(&who)
                     .geodes
@@ -386,17 +518,25 @@ pub mod pallet {
                 }
             };
 
+            Self::verify_proof(&who, &geode_id, report_type, &proof)?;
+
             let key = (geode_id.clone(), report_type);
             let mut report = ReportOf::<T>::default();
-            if <Reports<T>>::contains_key(&key) {
+            let new_report = !<Reports<T>>::contains_key(&key);
+            if !new_report {
                 report = <Reports<T>>::get(&key);
-                report.attestors.insert(who.clone());
             } else {
-                report.attestors.insert(who.clone());
                 let block_number =
                     <frame_system::Module<T>>::block_number().saturated_into::<BlockNumber>();
                 report.start = block_number;
             }
+            // a reporter bonds once, later reports only refresh its proof
+            if report.attestors.insert(who.clone()) {
+                let bond = T::ReportBond::get();
+                <T as pallet_attestor::Config>::Currency::reserve(&who, bond)?;
+                report.bonds.insert(who.clone(), bond);
+            }
+            report.proofs.insert(who.clone(), proof);
 
             // check current amount of misconduct satisfying the approval ratio
             if Percent::from_rational_approximation(
@@ -405,10 +545,27 @@ pub mod pallet {
             ) >= T::ReportApprovalRatio::get()
             {
                 // slash the geode
-                Self::slash_geode(&key.0);
+                for attestor in report.attestors.iter() {
+                    <pallet_attestor::Module<T>>::update_reputation(
+                        attestor,
+                        ReputationChange::ReportApproved,
+                    );
+                }
+                Self::slash_geode(
+                    &key.0,
+                    Some(report.clone()),
+                    pallet_geode::TransitionCause::Report,
+                );
                 <Reports<T>>::remove(&key);
+                <SlashEvidence<T>>::insert(&key.0, report);
                 Self::deposit_event(Event::SlashGeode(key.0.clone()));
             } else {
+                if new_report {
+                    Self::schedule(
+                        report.start + T::ReportExpiryBlockNumber::get() + 1,
+                        Deadline::Report(key.0.clone(), report_type),
+                    );
+                }
                 // update report storage
                 <Reports<T>>::insert(&key, report);
             }
@@ -417,6 +574,56 @@ pub mod pallet {
             Ok(().into())
         }
 
+        /// Called by provider to dispute the slash of its geode with counter-evidence, within
+        /// `DisputeWindow` blocks of the slash.
+        #[pallet::weight(0)]
+        pub fn provider_dispute_slash(
+            origin: OriginFor<T>,
+            geode: T::AccountId,
+            evidence: Vec<u8>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            let mut pending = <PendingSlashes<T>>::get(&geode).ok_or(Error::<T>::NoPendingSlash)?;
+            ensure!(pending.provider == who, pallet_geode::Error::<T>::NoRight);
+            ensure!(pending.dispute.is_none(), Error::<T>::AlreadyDisputed);
+            let now = <frame_system::Module<T>>::block_number().saturated_into::<BlockNumber>();
+            ensure!(
+                now <= pending.dispute_until,
+                Error::<T>::DisputeWindowClosed
+            );
+            ensure!(
+                !evidence.is_empty() && evidence.len() <= proof::MAX_EVIDENCE_LEN,
+                Error::<T>::InvalidInput
+            );
+
+            pending.dispute = Some(evidence);
+            <PendingSlashes<T>>::insert(&geode, pending);
+            Self::deposit_event(Event::SlashDisputed(geode, who));
+            Ok(().into())
+        }
+
+        /// Resolve the dispute of a slash. An upheld dispute reverses the slash and penalises
+        /// the reporters, otherwise the slash gets paid out.
+        #[pallet::weight(0)]
+        pub fn resolve_slash_dispute(
+            origin: OriginFor<T>,
+            geode: T::AccountId,
+            upheld: bool,
+        ) -> DispatchResultWithPostInfo {
+            T::SlashDisputeOrigin::ensure_origin(origin)?;
+            let pending = <PendingSlashes<T>>::get(&geode).ok_or(Error::<T>::NoPendingSlash)?;
+            ensure!(pending.dispute.is_some(), Error::<T>::NotDisputed);
+
+            <PendingSlashes<T>>::remove(&geode);
+            if upheld {
+                Self::reverse_slash(&geode, pending);
+            } else {
+                Self::confirm_slash(&geode, pending);
+                Self::deposit_event(Event::SlashDisputeRejected(geode));
+            }
+            Ok(().into())
+        }
+
         /// Called by attestor to attest Geode.
         #[pallet::weight(0)]
         pub fn attestor_attest_geode(
@@ -429,11 +636,19 @@ pub mod pallet {
                 pallet_attestor::Attestors::<T>::contains_key(&who),
                 pallet_attestor::Error::<T>::InvalidAttestor
             );
+            ensure!(
+                <pallet_attestor::Module<T>>::is_trusted(&who),
+                pallet_attestor::Error::<T>::UntrustedMeasurement
+            );
             let mut attestor = pallet_attestor::Attestors::<T>::get(&who);
             ensure!(
                 !attestor.geodes.contains(&geode),
                 Error::<T>::AlreadyAttestFor
             );
+            ensure!(
+                <pallet_attestor::Module<T>>::has_capacity(&attestor),
+                pallet_attestor::Error::<T>::AttestorFull
+            );
 
             // check geode existance and state
             ensure!(
@@ -466,12 +681,14 @@ pub mod pallet {
                         <pallet_geode::Module<T>>::transit_state(
                             &geode_record,
                             pallet_geode::GeodeState::Attested,
+                            pallet_geode::TransitionCause::Attestation,
                         );
                     }
                     pallet_geode::GeodeState::Degraded => {
                         <pallet_geode::Module<T>>::transit_state(
                             &geode_record,
                             pallet_geode::GeodeState::Instantiated,
+                            pallet_geode::TransitionCause::Attestation,
                         );
                     }
                     _ => {}
@@ -490,7 +707,6 @@ pub mod pallet {
                 pallet_attestor::Attestors::<T>::contains_key(&who),
                 pallet_attestor::Error::<T>::InvalidAttestor
             );
-            T::AttestorAccounting::attestor_unreserve(who.clone());
             Self::do_attestor_exit(&who);
             Ok(().into())
         }
@@ -525,12 +741,96 @@ pub mod pallet {
             match <pallet_geode::Module<T>>::transit_state(
                 &geode,
                 pallet_geode::GeodeState::Offline,
+                pallet_geode::TransitionCause::ProviderAction,
             ) {
                 true => Ok(().into()),
                 false => Err(pallet_geode::Error::<T>::InvalidTransition.into()),
             }
         }
 
+        /// Called by provider to take an attested geode down for maintenance for `blocks`
+        /// blocks. Attestors and promise are kept, the geode returns to Attested afterwards.
+        #[pallet::weight(0)]
+        pub fn provider_start_maintenance(
+            origin: OriginFor<T>,
+            geode: T::AccountId,
+            blocks: BlockNumber,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            ensure!(
+                pallet_geode::Geodes::<T>::contains_key(&geode),
+                pallet_geode::Error::<T>::InvalidGeode
+            );
+            let geode_use = pallet_geode::Geodes::<T>::get(&geode);
+            ensure!(geode_use.provider == who, pallet_geode::Error::<T>::NoRight);
+            ensure!(
+                geode_use.state == pallet_geode::GeodeState::Attested,
+                pallet_geode::Error::<T>::InvalidTransition
+            );
+            // a dispatch already picked the geode
+            ensure!(
+                !pallet_service::AwaitingDispatches::<T>::contains_key(&geode),
+                pallet_geode::Error::<T>::GeodeInWork
+            );
+            ensure!(
+                blocks > 0 && blocks <= T::MaxMaintenanceBlocks::get(),
+                Error::<T>::MaintenanceTooLong
+            );
+
+            let now = <frame_system::Module<T>>::block_number().saturated_into::<BlockNumber>();
+            let period = now / T::MaintenancePeriod::get().max(1);
+            let (used_period, used) = <MaintenanceUsage<T>>::get(&geode);
+            let used = if used_period == period { used } else { 0 };
+            ensure!(
+                used + blocks <= T::MaintenanceAllowance::get(),
+                Error::<T>::MaintenanceAllowanceExceeded
+            );
+
+            <pallet_geode::Module<T>>::transit_state(
+                &geode_use,
+                pallet_geode::GeodeState::Maintenance,
+                pallet_geode::TransitionCause::ProviderAction,
+            );
+            let until = now + blocks;
+            <MaintenanceUsage<T>>::insert(&geode, (period, used + blocks));
+            <MaintenanceEnds<T>>::insert(&geode, until);
+            Self::schedule(until, Deadline::Maintenance(geode.clone()));
+
+            Self::deposit_event(Event::MaintenanceStarted(geode, until));
+            Ok(().into())
+        }
+
+        /// Called by provider to end the maintenance of a geode early, unused blocks are
+        /// given back to the allowance.
+        #[pallet::weight(0)]
+        pub fn provider_end_maintenance(
+            origin: OriginFor<T>,
+            geode: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            ensure!(
+                pallet_geode::Geodes::<T>::contains_key(&geode),
+                pallet_geode::Error::<T>::InvalidGeode
+            );
+            let geode_use = pallet_geode::Geodes::<T>::get(&geode);
+            ensure!(geode_use.provider == who, pallet_geode::Error::<T>::NoRight);
+            ensure!(
+                geode_use.state == pallet_geode::GeodeState::Maintenance,
+                pallet_geode::Error::<T>::InvalidTransition
+            );
+
+            let now = <frame_system::Module<T>>::block_number().saturated_into::<BlockNumber>();
+            if let Some(until) = <MaintenanceEnds<T>>::take(&geode) {
+                <MaintenanceUsage<T>>::mutate(&geode, |(period, used)| {
+                    if *period == now / T::MaintenancePeriod::get().max(1) {
+                        *used = used.saturating_sub(until.saturating_sub(now));
+                    }
+                });
+            }
+            Self::finish_maintenance(&geode, pallet_geode::TransitionCause::ProviderAction);
+            Ok(().into())
+        }
+
         /// Called by root to set the min stake
         #[pallet::weight(0)]
         pub fn set_min_attestor_num(origin: OriginFor<T>, num: u32) -> DispatchResultWithPostInfo {
@@ -538,29 +838,45 @@ pub mod pallet {
             let prev_min_att_num = <MinAttestorNum<T>>::get();
             if num > prev_min_att_num {
                 let mut geodes = Vec::new();
-                for (geode, _block_num) in pallet_geode::AttestedGeodes::<T>::iter() {
+                for (geode, _block_num) in
+                    pallet_geode::GeodeStates::<T>::iter_prefix(pallet_geode::GeodeState::Attested)
+                {
                     let attestors = pallet_attestor::GeodeAttestors::<T>::get(&geode);
                     if num > attestors.len() as u32 {
                         // Self::degrade_geode(&geode);
                         geodes.push(geode);
                     }
                 }
-                for (geode, _block_num) in pallet_geode::InstantiatedGeodes::<T>::iter() {
+                for (geode, _block_num) in pallet_geode::GeodeStates::<T>::iter_prefix(
+                    pallet_geode::GeodeState::Instantiated,
+                ) {
+                    let attestors = pallet_attestor::GeodeAttestors::<T>::get(&geode);
+                    if num > attestors.len() as u32 {
+                        geodes.push(geode);
+                    }
+                }
+                for (geode, _block_num) in pallet_geode::GeodeStates::<T>::iter_prefix(
+                    pallet_geode::GeodeState::Maintenance,
+                ) {
                     let attestors = pallet_attestor::GeodeAttestors::<T>::get(&geode);
                     if num > attestors.len() as u32 {
                         geodes.push(geode);
                     }
                 }
                 for geode in geodes.iter() {
-                    Self::degrade_geode(&geode);
+                    Self::degrade_geode(&geode, pallet_geode::TransitionCause::Governance);
                 }
             } else if num < prev_min_att_num {
                 let mut geodes_use = Vec::new();
-                for (geode, _block_num) in pallet_geode::RegisteredGeodes::<T>::iter() {
+                for (geode, _block_num) in pallet_geode::GeodeStates::<T>::iter_prefix(
+                    pallet_geode::GeodeState::Registered,
+                ) {
                     let geode_use = pallet_geode::Geodes::<T>::get(&geode);
                     geodes_use.push(geode_use);
                 }
-                for (geode, _block_num) in pallet_geode::DegradedGeodes::<T>::iter() {
+                for (geode, _block_num) in
+                    pallet_geode::GeodeStates::<T>::iter_prefix(pallet_geode::GeodeState::Degraded)
+                {
                     let geode_use = pallet_geode::Geodes::<T>::get(&geode);
                     geodes_use.push(geode_use)
                 }
@@ -570,12 +886,14 @@ pub mod pallet {
                             <pallet_geode::Module<T>>::transit_state(
                                 &geode_use,
                                 pallet_geode::GeodeState::Attested,
+                                pallet_geode::TransitionCause::Governance,
                             );
                         }
                         pallet_geode::GeodeState::Degraded => {
                             <pallet_geode::Module<T>>::transit_state(
                                 &geode_use,
                                 pallet_geode::GeodeState::Instantiated,
+                                pallet_geode::TransitionCause::Governance,
                             );
                         }
                         _ => {}
@@ -600,6 +918,70 @@ pub mod pallet {
     }
 
     impl<T: Config> Pallet<T> {
+        /// Check `signed` is a fresh proof of `report_type` for `geode`, signed by the
+        /// current enclave key of `reporter`.
+        fn verify_proof(
+            reporter: &T::AccountId,
+            geode: &T::AccountId,
+            report_type: u8,
+            signed: &SignedProofOf<T>,
+        ) -> DispatchResult {
+            ensure!(
+                signed.proof.report_type() as u8 == report_type,
+                Error::<T>::InvalidProof
+            );
+            let pubkey = pallet_attestor::Attestors::<T>::get(reporter).pubkey;
+            ensure!(
+                verify_p256_signature(
+                    &pubkey,
+                    &proof::proof_message(geode, &signed.proof),
+                    &signed.signature
+                ),
+                Error::<T>::InvalidProofSignature
+            );
+
+            let now = <frame_system::Module<T>>::block_number().saturated_into::<BlockNumber>();
+            let fresh = |block: BlockNumber| {
+                block <= now && block + T::ReportExpiryBlockNumber::get() >= now
+            };
+            match &signed.proof {
+                MisconductProof::Challenge(transcript) => {
+                    ensure!(fresh(transcript.sent), Error::<T>::StaleProof);
+                    // a correct answer is no misconduct
+                    ensure!(
+                        !transcript.expected.is_empty()
+                            && transcript.response.as_ref() != Some(&transcript.expected),
+                        Error::<T>::InvalidProof
+                    );
+                }
+                MisconductProof::Service(probes) => {
+                    let order = pallet_geode::Geodes::<T>::get(geode).order;
+                    ensure!(
+                        order.map(|(service, _)| service) == Some(probes.service),
+                        Error::<T>::InvalidProof
+                    );
+                    ensure!(
+                        probes.probes.len() <= proof::MAX_PROBES
+                            && probes
+                                .probes
+                                .windows(2)
+                                .all(|pair| pair[0].block < pair[1].block),
+                        Error::<T>::InvalidProof
+                    );
+                    ensure!(
+                        probes.probes.iter().all(|probe| fresh(probe.block)),
+                        Error::<T>::StaleProof
+                    );
+                    ensure!(
+                        probes.probes.iter().filter(|probe| !probe.healthy).count()
+                            >= proof::MIN_FAILED_PROBES,
+                        Error::<T>::InvalidProof
+                    );
+                }
+            }
+            Ok(())
+        }
+
         fn detach_geode_services_dispatches(geode: &pallet_geode::GeodeOf<T>) {
             // Service related logic
             // check if any service
@@ -703,17 +1085,166 @@ pub mod pallet {
             }
         }
 
-        /// Slash geode including update storage and penalty related logics
-        fn slash_geode(key: &T::AccountId) {
+        /// Slash a geode, attestors which vouched for it without reporting it lose reputation.
+        /// The penalty of a slash backed by a report waits out the dispute window, any other
+        /// slash gets paid out right away.
+        fn slash_geode(
+            key: &T::AccountId,
+            report: Option<ReportOf<T>>,
+            cause: pallet_geode::TransitionCause,
+        ) {
             let geode = pallet_geode::Geodes::<T>::get(&key);
+            // the owner of the service the geode was serving gets compensated
+            let service_owner = geode.order.as_ref().and_then(|(service_id, _)| {
+                pallet_service::Services::<T>::try_get(service_id)
+                    .ok()
+                    .map(|service| service.owner)
+            });
             Self::detach_geode_services_dispatches(&geode);
 
-            // TODO... Penalty related logic
-            <pallet_geode::Module<T>>::transit_state(&geode, pallet_geode::GeodeState::Unknown);
+            let attestors = pallet_attestor::GeodeAttestors::<T>::get(&key);
+            let reporters = report
+                .as_ref()
+                .map(|report| report.attestors.clone())
+                .unwrap_or_default();
+            for attestor in attestors.iter() {
+                if !reporters.contains(attestor) {
+                    <pallet_attestor::Module<T>>::update_reputation(
+                        attestor,
+                        ReputationChange::GeodeSlashed,
+                    );
+                }
+            }
+
+            let penalty = <pallet_geode::Module<T>>::hold_penalty(key);
+            match report {
+                Some(report) => {
+                    let now =
+                        <frame_system::Module<T>>::block_number().saturated_into::<BlockNumber>();
+                    let dispute_until = now + T::DisputeWindow::get();
+                    <PendingSlashes<T>>::insert(
+                        key,
+                        PendingSlash {
+                            report,
+                            provider: geode.provider.clone(),
+                            penalty,
+                            attestors,
+                            service_owner,
+                            dispute_until,
+                            dispute: None,
+                        },
+                    );
+                    Self::schedule(dispute_until + 1, Deadline::Slash(key.clone()));
+                }
+                None => {
+                    Self::pay_out_penalty(key, &geode.provider, penalty, Vec::new(), service_owner);
+                }
+            }
+
+            <pallet_geode::Module<T>>::transit_state(
+                &geode,
+                pallet_geode::GeodeState::Unknown,
+                cause,
+            );
         }
 
-        /// Remove attestors while unlink the related geodes.
+        /// Slash a held penalty off the provider and report where it went.
+        fn pay_out_penalty(
+            key: &T::AccountId,
+            provider: &T::AccountId,
+            value: <T as pallet_geode::Config>::Balance,
+            reporters: Vec<T::AccountId>,
+            service_owner: Option<T::AccountId>,
+        ) {
+            let penalty = <pallet_geode::Module<T>>::settle_penalty(
+                key,
+                provider,
+                value,
+                reporters,
+                service_owner,
+            );
+            for (reporter, reward) in penalty.reporters.into_iter() {
+                Self::deposit_event(Event::ReporterRewarded(key.clone(), reporter, reward));
+            }
+            if let Some((owner, compensation)) = penalty.service_owner {
+                Self::deposit_event(Event::ServiceOwnerCompensated(
+                    key.clone(),
+                    owner,
+                    compensation,
+                ));
+            }
+            Self::deposit_event(Event::GeodePenalized(
+                key.clone(),
+                penalty.slashed,
+                penalty.treasury,
+            ));
+        }
+
+        /// Pay out a slash which stood, the reporters get their bonds back.
+        fn confirm_slash(key: &T::AccountId, pending: PendingSlashOf<T>) {
+            Self::pay_out_penalty(
+                key,
+                &pending.provider,
+                pending.penalty,
+                pending.report.attestors.iter().cloned().collect(),
+                pending.service_owner,
+            );
+            Self::settle_report_bonds(key, &pending.report, ReportOutcome::Approved);
+        }
+
+        /// Undo a slash overturned by a dispute. The provider gets the penalty back, the geode
+        /// its attestors and the state it can serve in again, the reporters lose their bonds.
+        fn reverse_slash(key: &T::AccountId, pending: PendingSlashOf<T>) {
+            <pallet_geode::Module<T>>::restore_penalty(key, &pending.provider, pending.penalty);
+            for attestor in pending.report.attestors.iter() {
+                <pallet_attestor::Module<T>>::update_reputation(
+                    attestor,
+                    ReputationChange::ReportDisputed,
+                );
+            }
+            Self::settle_report_bonds(key, &pending.report, ReportOutcome::Disputed);
+            <SlashEvidence<T>>::remove(key);
+
+            // the geode may have been removed meanwhile
+            let geode = pallet_geode::Geodes::<T>::get(key);
+            if pallet_geode::Geodes::<T>::contains_key(key)
+                && geode.state == pallet_geode::GeodeState::Unknown
+            {
+                let mut attestors = BTreeSet::<T::AccountId>::new();
+                for id in pending.attestors.iter() {
+                    // attestors which exited or filled up meanwhile stay detached
+                    if pallet_attestor::Attestors::<T>::contains_key(id) {
+                        let mut attestor = pallet_attestor::Attestors::<T>::get(id);
+                        if !<pallet_attestor::Module<T>>::has_capacity(&attestor) {
+                            continue;
+                        }
+                        attestor.geodes.insert(key.clone());
+                        pallet_attestor::Attestors::<T>::insert(id, attestor);
+                        attestors.insert(id.clone());
+                    }
+                }
+                if !attestors.is_empty() {
+                    pallet_attestor::GeodeAttestors::<T>::insert(key, &attestors);
+                }
+
+                // a service the geode served has moved on, it comes back attested at most
+                let to = if attestors.len() as u32 >= <MinAttestorNum<T>>::get() {
+                    pallet_geode::GeodeState::Attested
+                } else {
+                    pallet_geode::GeodeState::Registered
+                };
+                <pallet_geode::Module<T>>::transit_state(
+                    &geode,
+                    to,
+                    pallet_geode::TransitionCause::Dispute,
+                );
+            }
+            Self::deposit_event(Event::SlashReversed(key.clone()));
+        }
+
+        /// Remove attestors while unlink the related geodes, their stake starts unbonding.
         pub fn do_attestor_exit(key: &T::AccountId) {
+            let _ = T::AttestorAccounting::attestor_unreserve(key.clone());
             let related_geodes = <pallet_attestor::Module<T>>::attestor_remove(key.to_owned());
 
             for geode in related_geodes.iter() {
@@ -727,12 +1258,12 @@ pub mod pallet {
                 }
 
                 if <MinAttestorNum<T>>::get() > attestors.len() as u32 {
-                    Self::degrade_geode(&geode);
+                    Self::degrade_geode(&geode, pallet_geode::TransitionCause::AttestorExit);
                 }
             }
         }
 
-        fn degrade_geode(geode: &T::AccountId) {
+        fn degrade_geode(geode: &T::AccountId, cause: pallet_geode::TransitionCause) {
             let geode_use = pallet_geode::Geodes::<T>::get(&geode);
             match geode_use.state {
                 pallet_geode::GeodeState::Attested => {
@@ -741,6 +1272,7 @@ pub mod pallet {
                     <pallet_geode::Module<T>>::transit_state(
                         &geode_use,
                         pallet_geode::GeodeState::Registered,
+                        cause,
                     );
                 }
                 pallet_geode::GeodeState::Instantiated => {
@@ -754,6 +1286,15 @@ pub mod pallet {
                     <pallet_geode::Module<T>>::transit_state(
                         &geode_use,
                         pallet_geode::GeodeState::Degraded,
+                        cause,
+                    );
+                }
+                pallet_geode::GeodeState::Maintenance => {
+                    <MaintenanceEnds<T>>::remove(&geode);
+                    <pallet_geode::Module<T>>::transit_state(
+                        &geode_use,
+                        pallet_geode::GeodeState::Registered,
+                        cause,
                     );
                 }
                 _ => {
@@ -762,6 +1303,341 @@ pub mod pallet {
             }
         }
 
+        /// Bring a geode back from maintenance, to Registered if it lost too many attestors
+        /// in the meantime.
+        fn finish_maintenance(geode: &T::AccountId, cause: pallet_geode::TransitionCause) {
+            let geode_use = pallet_geode::Geodes::<T>::get(&geode);
+            if geode_use.state != pallet_geode::GeodeState::Maintenance {
+                return;
+            }
+            let attestors = pallet_attestor::GeodeAttestors::<T>::get(&geode);
+            let to = if attestors.len() as u32 >= <MinAttestorNum<T>>::get() {
+                pallet_geode::GeodeState::Attested
+            } else {
+                pallet_geode::GeodeState::Registered
+            };
+            <pallet_geode::Module<T>>::transit_state(&geode_use, to, cause);
+            Self::deposit_event(Event::MaintenanceEnded(geode.to_owned()));
+        }
+
+        /// Settle the bonds of a report. Reporters of a slash which stood get theirs back,
+        /// having got their reward out of the geode penalty, the others forfeit them.
+        fn settle_report_bonds(geode: &T::AccountId, report: &ReportOf<T>, outcome: ReportOutcome) {
+            for (attestor, bond) in report.bonds.iter() {
+                match outcome {
+                    ReportOutcome::Approved => {
+                        <T as pallet_attestor::Config>::Currency::unreserve(attestor, *bond);
+                        <ReportRecords<T>>::mutate(attestor, |record| record.approved += 1);
+                        Self::deposit_event(Event::ReportBondReturned(
+                            attestor.clone(),
+                            geode.clone(),
+                            *bond,
+                        ));
+                    }
+                    ReportOutcome::Expired | ReportOutcome::Disputed => {
+                        let (forfeited, _) =
+                            <T as pallet_attestor::Config>::Currency::slash_reserved(
+                                attestor, *bond,
+                            );
+                        let amount = forfeited.peek();
+                        T::ForfeitedBond::on_unbalanced(forfeited);
+                        <ReportRecords<T>>::mutate(attestor, |record| {
+                            if outcome == ReportOutcome::Expired {
+                                record.expired += 1;
+                            } else {
+                                record.disputed += 1;
+                            }
+                            record.forfeited = record.forfeited.saturating_add(amount);
+                        });
+                        Self::deposit_event(Event::ReportBondForfeited(
+                            attestor.clone(),
+                            geode.clone(),
+                            amount,
+                        ));
+                    }
+                }
+            }
+        }
+
+        /// Queue `deadline` for block `at`, the next block at the earliest. Returns the block
+        /// it got queued for.
+        fn schedule(at: BlockNumber, deadline: Deadline<T::AccountId>) -> BlockNumber {
+            let now = <frame_system::Module<T>>::block_number().saturated_into::<BlockNumber>();
+            let at = at.max(now + 1);
+            Self::queue(at, deadline);
+            at
+        }
+
+        /// Queue `deadline` for block `at` as is, even if `at` is due already.
+        pub(crate) fn queue(at: BlockNumber, deadline: Deadline<T::AccountId>) {
+            let index = <DeadlineCount<T>>::get(at);
+            <Deadlines<T>>::insert(at, index, deadline);
+            <DeadlineCount<T>>::insert(at, index + 1);
+            if index == 0 {
+                <DeadlineBlocks<T>>::mutate(|blocks| blocks.insert(at));
+            }
+        }
+
+        /// Act on the deadlines due by `now`, earliest block first and in the order they were
+        /// queued, as far as `budget` goes. Blocks without deadlines cost nothing.
+        fn process_deadlines(now: BlockNumber, budget: &mut u32) -> Weight {
+            let db = T::DbWeight::get();
+            let mut weight = db.reads_writes(1, 1);
+            let (mut block, mut index) = <DeadlineCursor<T>>::get();
+            while *budget > 0 {
+                // acting on deadlines queues new ones, but only after now
+                let next = <DeadlineBlocks<T>>::get().iter().next().cloned();
+                weight = weight.saturating_add(db.reads(2));
+                match next {
+                    Some(next) if next <= now => {
+                        if next != block {
+                            block = next;
+                            index = 0;
+                        }
+                    }
+                    _ => break,
+                }
+                let count = <DeadlineCount<T>>::get(block);
+                while index < count && *budget > 0 {
+                    *budget -= 1;
+                    weight = weight.saturating_add(db.reads_writes(1, 1));
+                    if let Some(deadline) = <Deadlines<T>>::take(block, index) {
+                        weight = weight.saturating_add(Self::expire(deadline, block, now));
+                    }
+                    index += 1;
+                }
+                if index < count {
+                    break;
+                }
+                <DeadlineCount<T>>::remove(block);
+                <DeadlineBlocks<T>>::mutate(|blocks| blocks.remove(&block));
+                weight = weight.saturating_add(db.writes(2));
+                block = 0;
+                index = 0;
+            }
+            <DeadlineCursor<T>>::put((block, index));
+            weight
+        }
+
+        /// Act on a deadline queued for `at`, unless what it was set for changed since.
+        fn expire(deadline: Deadline<T::AccountId>, at: BlockNumber, now: BlockNumber) -> Weight {
+            let db = T::DbWeight::get();
+            match deadline {
+                Deadline::Report(geode, report_type) => {
+                    let key = (geode, report_type);
+                    // the report may have been approved, and another one started since
+                    match <Reports<T>>::try_get(&key) {
+                        Ok(report)
+                            if report.start + T::ReportExpiryBlockNumber::get() + 1 == at =>
+                        {
+                            <Reports<T>>::remove(&key);
+                            for attestor in report.attestors.iter() {
+                                <pallet_attestor::Module<T>>::update_reputation(
+                                    attestor,
+                                    ReputationChange::ReportExpired,
+                                );
+                            }
+                            Self::settle_report_bonds(&key.0, &report, ReportOutcome::Expired);
+                            db.reads_writes(1, 1)
+                                .saturating_add(Self::bonds_weight(report.attestors.len()))
+                        }
+                        _ => db.reads(1),
+                    }
+                }
+                Deadline::Attestation(geode, since) => {
+                    Self::expire_unattested(geode, since, pallet_geode::GeodeState::Registered, now)
+                }
+                Deadline::Degraded(geode, since) => {
+                    Self::expire_unattested(geode, since, pallet_geode::GeodeState::Degraded, now)
+                }
+                Deadline::Unknown(geode, since) => {
+                    if pallet_geode::GeodeStates::<T>::try_get(
+                        pallet_geode::GeodeState::Unknown,
+                        &geode,
+                    ) != Ok(since)
+                    {
+                        return db.reads(1);
+                    }
+                    // the geode stays until its slash is settled
+                    if <PendingSlashes<T>>::contains_key(&geode) {
+                        Self::schedule(
+                            now + T::DisputeWindow::get() + 1,
+                            Deadline::Unknown(geode, since),
+                        );
+                        return db.reads_writes(4, 2);
+                    }
+                    let geode = pallet_geode::Geodes::<T>::get(geode);
+                    <pallet_geode::Module<T>>::transit_state(
+                        &geode,
+                        pallet_geode::GeodeState::Null,
+                        pallet_geode::TransitionCause::Timeout,
+                    );
+                    db.reads(3).saturating_add(Self::transition_weight())
+                }
+                Deadline::Heartbeat(attestor) => {
+                    // the attestor registered again since
+                    if <HeartbeatDeadlines<T>>::get(&attestor) != Some(at) {
+                        return db.reads(1);
+                    }
+                    if !pallet_attestor::Attestors::<T>::contains_key(&attestor) {
+                        <HeartbeatDeadlines<T>>::remove(&attestor);
+                        return db.reads_writes(2, 1);
+                    }
+                    let timeout = T::AttestorNotifyTimeoutBlockNumber::get();
+                    let notify = pallet_attestor::AttestorLastNotify::<T>::get(&attestor);
+                    if notify + timeout < now {
+                        <HeartbeatDeadlines<T>>::remove(&attestor);
+                        let geodes =
+                            pallet_attestor::Attestors::<T>::get(&attestor).geodes.len() as u64;
+                        Self::do_attestor_exit(&attestor);
+                        db.reads_writes(4 + 2 * geodes, 5 + geodes)
+                            .saturating_add(Self::transition_weight().saturating_mul(geodes))
+                    } else {
+                        let at = Self::schedule(
+                            notify + timeout + 1,
+                            Deadline::Heartbeat(attestor.clone()),
+                        );
+                        <HeartbeatDeadlines<T>>::insert(&attestor, at);
+                        db.reads_writes(5, 3)
+                    }
+                }
+                Deadline::Maintenance(geode) => {
+                    // window may have been ended or restarted since
+                    if <MaintenanceEnds<T>>::get(&geode) != Some(at) {
+                        return db.reads(1);
+                    }
+                    <MaintenanceEnds<T>>::remove(&geode);
+                    Self::finish_maintenance(&geode, pallet_geode::TransitionCause::Timeout);
+                    db.reads_writes(3, 2)
+                        .saturating_add(Self::transition_weight())
+                }
+                Deadline::Slash(geode) => match <PendingSlashes<T>>::get(&geode) {
+                    // disputed slashes wait for their resolution
+                    Some(pending)
+                        if pending.dispute.is_none() && pending.dispute_until + 1 == at =>
+                    {
+                        let reporters = pending.report.attestors.len() as u64;
+                        <PendingSlashes<T>>::remove(&geode);
+                        Self::confirm_slash(&geode, pending);
+                        db.reads_writes(4 + 2 * reporters, 5 + 2 * reporters)
+                            .saturating_add(Self::bonds_weight(reporters as usize))
+                    }
+                    _ => db.reads(1),
+                },
+            }
+        }
+
+        /// Geodes waiting for attestors in `state` only run out of time outside of degrade
+        /// mode, counted from its end at the earliest.
+        fn expire_unattested(
+            geode: T::AccountId,
+            since: BlockNumber,
+            state: pallet_geode::GeodeState,
+            now: BlockNumber,
+        ) -> Weight {
+            let db = T::DbWeight::get();
+            if pallet_geode::GeodeStates::<T>::try_get(&state, &geode) != Ok(since) {
+                return db.reads(1);
+            }
+            let (expiry, deadline) = match state {
+                pallet_geode::GeodeState::Registered => (
+                    T::AttestationExpiryBlockNumber::get(),
+                    Deadline::Attestation(geode.clone(), since),
+                ),
+                _ => (
+                    T::DegradedInstantiatedExpiryBlockNumber::get(),
+                    Deadline::Degraded(geode.clone(), since),
+                ),
+            };
+            let start = if <DegradeMode<T>>::get() {
+                now
+            } else {
+                since.max(<DegradeModeEnded<T>>::get())
+            };
+            if start + expiry >= now {
+                Self::schedule(start + expiry + 1, deadline);
+                return db.reads_writes(5, 2);
+            }
+
+            match state {
+                pallet_geode::GeodeState::Registered => {
+                    let geode = pallet_geode::Geodes::<T>::get(geode);
+                    <pallet_geode::Module<T>>::transit_state(
+                        &geode,
+                        pallet_geode::GeodeState::Null,
+                        pallet_geode::TransitionCause::Timeout,
+                    );
+                    db.reads(4).saturating_add(Self::transition_weight())
+                }
+                _ => {
+                    Self::slash_geode(&geode, None, pallet_geode::TransitionCause::Timeout);
+                    db.reads_writes(10, 6)
+                        .saturating_add(Self::transition_weight())
+                }
+            }
+        }
+
+        /// Take geodes whose promise runs out off their jobs, as far as `budget` goes.
+        fn process_promises(now: BlockNumber, budget: &mut u32) -> Weight {
+            let db = T::DbWeight::get();
+            let mut weight = db.reads_writes(1, 1);
+            let horizon = now + T::DispatchConfirmationTimeout::get() + T::PutOnlineTimeout::get();
+            // promises are only queued past the horizon, the ones before are done
+            let mut promise = match <PromiseCursor<T>>::get() {
+                0 => horizon,
+                promise => promise,
+            };
+            while promise <= horizon && *budget > 0 {
+                *budget -= 1;
+                let mut geodes = pallet_geode::PromisedGeodes::<T>::take(promise);
+                weight = weight.saturating_add(db.reads_writes(1, 1));
+
+                let rest = geodes.split_off((*budget as usize).min(geodes.len()));
+                *budget -= geodes.len() as u32;
+                for geode in geodes {
+                    weight = weight.saturating_add(Self::expire_promise(&geode));
+                }
+                if !rest.is_empty() {
+                    pallet_geode::PromisedGeodes::<T>::insert(promise, rest);
+                    weight = weight.saturating_add(db.writes(1));
+                    break;
+                }
+                promise += 1;
+            }
+            <PromiseCursor<T>>::put(promise);
+            weight
+        }
+
+        /// Detach a geode whose promise ran out from its service.
+        fn expire_promise(geode: &T::AccountId) -> Weight {
+            let geode_record = pallet_geode::Geodes::<T>::get(geode);
+            let to = match geode_record.state {
+                pallet_geode::GeodeState::Instantiated => pallet_geode::GeodeState::Attested,
+                pallet_geode::GeodeState::Degraded => pallet_geode::GeodeState::Registered,
+                _ => return T::DbWeight::get().reads(1),
+            };
+            Self::detach_geode_services_dispatches(&geode_record);
+            <pallet_geode::Module<T>>::transit_state(
+                &geode_record,
+                to,
+                pallet_geode::TransitionCause::Timeout,
+            );
+            T::DbWeight::get()
+                .reads(1)
+                .saturating_add(Self::transition_weight())
+        }
+
+        /// Estimated cost of a geode state transition, detaching its jobs included.
+        fn transition_weight() -> Weight {
+            T::DbWeight::get().reads_writes(12, 12)
+        }
+
+        /// Estimated cost of settling the bonds of `reporters` attestors.
+        fn bonds_weight(reporters: usize) -> Weight {
+            let reporters = reporters as u64;
+            T::DbWeight::get().reads_writes(3 * reporters + 1, 3 * reporters + 1)
+        }
+
         /// clean all the storage, USE WITH CARE!
         pub fn clean_storage() {
             // clean Reports
@@ -773,19 +1649,160 @@ pub mod pallet {
                     })
                     .all(|_| true);
                 for report in reports.iter() {
-                    <Reports<T>>::remove(report);
+                    // open reports hold bonds
+                    for (attestor, bond) in <Reports<T>>::take(report).bonds.iter() {
+                        <T as pallet_attestor::Config>::Currency::unreserve(attestor, *bond);
+                    }
+                }
+            }
+
+            // clean PendingSlashes
+            {
+                let mut geodes = Vec::new();
+                <PendingSlashes<T>>::iter()
+                    .map(|(key, _)| {
+                        geodes.push(key);
+                    })
+                    .all(|_| true);
+                for geode in geodes.iter() {
+                    // pending slashes hold the penalty and the bonds
+                    if let Some(pending) = <PendingSlashes<T>>::take(geode) {
+                        <pallet_geode::Module<T>>::restore_penalty(
+                            geode,
+                            &pending.provider,
+                            pending.penalty,
+                        );
+                        for (attestor, bond) in pending.report.bonds.iter() {
+                            <T as pallet_attestor::Config>::Currency::unreserve(attestor, *bond);
+                        }
+                    }
                 }
             }
 
+            // clean ReportRecords
+            {
+                let mut attestors = Vec::new();
+                <ReportRecords<T>>::iter()
+                    .map(|(key, _)| {
+                        attestors.push(key);
+                    })
+                    .all(|_| true);
+                for attestor in attestors.iter() {
+                    <ReportRecords<T>>::remove(attestor);
+                }
+            }
+
+            // clean SlashEvidence
+            {
+                let mut geodes = Vec::new();
+                <SlashEvidence<T>>::iter()
+                    .map(|(key, _)| {
+                        geodes.push(key);
+                    })
+                    .all(|_| true);
+                for geode in geodes.iter() {
+                    <SlashEvidence<T>>::remove(geode);
+                }
+            }
+
+            // clean maintenance records
+            {
+                let mut geodes = Vec::new();
+                <MaintenanceUsage<T>>::iter()
+                    .map(|(key, _)| {
+                        geodes.push(key);
+                    })
+                    .all(|_| true);
+                for geode in geodes.iter() {
+                    <MaintenanceUsage<T>>::remove(geode);
+                    <MaintenanceEnds<T>>::remove(geode);
+                }
+            }
+
+            // clean deadlines
+            {
+                let mut deadlines = Vec::new();
+                <Deadlines<T>>::iter()
+                    .map(|(block, index, _)| {
+                        deadlines.push((block, index));
+                    })
+                    .all(|_| true);
+                for (block, index) in deadlines.iter() {
+                    <Deadlines<T>>::remove(block, index);
+                }
+                let mut blocks = Vec::new();
+                <DeadlineCount<T>>::iter()
+                    .map(|(key, _)| {
+                        blocks.push(key);
+                    })
+                    .all(|_| true);
+                for block in blocks.iter() {
+                    <DeadlineCount<T>>::remove(block);
+                }
+                let mut attestors = Vec::new();
+                <HeartbeatDeadlines<T>>::iter()
+                    .map(|(key, _)| {
+                        attestors.push(key);
+                    })
+                    .all(|_| true);
+                for attestor in attestors.iter() {
+                    <HeartbeatDeadlines<T>>::remove(attestor);
+                }
+                <DeadlineBlocks<T>>::kill();
+                <DeadlineCursor<T>>::kill();
+                <PromiseCursor<T>>::kill();
+            }
+
             // reset MinAttestorNum
             <MinAttestorNum<T>>::put(T::DefaultMinAttestorNum::get());
 
             // reset DegradeMode
             <DegradeMode<T>>::put(true);
+            <DegradeModeEnded<T>>::kill();
 
             <pallet_geode::Module<T>>::clean_storage();
 
             <pallet_attestor::Module<T>>::clean_storage();
         }
     }
+
+    impl<T: Config> pallet_geode::OnGeodeStateChange<T::AccountId> for Pallet<T> {
+        fn on_geode_state_change(
+            geode: &T::AccountId,
+            to: &pallet_geode::GeodeState,
+            when: BlockNumber,
+        ) {
+            match to {
+                pallet_geode::GeodeState::Registered => {
+                    Self::schedule(
+                        when + T::AttestationExpiryBlockNumber::get() + 1,
+                        Deadline::Attestation(geode.clone(), when),
+                    );
+                }
+                pallet_geode::GeodeState::Unknown => {
+                    Self::schedule(
+                        when + T::UnknownExpiryBlockNumber::get() + 1,
+                        Deadline::Unknown(geode.clone(), when),
+                    );
+                }
+                pallet_geode::GeodeState::Degraded => {
+                    Self::schedule(
+                        when + T::DegradedInstantiatedExpiryBlockNumber::get() + 1,
+                        Deadline::Degraded(geode.clone(), when),
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    impl<T: Config> pallet_attestor::OnAttestorRegistered<T::AccountId> for Pallet<T> {
+        fn on_attestor_registered(attestor: &T::AccountId, when: BlockNumber) {
+            let at = Self::schedule(
+                when + T::AttestorNotifyTimeoutBlockNumber::get() + 1,
+                Deadline::Heartbeat(attestor.clone()),
+            );
+            <HeartbeatDeadlines<T>>::insert(attestor, at);
+        }
+    }
 }
@@ -0,0 +1,97 @@
+use crate::{
+    Config, Deadline, HeartbeatDeadlines, MaintenanceEnds, Pallet, PendingSlashes, PromiseCursor,
+    Reports,
+};
+use frame_support::{
+    traits::{Get, StorageVersion},
+    weights::Weight,
+};
+use pallet_geode::GeodeState;
+use sp_std::prelude::*;
+
+/// Queue the deadlines of the state kept before the deadline queues were introduced, and
+/// point the promise cursor at the oldest promise. Needs the geode states in `GeodeStates`.
+pub mod v1 {
+    use super::*;
+
+    pub fn migrate<T: Config>() -> Weight {
+        if StorageVersion::get::<Pallet<T>>() >= 1 {
+            return T::DbWeight::get().reads(1);
+        }
+
+        let mut reads = 2u64;
+        let mut writes = 1u64;
+        let mut deadlines = Vec::new();
+
+        let states = [
+            (
+                GeodeState::Registered,
+                T::AttestationExpiryBlockNumber::get(),
+            ),
+            (GeodeState::Unknown, T::UnknownExpiryBlockNumber::get()),
+            (
+                GeodeState::Degraded,
+                T::DegradedInstantiatedExpiryBlockNumber::get(),
+            ),
+        ];
+        for (state, expiry) in states.iter() {
+            for (geode, since) in pallet_geode::GeodeStates::<T>::iter_prefix(state) {
+                reads += 1;
+                let deadline = match state {
+                    GeodeState::Registered => Deadline::Attestation(geode, since),
+                    GeodeState::Unknown => Deadline::Unknown(geode, since),
+                    _ => Deadline::Degraded(geode, since),
+                };
+                deadlines.push((since + expiry + 1, deadline));
+            }
+        }
+        for ((geode, report_type), report) in <Reports<T>>::iter() {
+            reads += 1;
+            deadlines.push((
+                report.start + T::ReportExpiryBlockNumber::get() + 1,
+                Deadline::Report(geode, report_type),
+            ));
+        }
+        for attestor in pallet_attestor::Attestors::<T>::iter_keys() {
+            reads += 2;
+            let at = pallet_attestor::AttestorLastNotify::<T>::get(&attestor)
+                + T::AttestorNotifyTimeoutBlockNumber::get()
+                + 1;
+            <HeartbeatDeadlines<T>>::insert(&attestor, at);
+            writes += 1;
+            deadlines.push((at, Deadline::Heartbeat(attestor)));
+        }
+        for (geode, until) in <MaintenanceEnds<T>>::iter() {
+            reads += 1;
+            deadlines.push((until, Deadline::Maintenance(geode)));
+        }
+        for (geode, pending) in <PendingSlashes<T>>::iter() {
+            reads += 1;
+            // disputed slashes get settled by their resolution
+            if pending.dispute.is_none() {
+                deadlines.push((pending.dispute_until + 1, Deadline::Slash(geode)));
+            }
+        }
+
+        // deadlines already due get acted on right away
+        for (at, deadline) in deadlines {
+            reads += 2;
+            writes += 3;
+            <Pallet<T>>::queue(at, deadline);
+        }
+
+        // promises run out from the oldest one on
+        let promises = pallet_geode::PromisedGeodes::<T>::iter_keys().collect::<Vec<_>>();
+        reads += promises.len() as u64;
+        if let Some(first) = promises.into_iter().min() {
+            let promise = <PromiseCursor<T>>::get();
+            if promise == 0 || first < promise {
+                <PromiseCursor<T>>::put(first);
+            }
+        }
+
+        StorageVersion::new(1).put::<Pallet<T>>();
+
+        T::DbWeight::get().reads_writes(reads, writes)
+    }
+}
@@ -1,7 +1,7 @@
 use crate as liveness;
 use frame_support::{
     parameter_types,
-    traits::{OnFinalize, OnInitialize},
+    traits::{Currency, OnFinalize, OnInitialize, OnUnbalanced, ReservableCurrency},
 };
 use frame_system as system;
 use primitives::*;
@@ -11,11 +11,14 @@ use sp_runtime::{
     traits::{BlakeTwo256, IdentityLookup},
     Percent,
 };
+use sp_std::collections::btree_map::BTreeMap;
 
 use frame_support::dispatch::DispatchResultWithPostInfo;
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
 type Block = frame_system::mocking::MockBlock<Test>;
-use automata_traits::{AttestorAccounting, GeodeAccounting};
+use crate::proof::{ChallengeTranscript, MisconductProof, SignedProof};
+use automata_traits::{distribute_penalty, GeodeAccounting, Penalty};
+use pallet_attestor::attestation::{sha256, AttestationError, AttestationVerifier, EnclaveReport};
 
 pub const INIT_BALANCE: u64 = 100_100_100;
 
@@ -32,6 +35,7 @@ frame_support::construct_runtime!(
         GeodeModule: pallet_geode::{Pallet, Call, Storage, Event<T>},
         ServiceModule: pallet_service::{Pallet, Call, Storage, Event<T>},
         LivenessModule: liveness::{Pallet, Call, Storage, Event<T>},
+        AccountingModule: pallet_accounting::{Pallet, Call, Storage, Event<T>},
     }
 );
 
@@ -53,7 +57,7 @@ impl system::Config for Test {
     type Header = Header;
     type Event = Event;
     type BlockHashCount = BlockHashCount;
-    type DbWeight = ();
+    type DbWeight = frame_support::weights::constants::RocksDbWeight;
     type Version = ();
     type AccountData = pallet_balances::AccountData<u64>;
     type OnNewAccount = ();
@@ -92,49 +96,141 @@ where
     type OverarchingCall = Call;
 }
 
-impl AttestorAccounting for Test {
-    type AccountId = u64;
-    fn attestor_staking(who: Self::AccountId) -> DispatchResultWithPostInfo {
-        Ok(().into())
-    }
-    fn attestor_unreserve(who: Self::AccountId) -> DispatchResultWithPostInfo {
-        Ok(().into())
+parameter_types! {
+    pub const GeodePenalty: u64 = 3000;
+    pub const ReporterShare: u64 = 1000;
+    pub const ServiceOwnerShare: u64 = 600;
+}
+
+/// Account collecting what is left of geode penalties.
+pub const TREASURY: u64 = 99;
+
+pub struct Treasury;
+impl OnUnbalanced<pallet_balances::NegativeImbalance<Test>> for Treasury {
+    fn on_nonzero_unbalanced(amount: pallet_balances::NegativeImbalance<Test>) {
+        Balances::resolve_creating(&TREASURY, amount);
     }
 }
 
 impl GeodeAccounting for Test {
     type AccountId = u64;
-    fn geode_staking(who: Self::AccountId) -> DispatchResultWithPostInfo {
+    type Balance = u64;
+    fn geode_min_stake() -> Self::Balance {
+        0
+    }
+    fn geode_penalty() -> Self::Balance {
+        GeodePenalty::get()
+    }
+    fn geode_staking(who: Self::AccountId, value: Self::Balance) -> DispatchResultWithPostInfo {
+        Balances::reserve(&who, value)?;
         Ok(().into())
     }
-    fn geode_unreserve(who: Self::AccountId) -> DispatchResultWithPostInfo {
+    fn geode_unreserve(who: Self::AccountId, value: Self::Balance) -> DispatchResultWithPostInfo {
+        Balances::unreserve(&who, value);
         Ok(().into())
     }
+    fn geode_slash(who: Self::AccountId, value: Self::Balance) -> Self::Balance {
+        value - Balances::slash_reserved(&who, value).1
+    }
+    fn geode_penalize(
+        who: Self::AccountId,
+        value: Self::Balance,
+        reporters: Vec<Self::AccountId>,
+        service_owner: Option<Self::AccountId>,
+    ) -> Penalty<Self::AccountId, Self::Balance> {
+        let (slashed, _) = Balances::slash_reserved(&who, value);
+        distribute_penalty::<_, Balances, Treasury>(
+            slashed,
+            reporters,
+            ReporterShare::get(),
+            service_owner,
+            ServiceOwnerShare::get(),
+        )
+    }
+}
+
+pub const MR_ENCLAVE: [u8; 32] = [7u8; 32];
+
+/// Mock reports are `mr_enclave || report_data`.
+impl AttestationVerifier for Test {
+    fn verify(report: &[u8], _root: &[u8]) -> Result<EnclaveReport, AttestationError> {
+        if report.len() != 96 {
+            return Err(AttestationError::Malformed);
+        }
+        let mut enclave = EnclaveReport {
+            mr_enclave: [0u8; 32],
+            mr_signer: [0u8; 32],
+            report_data: [0u8; 64],
+        };
+        enclave.mr_enclave.copy_from_slice(&report[..32]);
+        enclave.report_data.copy_from_slice(&report[32..]);
+        Ok(enclave)
+    }
+}
+
+/// Build a mock attestation report of `mr_enclave` binding `pubkey`.
+pub fn attestation_report(mr_enclave: [u8; 32], pubkey: &[u8]) -> Vec<u8> {
+    let mut report = mr_enclave.to_vec();
+    report.extend_from_slice(&sha256(pubkey));
+    report.extend_from_slice(&[0u8; 32]);
+    report
+}
+
+parameter_types! {
+    pub const NotifyFreshnessBlockNumber: BlockNumber = 5;
+    pub const NotifyInterval: BlockNumber = 2;
+    pub const ReputationDecayPeriod: BlockNumber = 10;
+    pub const ReputationDecay: Percent = Percent::from_percent(50);
+    pub const MaxGeodesPerAttestor: u32 = 2;
 }
 
 impl pallet_attestor::Config for Test {
     type Event = Event;
     type Currency = Balances;
     type Call = Call;
-    type AttestorAccounting = Test;
+    type AttestorAccounting = AccountingModule;
+    type AttestationVerifier = Test;
+    type NotifyFreshnessBlockNumber = NotifyFreshnessBlockNumber;
+    type NotifyInterval = NotifyInterval;
+    type MaxGeodesPerAttestor = MaxGeodesPerAttestor;
+    type ReputationDecayPeriod = ReputationDecayPeriod;
+    type ReputationDecay = ReputationDecay;
+    type AttestorAdminOrigin = frame_system::EnsureRoot<Self::AccountId>;
+    type OnAttestorRegistered = LivenessModule;
 }
 
 parameter_types! {
     pub const DispatchConfirmationTimeout: BlockNumber = 12;
     pub const PutOnlineTimeout: BlockNumber = 40;
     pub const AttestationExpiryBlockNumber: BlockNumber = 30;
+    pub const MaxBatchSize: u32 = 4;
+    pub const MaxHistoryLength: u32 = 3;
+    pub const DnsApprovalRatio: Percent = Percent::from_percent(50);
+    pub const DnsChallengeExpiry: BlockNumber = 10;
 }
 
 impl pallet_geode::Config for Test {
     type Event = Event;
+    type Balance = u64;
     type GeodeAccounting = Test;
     type DispatchConfirmationTimeout = DispatchConfirmationTimeout;
     type PutOnlineTimeout = PutOnlineTimeout;
     type AttestationExpiryBlockNumber = AttestationExpiryBlockNumber;
+    type MaxBatchSize = MaxBatchSize;
+    type MaxHistoryLength = MaxHistoryLength;
+    type DnsApprovalRatio = DnsApprovalRatio;
+    type DnsChallengeExpiry = DnsChallengeExpiry;
+    type CapabilityAdminOrigin = frame_system::EnsureRoot<Self::AccountId>;
+    type OnGeodeStateChange = LivenessModule;
+}
+
+parameter_types! {
+    pub const MaxDispatchesPerBlock: u32 = 100;
 }
 
 impl pallet_service::Config for Test {
     type Event = Event;
+    type MaxDispatchesPerBlock = MaxDispatchesPerBlock;
 }
 
 parameter_types! {
@@ -144,6 +240,12 @@ parameter_types! {
     pub const DegradedInstantiatedExpiryBlockNumber: BlockNumber = 30;
     pub const AttestorNotifyTimeoutBlockNumber: BlockNumber = 12;
     pub const DefaultMinAttestorNum: u32 = 1;
+    pub const MaxMaintenanceBlocks: BlockNumber = 5;
+    pub const MaintenanceAllowance: BlockNumber = 8;
+    pub const MaintenancePeriod: BlockNumber = 100;
+    pub const ReportBond: u64 = 1000;
+    pub const DisputeWindow: BlockNumber = 5;
+    pub const MaxExpiriesPerBlock: u32 = 100;
 }
 
 impl liveness::Config for Test {
@@ -154,6 +256,50 @@ impl liveness::Config for Test {
     type DegradedInstantiatedExpiryBlockNumber = DegradedInstantiatedExpiryBlockNumber;
     type AttestorNotifyTimeoutBlockNumber = AttestorNotifyTimeoutBlockNumber;
     type DefaultMinAttestorNum = DefaultMinAttestorNum;
+    type MaxMaintenanceBlocks = MaxMaintenanceBlocks;
+    type MaintenanceAllowance = MaintenanceAllowance;
+    type MaintenancePeriod = MaintenancePeriod;
+    type ReportBond = ReportBond;
+    type ForfeitedBond = Treasury;
+    type DisputeWindow = DisputeWindow;
+    type SlashDisputeOrigin = frame_system::EnsureRoot<Self::AccountId>;
+    type MaxExpiriesPerBlock = MaxExpiriesPerBlock;
+}
+
+pub const ATTESTOR_STAKE: u64 = 100;
+
+parameter_types! {
+    pub RewardedAttestors: BTreeMap<u64, usize> = BTreeMap::new();
+    pub RewardedGeodes: Vec<u64> = Vec::new();
+    pub const AttestorStakingAmount: u64 = ATTESTOR_STAKE;
+    pub const GeodeStakingAmount: u64 = 0;
+    pub const TotalReward: u64 = 0;
+    pub const SlotLength: u64 = 100;
+    pub const UnbondingPeriod: u64 = 10;
+    pub const RewardRatio: u8 = 0;
+    pub const RewardEachSlot: u64 = 0;
+}
+
+impl pallet_accounting::Config for Test {
+    type Event = Event;
+    type Currency = Balances;
+    type GetAttestors = RewardedAttestors;
+    type GetGeodes = RewardedGeodes;
+    type AttestorStakingAmount = AttestorStakingAmount;
+    type GeodeStakingAmount = GeodeStakingAmount;
+    type AttestorTotalReward = TotalReward;
+    type GeodeTotalReward = TotalReward;
+    type GeodeTerminatePenalty = GeodePenalty;
+    type GeodeMisconductForAttestor = ReporterShare;
+    type GeodeMisconductForServiceUser = ServiceOwnerShare;
+    type Slash = Treasury;
+    type SlotLength = SlotLength;
+    type UnbondingPeriod = UnbondingPeriod;
+    type AttestorBasicRewardRatio = RewardRatio;
+    type CommissionRateForService = RewardRatio;
+    type CommissionRateForOnDemand = RewardRatio;
+    type AttestorRewardEachSlot = RewardEachSlot;
+    type GeodeRewardEachSlot = RewardEachSlot;
 }
 
 // Build genesis storage according to the mock runtime.
@@ -168,7 +314,11 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
     .unwrap();
 
     let mut ext = sp_io::TestExternalities::new(t);
-    ext.execute_with(|| System::set_block_number(1));
+    ext.execute_with(|| {
+        System::set_block_number(1);
+        pallet_attestor::AttestationRoot::<Test>::put(vec![1]);
+        pallet_attestor::AllowedMeasurements::<Test>::insert(MR_ENCLAVE, 0);
+    });
     ext
 }
 
@@ -184,9 +334,46 @@ pub fn events() -> Vec<Event> {
     evt
 }
 
+/// Enclave key of the attestor registered by `register_attestor`.
+pub fn attestor_key() -> p256::ecdsa::SigningKey {
+    p256::ecdsa::SigningKey::from_bytes(&[1; 32]).unwrap()
+}
+
+/// Sign `proof` against `geode` with `attestor_key`.
+pub fn signed_proof(
+    geode: <Test as system::Config>::AccountId,
+    proof: MisconductProof<<Test as system::Config>::Hash>,
+) -> SignedProof<<Test as system::Config>::Hash> {
+    use p256::ecdsa::signature::Signer;
+    let signature: p256::ecdsa::Signature =
+        attestor_key().sign(&crate::proof::proof_message(&geode, &proof));
+    SignedProof {
+        proof,
+        signature: signature.as_ref().to_vec(),
+    }
+}
+
+/// Proof that `geode` didn't answer a challenge sent at the current block.
+pub fn challenge_proof(
+    geode: <Test as system::Config>::AccountId,
+) -> SignedProof<<Test as system::Config>::Hash> {
+    signed_proof(
+        geode,
+        MisconductProof::Challenge(ChallengeTranscript {
+            nonce: [7; 32],
+            sent: System::block_number() as BlockNumber,
+            expected: vec![1],
+            response: None,
+        }),
+    )
+}
+
 pub fn register_attestor(_attestor_account: <Test as system::Config>::AccountId) {
     let url = vec![1];
-    let pubkey = vec![2];
+    let pubkey = p256::ecdsa::VerifyingKey::from(&attestor_key())
+        .to_encoded_point(false)
+        .as_bytes()
+        .to_vec();
     let min_stake = 100;
     let attestor_account = 1;
 
@@ -195,6 +382,7 @@ pub fn register_attestor(_attestor_account: <Test as system::Config>::AccountId)
         Origin::signed(attestor_account),
         url.clone(),
         pubkey.clone(),
+        attestation_report(MR_ENCLAVE, &pubkey),
     );
 }
 
@@ -209,8 +397,7 @@ pub fn provider_register_geode(
         id: geode_id,
         provider: provider,
         order: None,
-        ip: vec![],
-        dns: vec![],
+        endpoints: vec![],
         props: Default::default(),
         state: Default::default(),
         promise: Default::default(),
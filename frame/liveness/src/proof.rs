@@ -0,0 +1,82 @@
+use crate::ReportType;
+use codec::{Decode, Encode};
+use primitives::BlockNumber;
+use sp_runtime::RuntimeDebug;
+use sp_std::prelude::*;
+
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+/// Max number of health probes in a service proof.
+pub const MAX_PROBES: usize = 16;
+/// Number of failed probes a service proof needs.
+pub const MIN_FAILED_PROBES: usize = 3;
+/// Max length of the counter-evidence a provider disputes a slash with.
+pub const MAX_EVIDENCE_LEN: usize = 1024;
+
+/// What an attestor saw when challenging the enclave of a geode.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct ChallengeTranscript {
+    /// Random challenge sent to the geode.
+    pub nonce: [u8; 32],
+    /// Block the challenge was sent at.
+    pub sent: BlockNumber,
+    /// Response a healthy enclave gives.
+    pub expected: Vec<u8>,
+    /// Response the geode gave, `None` if it didn't answer.
+    pub response: Option<Vec<u8>>,
+}
+
+/// Result of one health probe against a service.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct ProbeResult {
+    pub block: BlockNumber,
+    pub healthy: bool,
+}
+
+/// Health probes of the service a geode serves, oldest first.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct ServiceProbes<Hash> {
+    pub service: Hash,
+    pub probes: Vec<ProbeResult>,
+}
+
+/// Evidence of a misconduct, one format per `ReportType`.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub enum MisconductProof<Hash> {
+    Challenge(ChallengeTranscript),
+    Service(ServiceProbes<Hash>),
+}
+
+impl<Hash> MisconductProof<Hash> {
+    pub fn report_type(&self) -> ReportType {
+        match self {
+            MisconductProof::Challenge(_) => ReportType::Challenge,
+            MisconductProof::Service(_) => ReportType::Service,
+        }
+    }
+}
+
+/// A proof signed by the enclave key of the reporting attestor.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug)]
+pub struct SignedProof<Hash> {
+    pub proof: MisconductProof<Hash>,
+    /// P-256 signature of `proof_message` for the offending geode.
+    pub signature: Vec<u8>,
+}
+
+/// Message an attestor signs to report `geode` with `proof`.
+pub fn proof_message<AccountId: Encode, Hash: Encode>(
+    geode: &AccountId,
+    proof: &MisconductProof<Hash>,
+) -> Vec<u8> {
+    let mut message = b"automata/liveness/misconduct".to_vec();
+    message.extend_from_slice(&geode.encode());
+    message.extend_from_slice(&proof.encode());
+    message
+}
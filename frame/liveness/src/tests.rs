@@ -1,5 +1,7 @@
+use crate::proof::{ChallengeTranscript, MisconductProof, SignedProof};
 use crate::{mock::*, Error, ReportType};
 use frame_support::{assert_noop, assert_ok};
+use pallet_attestor::reputation::REPUTATION_NEUTRAL;
 use pallet_attestor::Attestor;
 
 #[test]
@@ -73,13 +75,84 @@ fn it_attestor_attest_geode_invalid_geode() {
     });
 }
 
+#[test]
+fn it_attestor_attest_geode_attestor_full() {
+    new_test_ext().execute_with(|| {
+        let attestor_account = 1;
+
+        register_attestor(attestor_account);
+        for geode_account in 2..5 {
+            provider_register_geode(attestor_account, geode_account);
+        }
+
+        assert_ok!(LivenessModule::attestor_attest_geode(
+            Origin::signed(attestor_account),
+            2
+        ));
+        assert_ok!(LivenessModule::attestor_attest_geode(
+            Origin::signed(attestor_account),
+            3
+        ));
+
+        // capacity of 2 geodes reached
+        assert_noop!(
+            LivenessModule::attestor_attest_geode(Origin::signed(attestor_account), 4),
+            pallet_attestor::Error::<Test>::AttestorFull
+        );
+    });
+}
+
+#[test]
+fn it_stops_attestors_of_revoked_measurement() {
+    new_test_ext().execute_with(|| {
+        let attestor_account = 1;
+        let geode_account = 2;
+
+        register_attestor(attestor_account);
+        provider_register_geode(attestor_account, geode_account);
+        provider_register_geode(attestor_account, 3);
+        assert_ok!(LivenessModule::attestor_attest_geode(
+            Origin::signed(attestor_account),
+            geode_account
+        ));
+
+        assert_ok!(AttestorModule::revoke_measurement(
+            Origin::root(),
+            MR_ENCLAVE
+        ));
+        assert_noop!(
+            LivenessModule::attestor_attest_geode(Origin::signed(attestor_account), 3),
+            pallet_attestor::Error::<Test>::UntrustedMeasurement
+        );
+        assert_noop!(
+            LivenessModule::report_misconduct(
+                Origin::signed(attestor_account),
+                geode_account,
+                ReportType::Challenge as u8,
+                vec![]
+            ),
+            pallet_attestor::Error::<Test>::UntrustedMeasurement
+        );
+
+        // it can't send heartbeats either, so it times out and its geodes degrade
+        run_to_block(1 + AttestorNotifyTimeoutBlockNumber::get() + 1);
+        assert!(!pallet_attestor::Attestors::<Test>::contains_key(
+            attestor_account
+        ));
+        assert_eq!(
+            GeodeModule::geodes(geode_account).state,
+            pallet_geode::GeodeState::Registered
+        );
+    });
+}
+
 #[test]
 fn it_works_report_misconduct() {
     new_test_ext().execute_with(|| {
         let attestor_account = 1;
         let geode_account = 2;
         let report_type = ReportType::Challenge;
-        let proof: Vec<u8> = vec![];
+        let proof = challenge_proof(geode_account);
 
         register_attestor(attestor_account);
         provider_register_geode(attestor_account, geode_account);
@@ -99,6 +172,355 @@ fn it_works_report_misconduct() {
 
         let unknown_geode = GeodeModule::geodes(geode_account);
         assert_eq!(unknown_geode.state, pallet_geode::GeodeState::Unknown);
+        // the approved report is kept with its proof
+        let evidence = LivenessModule::slash_evidence(geode_account).unwrap();
+        assert_eq!(
+            evidence.proofs.get(&attestor_account),
+            Some(&challenge_proof(geode_account))
+        );
+
+        // approved reporters gain reputation
+        assert_eq!(
+            AttestorModule::attestor_reputation(attestor_account),
+            REPUTATION_NEUTRAL + 20
+        );
+    });
+}
+
+#[test]
+fn it_works_report_misconduct_penalty() {
+    new_test_ext().execute_with(|| {
+        let attestor_account = 1;
+        let provider_account = 2;
+        let geode_account = 3;
+
+        register_attestor(attestor_account);
+        provider_register_geode(provider_account, geode_account);
+        assert_ok!(GeodeModule::provider_stake_geode(
+            Origin::signed(provider_account),
+            geode_account,
+            5000
+        ));
+        assert_ok!(LivenessModule::attestor_attest_geode(
+            Origin::signed(attestor_account),
+            geode_account
+        ));
+
+        let reporter_free = Balances::free_balance(attestor_account);
+        assert_ok!(LivenessModule::report_misconduct(
+            Origin::signed(attestor_account),
+            geode_account,
+            ReportType::Challenge as u8,
+            challenge_proof(geode_account)
+        ));
+
+        // the penalty and the bond are held while the slash can be disputed
+        assert_eq!(
+            GeodeModule::geode_stake(geode_account),
+            5000 - GeodePenalty::get()
+        );
+        assert_eq!(Balances::reserved_balance(provider_account), 5000);
+        assert_eq!(
+            Balances::reserved_balance(attestor_account),
+            ReportBond::get()
+        );
+        assert!(LivenessModule::pending_slash(geode_account).is_some());
+
+        run_to_block(DisputeWindow::get() + 2);
+
+        // the penalty leaves the provider's stake, the reporter gets its share and the
+        // treasury the rest as no service was affected
+        assert!(LivenessModule::pending_slash(geode_account).is_none());
+        assert_eq!(GeodeModule::geode_stake(geode_account), 2000);
+        assert_eq!(Balances::reserved_balance(provider_account), 2000);
+        assert_eq!(
+            Balances::free_balance(attestor_account),
+            reporter_free + 1000
+        );
+        assert_eq!(Balances::free_balance(TREASURY), 2000);
+        // the bond came back
+        assert_eq!(Balances::reserved_balance(attestor_account), 0);
+        assert_eq!(LivenessModule::report_record(attestor_account).approved, 1);
+        // too late to dispute
+        assert_noop!(
+            LivenessModule::provider_dispute_slash(
+                Origin::signed(provider_account),
+                geode_account,
+                vec![1]
+            ),
+            Error::<Test>::NoPendingSlash
+        );
+
+        let evts = events();
+        assert!(
+            evts.contains(&Event::LivenessModule(crate::Event::ReporterRewarded(
+                geode_account,
+                attestor_account,
+                1000
+            )))
+        );
+        assert!(
+            evts.contains(&Event::LivenessModule(crate::Event::GeodePenalized(
+                geode_account,
+                3000,
+                2000
+            )))
+        );
+    });
+}
+
+#[test]
+fn it_works_dispute_slash() {
+    new_test_ext().execute_with(|| {
+        let attestor_account = 1;
+        let provider_account = 2;
+        let geode_account = 3;
+
+        register_attestor(attestor_account);
+        provider_register_geode(provider_account, geode_account);
+        assert_ok!(GeodeModule::provider_stake_geode(
+            Origin::signed(provider_account),
+            geode_account,
+            5000
+        ));
+        assert_ok!(LivenessModule::attestor_attest_geode(
+            Origin::signed(attestor_account),
+            geode_account
+        ));
+        assert_eq!(
+            GeodeModule::geodes(geode_account).state,
+            pallet_geode::GeodeState::Attested
+        );
+        // a second attestor of the geode, which fills up before the dispute is resolved
+        let full_attestor = 5;
+        pallet_attestor::Attestors::<Test>::insert(
+            full_attestor,
+            Attestor {
+                geodes: vec![geode_account].into_iter().collect(),
+                ..Default::default()
+            },
+        );
+        pallet_attestor::GeodeAttestors::<Test>::mutate(geode_account, |attestors| {
+            attestors.insert(full_attestor);
+        });
+        let reporter_free = Balances::free_balance(attestor_account);
+        assert_ok!(LivenessModule::report_misconduct(
+            Origin::signed(attestor_account),
+            geode_account,
+            ReportType::Challenge as u8,
+            challenge_proof(geode_account)
+        ));
+        assert_eq!(
+            GeodeModule::geodes(geode_account).state,
+            pallet_geode::GeodeState::Unknown
+        );
+
+        // only the provider disputes, only once and with evidence
+        assert_noop!(
+            LivenessModule::resolve_slash_dispute(Origin::root(), geode_account, true),
+            Error::<Test>::NotDisputed
+        );
+        assert_noop!(
+            LivenessModule::provider_dispute_slash(
+                Origin::signed(attestor_account),
+                geode_account,
+                vec![1]
+            ),
+            pallet_geode::Error::<Test>::NoRight
+        );
+        assert_noop!(
+            LivenessModule::provider_dispute_slash(
+                Origin::signed(provider_account),
+                geode_account,
+                vec![]
+            ),
+            Error::<Test>::InvalidInput
+        );
+        assert_ok!(LivenessModule::provider_dispute_slash(
+            Origin::signed(provider_account),
+            geode_account,
+            vec![1]
+        ));
+        assert_noop!(
+            LivenessModule::provider_dispute_slash(
+                Origin::signed(provider_account),
+                geode_account,
+                vec![2]
+            ),
+            Error::<Test>::AlreadyDisputed
+        );
+
+        // a disputed slash isn't paid out when the window closes
+        run_to_block(DisputeWindow::get() + 2);
+        assert!(LivenessModule::pending_slash(geode_account).is_some());
+
+        assert_noop!(
+            LivenessModule::resolve_slash_dispute(
+                Origin::signed(attestor_account),
+                geode_account,
+                true
+            ),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        pallet_attestor::Attestors::<Test>::mutate(full_attestor, |attestor| {
+            attestor.geodes = vec![10, 11].into_iter().collect();
+        });
+        assert_ok!(LivenessModule::resolve_slash_dispute(
+            Origin::root(),
+            geode_account,
+            true
+        ));
+
+        // the geode is back with its attestor and its full stake
+        assert!(LivenessModule::pending_slash(geode_account).is_none());
+        assert!(LivenessModule::slash_evidence(geode_account).is_none());
+        assert_eq!(
+            GeodeModule::geodes(geode_account).state,
+            pallet_geode::GeodeState::Attested
+        );
+        assert!(
+            pallet_attestor::GeodeAttestors::<Test>::get(geode_account).contains(&attestor_account)
+        );
+        assert!(AttestorModule::attestors(attestor_account)
+            .geodes
+            .contains(&geode_account));
+        // the attestor without capacity left stays detached
+        assert!(
+            !pallet_attestor::GeodeAttestors::<Test>::get(geode_account).contains(&full_attestor)
+        );
+        assert_eq!(AttestorModule::attestors(full_attestor).geodes.len(), 2);
+        assert_eq!(GeodeModule::geode_stake(geode_account), 5000);
+        assert_eq!(Balances::reserved_balance(provider_account), 5000);
+
+        // the reporter lost its bond
+        assert_eq!(Balances::reserved_balance(attestor_account), 0);
+        assert_eq!(
+            Balances::free_balance(attestor_account),
+            reporter_free - ReportBond::get()
+        );
+        assert_eq!(Balances::free_balance(TREASURY), ReportBond::get());
+        let record = LivenessModule::report_record(attestor_account);
+        assert_eq!(record.disputed, 1);
+        assert_eq!(record.approved, 0);
+    });
+}
+
+#[test]
+fn it_forfeits_bond_of_expired_report() {
+    new_test_ext().execute_with(|| {
+        let attestor_account = 1;
+        let geode_account = 2;
+
+        register_attestor(attestor_account);
+        provider_register_geode(attestor_account, geode_account);
+        assert_ok!(LivenessModule::attestor_attest_geode(
+            Origin::signed(attestor_account),
+            geode_account
+        ));
+        // two more attestors which never back the report
+        pallet_attestor::GeodeAttestors::<Test>::mutate(geode_account, |attestors| {
+            attestors.insert(5);
+            attestors.insert(6);
+        });
+
+        let free = Balances::free_balance(attestor_account);
+        assert_ok!(LivenessModule::report_misconduct(
+            Origin::signed(attestor_account),
+            geode_account,
+            ReportType::Challenge as u8,
+            challenge_proof(geode_account)
+        ));
+        assert_eq!(
+            Balances::reserved_balance(attestor_account),
+            ReportBond::get()
+        );
+        // reporting again only refreshes the proof
+        assert_ok!(LivenessModule::report_misconduct(
+            Origin::signed(attestor_account),
+            geode_account,
+            ReportType::Challenge as u8,
+            challenge_proof(geode_account)
+        ));
+        assert_eq!(
+            Balances::reserved_balance(attestor_account),
+            ReportBond::get()
+        );
+
+        run_to_block(ReportExpiryBlockNumber::get() + 2);
+
+        assert_eq!(
+            LivenessModule::reports((geode_account, ReportType::Challenge as u8)),
+            Default::default()
+        );
+        assert_eq!(Balances::reserved_balance(attestor_account), 0);
+        assert_eq!(
+            Balances::free_balance(attestor_account),
+            free - ReportBond::get()
+        );
+        assert_eq!(Balances::free_balance(TREASURY), ReportBond::get());
+        let record = LivenessModule::report_record(attestor_account);
+        assert_eq!(record.expired, 1);
+        assert_eq!(record.forfeited, ReportBond::get());
+    });
+}
+
+#[test]
+fn it_report_misconduct_invalid_proof() {
+    new_test_ext().execute_with(|| {
+        let attestor_account = 1;
+        let geode_account = 2;
+        let report = |proof| {
+            LivenessModule::report_misconduct(
+                Origin::signed(attestor_account),
+                geode_account,
+                ReportType::Challenge as u8,
+                proof,
+            )
+        };
+        let transcript = |sent, response| {
+            MisconductProof::Challenge(ChallengeTranscript {
+                nonce: [7; 32],
+                sent,
+                expected: vec![1],
+                response,
+            })
+        };
+
+        register_attestor(attestor_account);
+        provider_register_geode(attestor_account, geode_account);
+        assert_ok!(LivenessModule::attestor_attest_geode(
+            Origin::signed(attestor_account),
+            geode_account
+        ));
+        run_to_block(ReportExpiryBlockNumber::get() + 2);
+
+        // signed for another geode
+        assert_noop!(
+            report(SignedProof {
+                signature: challenge_proof(3).signature,
+                ..challenge_proof(geode_account)
+            }),
+            Error::<Test>::InvalidProofSignature
+        );
+        // the geode answered correctly
+        let now = System::block_number() as u32;
+        assert_noop!(
+            report(signed_proof(geode_account, transcript(now, Some(vec![1])))),
+            Error::<Test>::InvalidProof
+        );
+        // challenged too long ago
+        assert_noop!(
+            report(signed_proof(geode_account, transcript(1, None))),
+            Error::<Test>::StaleProof
+        );
+        assert_ok!(report(signed_proof(
+            geode_account,
+            transcript(now, Some(vec![2]))
+        )));
+        assert_eq!(
+            GeodeModule::geodes(geode_account).state,
+            pallet_geode::GeodeState::Unknown
+        );
     });
 }
 
@@ -108,7 +530,7 @@ fn it_report_misconduct_invalid_attestor() {
         let attestor_account = 1;
         let geode_account = 2;
         let report_type = ReportType::Challenge;
-        let proof: Vec<u8> = vec![];
+        let proof = challenge_proof(geode_account);
 
         // attestor not registered
         assert_noop!(
@@ -129,7 +551,7 @@ fn it_report_misconduct_not_attesting_for() {
         let attestor_account = 1;
         let geode_account = 2;
         let report_type = ReportType::Challenge;
-        let proof: Vec<u8> = vec![];
+        let proof = challenge_proof(geode_account);
         register_attestor(attestor_account);
 
         // attestor not for the geode
@@ -152,7 +574,7 @@ fn it_report_misconduct_invalid_report_type() {
         let geode_account = 2;
         // wrong report type
         let report_type = 100_u8;
-        let proof: Vec<u8> = vec![];
+        let proof = challenge_proof(geode_account);
 
         register_attestor(attestor_account);
         provider_register_geode(attestor_account, geode_account);
@@ -175,15 +597,305 @@ fn it_report_misconduct_invalid_report_type() {
     });
 }
 
-// #[test]
-// fn it_attestor_cleaned_after_expiration() {
-//     new_test_ext().execute_with(|| {
-//         let attestor_account = 1;
-//         register_attestor(attestor_account);
+#[test]
+fn it_works_for_geode_maintenance() {
+    new_test_ext().execute_with(|| {
+        let attestor_account = 1;
+        let geode_account = 2;
+
+        register_attestor(attestor_account);
+        provider_register_geode(attestor_account, geode_account);
+        assert_ok!(LivenessModule::attestor_attest_geode(
+            Origin::signed(attestor_account),
+            geode_account
+        ));
+
+        assert_noop!(
+            LivenessModule::provider_start_maintenance(Origin::signed(3), geode_account, 5),
+            pallet_geode::Error::<Test>::NoRight
+        );
+        assert_noop!(
+            LivenessModule::provider_start_maintenance(
+                Origin::signed(attestor_account),
+                geode_account,
+                MaxMaintenanceBlocks::get() + 1
+            ),
+            Error::<Test>::MaintenanceTooLong
+        );
+
+        assert_ok!(LivenessModule::provider_start_maintenance(
+            Origin::signed(attestor_account),
+            geode_account,
+            5
+        ));
+        System::assert_last_event(crate::Event::MaintenanceStarted(geode_account, 6).into());
+        assert_eq!(
+            GeodeModule::geodes(geode_account).state,
+            pallet_geode::GeodeState::Maintenance
+        );
+        // attestor links are kept
+        assert!(AttestorModule::geode_attestors(geode_account).contains(&attestor_account));
+        assert_eq!(GeodeModule::check_invariants(), Ok(()));
+
+        // back to Attested once the window ends
+        run_to_block(6);
+        assert_eq!(
+            GeodeModule::geodes(geode_account).state,
+            pallet_geode::GeodeState::Attested
+        );
+        System::assert_has_event(crate::Event::MaintenanceEnded(geode_account).into());
+        assert_eq!(LivenessModule::maintenance_end(geode_account), None);
+
+        // only 3 blocks of the allowance are left in this period
+        assert_noop!(
+            LivenessModule::provider_start_maintenance(
+                Origin::signed(attestor_account),
+                geode_account,
+                5
+            ),
+            Error::<Test>::MaintenanceAllowanceExceeded
+        );
+        assert_ok!(LivenessModule::provider_start_maintenance(
+            Origin::signed(attestor_account),
+            geode_account,
+            3
+        ));
+
+        // ending early gives back the unused blocks
+        run_to_block(7);
+        assert_ok!(LivenessModule::provider_end_maintenance(
+            Origin::signed(attestor_account),
+            geode_account
+        ));
+        assert_eq!(LivenessModule::maintenance_usage(geode_account), (0, 6));
+        assert_eq!(
+            GeodeModule::geodes(geode_account).state,
+            pallet_geode::GeodeState::Attested
+        );
+        assert_eq!(GeodeModule::check_invariants(), Ok(()));
+    });
+}
+
+#[test]
+fn it_keeps_on_initialize_weight_flat_with_many_geodes() {
+    use frame_support::traits::OnInitialize;
+
+    // weight of an idle block and of the block the attestations of the fleet run out at
+    let weights = |fleet: u64| {
+        new_test_ext().execute_with(|| {
+            register_attestor(1);
+            run_to_block(2);
+            for geode in 10..10 + fleet {
+                // one geode per provider keeps the provider sets small
+                provider_register_geode(geode, geode);
+            }
+            let weight_at = |n: u32| {
+                run_to_block(n - 1);
+                System::set_block_number(n as u64);
+                LivenessModule::on_initialize(n as u64)
+            };
+
+            let idle = weight_at(10);
+            let due = 2 + AttestationExpiryBlockNumber::get() + 1;
+            let busy = weight_at(due);
+            let registered = || {
+                pallet_geode::GeodeStates::<Test>::iter_prefix(pallet_geode::GeodeState::Registered)
+                    .count() as u64
+            };
+            // the rest carries over to the next blocks
+            assert_eq!(registered(), fleet - MaxExpiriesPerBlock::get() as u64);
+            run_to_block(due + (fleet / MaxExpiriesPerBlock::get() as u64) as u32 + 2);
+            assert_eq!(registered(), 0);
 
-//         run_to_block(AttestorNotifyTimeoutBlockNumber::get() + 1);
+            (idle, busy)
+        })
+    };
 
-//         assert_eq!(pallet_attestor::Attestors::<Test>::contains_key(attestor_account), false);
+    let (idle, busy) = weights(1_000);
+    assert!(busy > idle);
+    assert_eq!(weights(10_000), (idle, busy));
+}
+
+#[test]
+fn migrate_deadlines_to_v1() {
+    use crate::migrations;
+    use frame_support::traits::StorageVersion;
+
+    new_test_ext().execute_with(|| {
+        let attestor_account = 1;
+        let geode_account = 2;
+        let unattested_geode = 3;
+        let report_key = (geode_account, ReportType::Challenge as u8);
 
-//     })
-// }
+        register_attestor(attestor_account);
+        run_to_block(2);
+        provider_register_geode(attestor_account, geode_account);
+        provider_register_geode(attestor_account, unattested_geode);
+        assert_ok!(LivenessModule::attestor_attest_geode(
+            Origin::signed(attestor_account),
+            geode_account
+        ));
+        pallet_attestor::GeodeAttestors::<Test>::mutate(geode_account, |attestors| {
+            attestors.insert(5);
+            attestors.insert(6);
+        });
+        assert_ok!(LivenessModule::report_misconduct(
+            Origin::signed(attestor_account),
+            geode_account,
+            ReportType::Challenge as u8,
+            challenge_proof(geode_account)
+        ));
+        pallet_geode::PromisedGeodes::<Test>::insert(3, vec![geode_account]);
+
+        // rewind to the state kept before the deadline queues
+        crate::Deadlines::<Test>::drain().for_each(drop);
+        crate::DeadlineCount::<Test>::drain().for_each(drop);
+        crate::HeartbeatDeadlines::<Test>::drain().for_each(drop);
+        crate::DeadlineBlocks::<Test>::kill();
+        crate::DeadlineCursor::<Test>::kill();
+        crate::PromiseCursor::<Test>::kill();
+        StorageVersion::new(0).put::<LivenessModule>();
+
+        migrations::v1::migrate::<Test>();
+        assert_eq!(
+            StorageVersion::get::<LivenessModule>(),
+            StorageVersion::new(1)
+        );
+        assert_eq!(LivenessModule::promise_cursor(), 3);
+
+        run_to_block(3);
+        assert!(!pallet_geode::PromisedGeodes::<Test>::contains_key(3));
+
+        // report started at 2
+        run_to_block(2 + ReportExpiryBlockNumber::get());
+        assert!(crate::Reports::<Test>::contains_key(&report_key));
+        run_to_block(2 + ReportExpiryBlockNumber::get() + 1);
+        assert!(!crate::Reports::<Test>::contains_key(&report_key));
+        assert_eq!(LivenessModule::report_record(attestor_account).expired, 1);
+
+        // attestor last notified at 1
+        run_to_block(1 + AttestorNotifyTimeoutBlockNumber::get());
+        assert!(pallet_attestor::Attestors::<Test>::contains_key(
+            attestor_account
+        ));
+        run_to_block(1 + AttestorNotifyTimeoutBlockNumber::get() + 1);
+        assert!(!pallet_attestor::Attestors::<Test>::contains_key(
+            attestor_account
+        ));
+
+        // geode registered at 2
+        let registered = || {
+            pallet_geode::GeodeStates::<Test>::contains_key(
+                pallet_geode::GeodeState::Registered,
+                unattested_geode,
+            )
+        };
+        run_to_block(2 + AttestationExpiryBlockNumber::get());
+        assert!(registered());
+        run_to_block(2 + AttestationExpiryBlockNumber::get() + 1);
+        assert!(!registered());
+    });
+}
+
+#[test]
+fn migrate_deadlines_far_in_the_past_to_v1() {
+    use crate::migrations;
+    use frame_support::traits::StorageVersion;
+
+    new_test_ext().execute_with(|| {
+        let attestor_account = 1;
+        let geode_account = 2;
+        let unattested_geode = 3;
+        let report_key = (geode_account, ReportType::Challenge as u8);
+
+        // the attestor last notified at 1, long before the upgrade
+        register_attestor(attestor_account);
+        System::set_block_number(4990);
+        provider_register_geode(attestor_account, geode_account);
+        provider_register_geode(attestor_account, unattested_geode);
+        assert_ok!(LivenessModule::attestor_attest_geode(
+            Origin::signed(attestor_account),
+            geode_account
+        ));
+        pallet_attestor::GeodeAttestors::<Test>::mutate(geode_account, |attestors| {
+            attestors.insert(5);
+            attestors.insert(6);
+        });
+        assert_ok!(LivenessModule::report_misconduct(
+            Origin::signed(attestor_account),
+            geode_account,
+            ReportType::Challenge as u8,
+            challenge_proof(geode_account)
+        ));
+
+        // rewind to the state kept before the deadline queues
+        crate::Deadlines::<Test>::drain().for_each(drop);
+        crate::DeadlineCount::<Test>::drain().for_each(drop);
+        crate::HeartbeatDeadlines::<Test>::drain().for_each(drop);
+        crate::DeadlineBlocks::<Test>::kill();
+        crate::DeadlineCursor::<Test>::kill();
+        crate::PromiseCursor::<Test>::kill();
+        StorageVersion::new(0).put::<LivenessModule>();
+        migrations::v1::migrate::<Test>();
+
+        // the missed heartbeat is acted on right away
+        run_to_block(4991);
+        assert!(!pallet_attestor::Attestors::<Test>::contains_key(
+            attestor_account
+        ));
+
+        // and doesn't hold up the deadlines after it
+        run_to_block(4990 + ReportExpiryBlockNumber::get());
+        assert!(crate::Reports::<Test>::contains_key(&report_key));
+        run_to_block(4990 + ReportExpiryBlockNumber::get() + 1);
+        assert!(!crate::Reports::<Test>::contains_key(&report_key));
+
+        // degrade mode ended at 4991
+        let registered = || {
+            pallet_geode::GeodeStates::<Test>::contains_key(
+                pallet_geode::GeodeState::Registered,
+                unattested_geode,
+            )
+        };
+        run_to_block(4991 + AttestationExpiryBlockNumber::get());
+        assert!(registered());
+        run_to_block(4991 + AttestationExpiryBlockNumber::get() + 1);
+        assert!(!registered());
+    });
+}
+
+#[test]
+fn it_unbonds_stake_of_timed_out_attestor() {
+    new_test_ext().execute_with(|| {
+        let attestor_account = 1;
+        let geode_account = 2;
+
+        register_attestor(attestor_account);
+        provider_register_geode(attestor_account, geode_account);
+        assert_ok!(LivenessModule::attestor_attest_geode(
+            Origin::signed(attestor_account),
+            geode_account
+        ));
+        assert_eq!(Balances::reserved_balance(attestor_account), ATTESTOR_STAKE);
+
+        run_to_block(1 + AttestorNotifyTimeoutBlockNumber::get() + 1);
+        assert!(!pallet_attestor::Attestors::<Test>::contains_key(
+            attestor_account
+        ));
+        assert_eq!(
+            AccountingModule::bonded(attestor_account, pallet_accounting::StakeKind::Attestor),
+            0
+        );
+
+        // the stake stays slashable for the unbonding period
+        assert_noop!(
+            AccountingModule::withdraw_unbonded(Origin::signed(attestor_account)),
+            pallet_accounting::Error::<Test>::NothingToWithdraw
+        );
+        System::set_block_number(System::block_number() + UnbondingPeriod::get());
+        assert_ok!(AccountingModule::withdraw_unbonded(Origin::signed(
+            attestor_account
+        )));
+        assert_eq!(Balances::reserved_balance(attestor_account), 0);
+    });
+}
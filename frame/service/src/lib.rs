@@ -3,11 +3,19 @@
 
 pub use pallet::*;
 
+pub mod migrations;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
 #[frame_support::pallet]
 pub mod pallet {
     use codec::{Decode, Encode};
     use core::convert::TryInto;
-    use frame_support::pallet_prelude::*;
+    use frame_support::{pallet_prelude::*, traits::StorageVersion};
     use frame_system::pallet_prelude::*;
     use primitives::{BlockNumber, DispatchId};
     use sp_core::H256;
@@ -27,7 +35,7 @@ pub mod pallet {
     /// The service order struct proposed by the user
     #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
     #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, Default)]
-    pub struct Order {
+    pub struct Order<Balance> {
         /// Service data.
         pub binary: Vec<u8>,
         /// Service dns.
@@ -38,6 +46,8 @@ pub mod pallet {
         pub duration: BlockNumber,
         /// maximum number of geodes to serve the order
         pub geode_num: u32,
+        /// maximum per-block price of a geode the order accepts
+        pub max_price: Balance,
     }
 
     /// Geode state
@@ -120,6 +130,7 @@ pub mod pallet {
         pub state: ServiceState,
     }
 
+    pub type OrderOf<T> = Order<<T as pallet_geode::Config>::Balance>;
     pub type ServiceOf<T> =
         Service<<T as frame_system::Config>::AccountId, <T as frame_system::Config>::Hash>;
     pub type DispatchOf<T> =
@@ -128,11 +139,20 @@ pub mod pallet {
     #[pallet::config]
     pub trait Config: frame_system::Config + pallet_geode::Config {
         type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+        /// Pending dispatches looked at for a geode each block at most.
+        #[pallet::constant]
+        type MaxDispatchesPerBlock: Get<u32>;
     }
 
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_runtime_upgrade() -> Weight {
+            crate::migrations::v1::migrate::<T>()
+        }
+
         fn on_initialize(block_number: T::BlockNumber) -> Weight {
+            let db = T::DbWeight::get();
+            let mut weight: Weight = 0;
             if let Ok(now) = TryInto::<BlockNumber>::try_into(block_number) {
                 // process pending service orders
                 {
@@ -140,19 +160,37 @@ pub mod pallet {
                     let mut avail_geodes = BTreeMap::<BlockNumber, Vec<T::AccountId>>::new();
                     // let mut avail_promises = Vec::<T::BlockNumber>::new();
                     let mut updated_geodes = BTreeMap::<BlockNumber, Vec<T::AccountId>>::new();
+                    let mut prices = BTreeMap::<T::AccountId, T::Balance>::new();
                     pallet_geode::PromisedGeodes::<T>::iter()
                         .map(|(promise, geodes)| {
+                            for geode in geodes.iter() {
+                                prices.insert(
+                                    geode.clone(),
+                                    pallet_geode::GeodePrices::<T>::get(geode),
+                                );
+                            }
                             avail_geodes.insert(promise.clone().into(), geodes);
                         })
                         .all(|_| true);
+                    weight = weight.saturating_add(
+                        db.reads(avail_geodes.len() as Weight + prices.len() as Weight),
+                    );
+                    let mut cheapest = prices.values().min().cloned();
 
                     let mut processed_services = Vec::<u32>::new();
+                    let mut looked_at = 0u32;
                     for (dispatch, order_id) in <PendingDispatchesQueue<T>>::iter() {
-                        if avail_geodes.is_empty() {
+                        if avail_geodes.is_empty() || looked_at >= T::MaxDispatchesPerBlock::get() {
                             break;
                         }
+                        looked_at += 1;
 
                         let order = <Orders<T>>::get(order_id);
+                        weight = weight.saturating_add(db.reads(2));
+                        // no geode left is cheap enough, don't search
+                        if cheapest.map_or(true, |price| price > order.max_price) {
+                            continue;
+                        }
 
                         let geode;
 
@@ -160,24 +198,35 @@ pub mod pallet {
                             + T::PutOnlineTimeout::get()
                             + T::DispatchConfirmationTimeout::get();
                         let expected_promise = min_promise + order.duration;
-                        let promise;
-                        if let Some(entry) = avail_geodes.range(expected_promise..).next() {
-                            // try to find the smallest larger geode
-                            promise = *entry.0;
-                        } else if avail_geodes.contains_key(&0) {
-                            promise = 0;
-                        } else {
-                            if let Some(entry) =
-                                avail_geodes.range(min_promise..expected_promise).last()
-                            {
-                                // else find the largest smaller geode
-                                promise = *entry.0;
-                            } else {
-                                break;
+
+                        // cheapest geode the order can afford, ties broken by promise fit
+                        let mut best: Option<(T::Balance, (u8, BlockNumber), BlockNumber, usize)> =
+                            None;
+                        for (promise, geodes) in avail_geodes.iter() {
+                            let fit =
+                                match Self::promise_fit(*promise, min_promise, expected_promise) {
+                                    Some(fit) => fit,
+                                    None => continue,
+                                };
+                            for (index, geode) in geodes.iter().enumerate() {
+                                let price = prices.get(geode).cloned().unwrap_or_default();
+                                if price > order.max_price {
+                                    continue;
+                                }
+                                if best.map_or(true, |(p, f, _, _)| (price, fit) < (p, f)) {
+                                    best = Some((price, fit, *promise, index));
+                                }
                             }
                         }
-
-                        geode = avail_geodes.get_mut(&promise).unwrap().remove(0);
+                        // leave the dispatch pending, later orders may still fit
+                        let (promise, index) = match best {
+                            Some((_, _, promise, index)) => (promise, index),
+                            None => continue,
+                        };
+
+                        geode = avail_geodes.get_mut(&promise).unwrap().remove(index);
+                        prices.remove(&geode);
+                        cheapest = prices.values().min().cloned();
                         updated_geodes
                             .insert(promise.clone(), avail_geodes.get(&promise).unwrap().clone());
 
@@ -197,7 +246,11 @@ pub mod pallet {
                         <Dispatches<T>>::insert(&dispatch, dispatch_use);
 
                         Self::deposit_event(Event::DispatchQueriedGeode(dispatch, geode));
+                        weight = weight.saturating_add(db.reads_writes(1, 3));
                     }
+                    weight = weight.saturating_add(db.writes(
+                        updated_geodes.len() as Weight + processed_services.len() as Weight,
+                    ));
                     // handling the updated geode maps in batch
                     for (p, v) in updated_geodes.iter() {
                         if v.is_empty() {
@@ -230,6 +283,7 @@ pub mod pallet {
                             <pallet_geode::Module<T>>::transit_state(
                                 &geode_use,
                                 pallet_geode::GeodeState::Unknown,
+                                pallet_geode::TransitionCause::Timeout,
                             );
                             // clean from AwaitingDispatches
                             expired.push(geode);
@@ -260,6 +314,7 @@ pub mod pallet {
                             <pallet_geode::Module<T>>::transit_state(
                                 &geode_use,
                                 pallet_geode::GeodeState::Unknown,
+                                pallet_geode::TransitionCause::Timeout,
                             );
                             // TODO: punish geode
 
@@ -290,7 +345,7 @@ pub mod pallet {
 
                 // clean expired terminated services records
             }
-            0
+            weight
         }
     }
 
@@ -338,13 +393,16 @@ pub mod pallet {
         WrongDispatch,
     }
 
+    pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(_);
 
     #[pallet::storage]
     #[pallet::getter(fn orders)]
-    pub type Orders<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, Order, ValueQuery>;
+    pub type Orders<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, OrderOf<T>, ValueQuery>;
 
     #[pallet::storage]
     #[pallet::getter(fn services)]
@@ -431,7 +489,7 @@ pub mod pallet {
         #[pallet::weight(0)]
         pub fn user_create_service(
             origin: OriginFor<T>,
-            service_order: Order,
+            service_order: OrderOf<T>,
         ) -> DispatchResultWithPostInfo {
             ensure!(service_order.geode_num >= 1, Error::<T>::InvalidService);
             ensure!(
@@ -597,7 +655,8 @@ pub mod pallet {
             ensure!(
                 <pallet_geode::Module<T>>::transit_state(
                     &geode_use,
-                    pallet_geode::GeodeState::Instantiated
+                    pallet_geode::GeodeState::Instantiated,
+                    pallet_geode::TransitionCause::Service
                 ),
                 pallet_geode::Error::<T>::InvalidTransition
             );
@@ -756,12 +815,14 @@ pub mod pallet {
                     ret = <pallet_geode::Module<T>>::transit_state(
                         &geode_use,
                         pallet_geode::GeodeState::Attested,
+                        pallet_geode::TransitionCause::ProviderAction,
                     );
                 }
                 pallet_geode::GeodeState::Degraded => {
                     ret = <pallet_geode::Module<T>>::transit_state(
                         &geode_use,
                         pallet_geode::GeodeState::Registered,
+                        pallet_geode::TransitionCause::ProviderAction,
                     );
                 }
                 _ => {
@@ -788,6 +849,25 @@ pub mod pallet {
             r
         }
 
+        /// Rank how well a geode promise fits an order, lower is better. The smallest promise
+        /// covering the order comes first, then endless promises, then the largest promise
+        /// short of it. `None` if the geode can't be dispatched in time.
+        fn promise_fit(
+            promise: BlockNumber,
+            min_promise: BlockNumber,
+            expected_promise: BlockNumber,
+        ) -> Option<(u8, BlockNumber)> {
+            if promise >= expected_promise {
+                Some((0, promise - expected_promise))
+            } else if promise == 0 {
+                Some((1, 0))
+            } else if promise >= min_promise {
+                Some((2, expected_promise - promise))
+            } else {
+                None
+            }
+        }
+
         pub fn get_updated_weighted_uptime(
             prev_weighted_uptime: u64,
             last_update: BlockNumber,
@@ -0,0 +1,45 @@
+use crate::{Config, Order, Orders, Pallet};
+use codec::{Decode, Encode};
+use frame_support::{
+    traits::{Get, StorageVersion},
+    weights::Weight,
+};
+use primitives::BlockNumber;
+use sp_runtime::traits::Bounded;
+use sp_std::prelude::*;
+
+/// Orders placed before geodes had prices accept any price.
+pub mod v1 {
+    use super::*;
+
+    #[derive(Encode, Decode)]
+    struct OldOrder {
+        binary: Vec<u8>,
+        dns: Vec<u8>,
+        name: Option<Vec<u8>>,
+        duration: BlockNumber,
+        geode_num: u32,
+    }
+
+    pub fn migrate<T: Config>() -> Weight {
+        if StorageVersion::get::<Pallet<T>>() >= 1 {
+            return T::DbWeight::get().reads(1);
+        }
+
+        let mut orders = 0u64;
+        <Orders<T>>::translate::<OldOrder, _>(|_, old| {
+            orders += 1;
+            Some(Order {
+                binary: old.binary,
+                dns: old.dns,
+                name: old.name,
+                duration: old.duration,
+                geode_num: old.geode_num,
+                max_price: T::Balance::max_value(),
+            })
+        });
+
+        StorageVersion::new(1).put::<Pallet<T>>();
+        T::DbWeight::get().reads_writes(orders + 1, orders + 1)
+    }
+}
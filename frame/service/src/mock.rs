@@ -0,0 +1,243 @@
+use crate as pallet_service;
+use frame_support::{parameter_types, traits::ReservableCurrency};
+use frame_system as system;
+use primitives::*;
+use sp_core::H256;
+use sp_runtime::{
+    testing::Header,
+    traits::{BlakeTwo256, IdentityLookup},
+};
+
+use frame_support::dispatch::DispatchResultWithPostInfo;
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+use automata_traits::{distribute_penalty, AttestorAccounting, GeodeAccounting, Penalty};
+use pallet_attestor::attestation::{AttestationError, AttestationVerifier, EnclaveReport};
+
+pub const INIT_BALANCE: u64 = 100_100_100;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+    pub enum Test where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+        Balances: pallet_balances::{Pallet, Call, Storage, Event<T>},
+        AttestorModule: pallet_attestor::{Pallet, Call, Storage, Event<T>},
+        GeodeModule: pallet_geode::{Pallet, Call, Storage, Event<T>},
+        ServiceModule: pallet_service::{Pallet, Call, Storage, Event<T>},
+    }
+);
+
+parameter_types! {
+    pub const BlockHashCount: u64 = 250;
+    pub const SS58Prefix: u8 = 42;
+}
+
+impl system::Config for Test {
+    type BaseCallFilter = frame_support::traits::Everything;
+    type Origin = Origin;
+    type Call = Call;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = Event;
+    type BlockHashCount = BlockHashCount;
+    type DbWeight = ();
+    type Version = ();
+    type AccountData = pallet_balances::AccountData<u64>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type PalletInfo = PalletInfo;
+    type BlockWeights = ();
+    type BlockLength = ();
+    type SS58Prefix = SS58Prefix;
+    type OnSetCode = ();
+}
+
+parameter_types! {
+    pub const ExistentialDeposit: u64 = 500;
+}
+
+impl pallet_balances::Config for Test {
+    type MaxLocks = ();
+    /// The type for recording an account's balance.
+    type Balance = u64;
+    /// The ubiquitous event type.
+    type Event = Event;
+    type DustRemoval = ();
+    type ExistentialDeposit = ExistentialDeposit;
+    type AccountStore = System;
+    type WeightInfo = ();
+    type MaxReserves = ();
+    type ReserveIdentifier = [u8; 8];
+}
+
+impl AttestorAccounting for Test {
+    type AccountId = u64;
+    fn attestor_staking(_who: Self::AccountId) -> DispatchResultWithPostInfo {
+        Ok(().into())
+    }
+    fn attestor_unreserve(_who: Self::AccountId) -> DispatchResultWithPostInfo {
+        Ok(().into())
+    }
+}
+
+impl GeodeAccounting for Test {
+    type AccountId = u64;
+    type Balance = u64;
+    fn geode_min_stake() -> Self::Balance {
+        0
+    }
+    fn geode_penalty() -> Self::Balance {
+        0
+    }
+    fn geode_staking(who: Self::AccountId, value: Self::Balance) -> DispatchResultWithPostInfo {
+        Balances::reserve(&who, value)?;
+        Ok(().into())
+    }
+    fn geode_unreserve(who: Self::AccountId, value: Self::Balance) -> DispatchResultWithPostInfo {
+        Balances::unreserve(&who, value);
+        Ok(().into())
+    }
+    fn geode_slash(who: Self::AccountId, value: Self::Balance) -> Self::Balance {
+        value - Balances::slash_reserved(&who, value).1
+    }
+    fn geode_penalize(
+        who: Self::AccountId,
+        value: Self::Balance,
+        reporters: Vec<Self::AccountId>,
+        service_owner: Option<Self::AccountId>,
+    ) -> Penalty<Self::AccountId, Self::Balance> {
+        let (slashed, _) = Balances::slash_reserved(&who, value);
+        distribute_penalty::<_, Balances, ()>(slashed, reporters, 0, service_owner, 0)
+    }
+}
+
+impl<C> frame_system::offchain::SendTransactionTypes<C> for Test
+where
+    Call: From<C>,
+{
+    type Extrinsic = UncheckedExtrinsic;
+    type OverarchingCall = Call;
+}
+
+/// No attestors are needed to dispatch orders.
+impl AttestationVerifier for Test {
+    fn verify(_report: &[u8], _root: &[u8]) -> Result<EnclaveReport, AttestationError> {
+        Err(AttestationError::Malformed)
+    }
+}
+
+parameter_types! {
+    pub const NotifyFreshnessBlockNumber: BlockNumber = 5;
+    pub const NotifyInterval: BlockNumber = 2;
+    pub const ReputationDecayPeriod: BlockNumber = 10;
+    pub const ReputationDecay: sp_runtime::Percent = sp_runtime::Percent::from_percent(50);
+    pub const MaxGeodesPerAttestor: u32 = 3;
+}
+
+impl pallet_attestor::Config for Test {
+    type Event = Event;
+    type Currency = Balances;
+    type Call = Call;
+    type AttestorAccounting = Test;
+    type AttestationVerifier = Test;
+    type NotifyFreshnessBlockNumber = NotifyFreshnessBlockNumber;
+    type NotifyInterval = NotifyInterval;
+    type MaxGeodesPerAttestor = MaxGeodesPerAttestor;
+    type ReputationDecayPeriod = ReputationDecayPeriod;
+    type ReputationDecay = ReputationDecay;
+    type AttestorAdminOrigin = frame_system::EnsureRoot<Self::AccountId>;
+    type OnAttestorRegistered = ();
+}
+
+parameter_types! {
+    pub const DispatchConfirmationTimeout: BlockNumber = 12;
+    pub const PutOnlineTimeout: BlockNumber = 40;
+    pub const AttestationExpiryBlockNumber: BlockNumber = 30;
+    pub const MaxBatchSize: u32 = 4;
+    pub const MaxHistoryLength: u32 = 3;
+    pub const DnsApprovalRatio: sp_runtime::Percent = sp_runtime::Percent::from_percent(50);
+    pub const DnsChallengeExpiry: BlockNumber = 10;
+}
+
+impl pallet_geode::Config for Test {
+    type Event = Event;
+    type Balance = u64;
+    type GeodeAccounting = Test;
+    type DispatchConfirmationTimeout = DispatchConfirmationTimeout;
+    type PutOnlineTimeout = PutOnlineTimeout;
+    type AttestationExpiryBlockNumber = AttestationExpiryBlockNumber;
+    type MaxBatchSize = MaxBatchSize;
+    type MaxHistoryLength = MaxHistoryLength;
+    type DnsApprovalRatio = DnsApprovalRatio;
+    type DnsChallengeExpiry = DnsChallengeExpiry;
+    type CapabilityAdminOrigin = frame_system::EnsureRoot<Self::AccountId>;
+    type OnGeodeStateChange = ();
+}
+
+parameter_types! {
+    pub const MaxDispatchesPerBlock: u32 = 3;
+}
+
+impl pallet_service::Config for Test {
+    type Event = Event;
+    type MaxDispatchesPerBlock = MaxDispatchesPerBlock;
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut t = system::GenesisConfig::default()
+        .build_storage::<Test>()
+        .unwrap();
+    pallet_balances::GenesisConfig::<Test> {
+        balances: vec![(1, INIT_BALANCE), (2, INIT_BALANCE)],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+
+    let mut ext = sp_io::TestExternalities::new(t);
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
+
+/// Offer `geode` for dispatching with an endless promise at `price` per block.
+pub fn promise_geode(geode: <Test as system::Config>::AccountId, price: u64) {
+    pallet_geode::PromisedGeodes::<Test>::append(0, geode);
+    pallet_geode::GeodePrices::<Test>::insert(geode, price);
+}
+
+/// Queue a dispatch of a new order accepting up to `max_price` per block.
+pub fn pending_dispatch(dispatch: DispatchId, max_price: u64) -> H256 {
+    let order_id = H256::repeat_byte(dispatch as u8);
+    crate::Orders::<Test>::insert(
+        order_id,
+        crate::Order {
+            binary: vec![],
+            dns: vec![],
+            name: None,
+            duration: crate::MIN_ORDER_DURATION,
+            geode_num: 1,
+            max_price,
+        },
+    );
+    crate::Dispatches::<Test>::insert(
+        dispatch,
+        crate::Dispatch {
+            dispatch_id: dispatch,
+            service_id: order_id,
+            geode: None,
+            state: crate::DispatchState::Pending,
+        },
+    );
+    crate::PendingDispatchesQueue::<Test>::insert(dispatch, order_id);
+    order_id
+}
@@ -0,0 +1,104 @@
+use crate::{mock::*, DispatchState};
+use frame_support::traits::OnInitialize;
+
+#[test]
+fn dispatch_goes_to_cheapest_geode() {
+    new_test_ext().execute_with(|| {
+        promise_geode(10, 5);
+        promise_geode(11, 2);
+        promise_geode(12, 8);
+        let order_id = pending_dispatch(1, 10);
+
+        ServiceModule::on_initialize(1);
+
+        assert_eq!(ServiceModule::awaiting_dispatch(11), (order_id, 1, 1));
+        let dispatch = ServiceModule::dispatch_states(1);
+        assert_eq!(dispatch.geode, Some(11));
+        assert_eq!(dispatch.state, DispatchState::Awaiting);
+        assert!(!crate::PendingDispatchesQueue::<Test>::contains_key(1));
+        assert_eq!(GeodeModule::promised_geodes(0), vec![10, 12]);
+    });
+}
+
+#[test]
+fn dispatch_respects_max_price() {
+    new_test_ext().execute_with(|| {
+        promise_geode(10, 20);
+        let cheap_order = pending_dispatch(1, 10);
+        let order_id = pending_dispatch(2, 20);
+
+        ServiceModule::on_initialize(1);
+
+        // the order which can't afford the geode stays pending
+        assert_eq!(ServiceModule::pending_dispatches(1), cheap_order);
+        assert_eq!(ServiceModule::dispatch_states(1).geode, None);
+        assert_eq!(ServiceModule::awaiting_dispatch(10), (order_id, 1, 2));
+        assert!(!pallet_geode::PromisedGeodes::<Test>::contains_key(0));
+
+        // and is picked up once an affordable geode shows up
+        promise_geode(11, 10);
+        ServiceModule::on_initialize(2);
+        assert_eq!(ServiceModule::awaiting_dispatch(11), (cheap_order, 2, 1));
+        assert!(!crate::PendingDispatchesQueue::<Test>::contains_key(1));
+    });
+}
+
+#[test]
+fn dispatches_are_bounded_per_block() {
+    new_test_ext().execute_with(|| {
+        for n in 0..5 {
+            promise_geode(10 + n, 1);
+            pending_dispatch(n as u32 + 1, 1);
+        }
+
+        ServiceModule::on_initialize(1);
+        assert_eq!(
+            crate::PendingDispatchesQueue::<Test>::iter().count() as u32,
+            5 - MaxDispatchesPerBlock::get()
+        );
+
+        ServiceModule::on_initialize(2);
+        assert_eq!(crate::PendingDispatchesQueue::<Test>::iter().count(), 0);
+        assert!(!pallet_geode::PromisedGeodes::<Test>::contains_key(0));
+    });
+}
+
+#[test]
+fn migrate_orders_to_v1() {
+    use codec::Encode;
+    use frame_support::traits::StorageVersion;
+
+    new_test_ext().execute_with(|| {
+        let order_id = sp_core::H256::repeat_byte(1);
+        // an order stored before it had a maximum price
+        let old = (
+            vec![1u8],
+            vec![2u8],
+            Some(vec![3u8]),
+            crate::MIN_ORDER_DURATION,
+            2u32,
+        );
+        frame_support::storage::unhashed::put_raw(
+            &crate::Orders::<Test>::hashed_key_for(order_id),
+            &old.encode(),
+        );
+        StorageVersion::new(0).put::<ServiceModule>();
+
+        crate::migrations::v1::migrate::<Test>();
+        assert_eq!(
+            StorageVersion::get::<ServiceModule>(),
+            StorageVersion::new(1)
+        );
+        assert_eq!(
+            ServiceModule::orders(order_id),
+            crate::Order {
+                binary: vec![1],
+                dns: vec![2],
+                name: Some(vec![3]),
+                duration: crate::MIN_ORDER_DURATION,
+                geode_num: 2,
+                max_price: u64::max_value(),
+            }
+        );
+    });
+}
@@ -7,7 +7,7 @@
 
 use std::sync::Arc;
 
-use node_template_runtime::{opaque::Block, AccountId, Balance, Index};
+use node_template_runtime::{opaque::Block, AccountId, Balance, Hash, Index};
 pub use sc_rpc_api::DenyUnsafe;
 use sc_transaction_pool_api::TransactionPool;
 use sp_api::ProvideRuntimeApi;
@@ -35,9 +35,13 @@ where
     C::Api: BlockBuilder<Block>,
     C::Api: pallet_daoportal_rpc::DAOPortalRuntimeApi<Block, AccountId>,
     C::Api: pallet_gmetadata_rpc::GmetadataRuntimeApi<Block>,
+    C::Api: pallet_attestor_rpc::AttestorRuntimeApi<Block, AccountId>,
+    C::Api: pallet_geode_rpc::GeodeRuntimeApi<Block, AccountId, Hash>,
     P: TransactionPool + 'static,
 {
+    use pallet_attestor_rpc::{AttestorApi, AttestorClient};
     use pallet_daoportal_rpc::{DAOPortalApi, DAOPortalClient};
+    use pallet_geode_rpc::{GeodeApi, GeodeClient};
     use pallet_gmetadata_rpc::{GmetadataApi, GmetadataClient};
     use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApi};
     use substrate_frame_rpc_system::{FullSystem, SystemApi};
@@ -67,5 +71,11 @@ where
         client.clone(),
     )));
 
+    io.extend_with(AttestorApi::to_delegate(AttestorClient::new(
+        client.clone(),
+    )));
+
+    io.extend_with(GeodeApi::to_delegate(GeodeClient::new(client.clone())));
+
     io
 }
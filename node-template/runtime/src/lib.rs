@@ -41,7 +41,7 @@ pub use pallet_timestamp::Call as TimestampCall;
 use pallet_transaction_payment::CurrencyAdapter;
 #[cfg(any(feature = "std", test))]
 pub use sp_runtime::BuildStorage;
-pub use sp_runtime::{Perbill, Permill};
+pub use sp_runtime::{Perbill, Percent, Permill};
 
 /// Import the template pallet.
 pub use pallet_template;
@@ -340,6 +340,100 @@ impl pallet_gmetadata::Config for Runtime {
     type MaxIndexLength = MaxIndexLength;
 }
 
+impl<C> frame_system::offchain::SendTransactionTypes<C> for Runtime
+where
+    Call: From<C>,
+{
+    type OverarchingCall = Call;
+    type Extrinsic = UncheckedExtrinsic;
+}
+
+parameter_types! {
+    pub const AttestorStakingAmount: Balance = 1000;
+    pub const GeodeStakingAmount: Balance = 1000;
+    pub const GeodeTerminatePenalty: Balance = 1000;
+    pub const GeodeMisconductForAttestor: Balance = 300;
+    pub const GeodeMisconductForServiceUser: Balance = 300;
+    pub const UnbondingPeriod: BlockNumber = 7 * DAYS;
+    pub const SlotLength: BlockNumber = DAYS;
+    // the node template pays no staking rewards
+    pub const TotalReward: Balance = Balance::MAX;
+    pub const RewardEachSlot: Balance = 0;
+    pub const AttestorBasicRewardRatio: u8 = 100;
+    pub const CommissionRate: u8 = 0;
+}
+
+impl pallet_accounting::Config for Runtime {
+    type Event = Event;
+    type Currency = Balances;
+    type GetAttestors = Attestor;
+    type GetGeodes = Geode;
+    type AttestorStakingAmount = AttestorStakingAmount;
+    type GeodeStakingAmount = GeodeStakingAmount;
+    type AttestorTotalReward = TotalReward;
+    type GeodeTotalReward = TotalReward;
+    type GeodeTerminatePenalty = GeodeTerminatePenalty;
+    type GeodeMisconductForAttestor = GeodeMisconductForAttestor;
+    type GeodeMisconductForServiceUser = GeodeMisconductForServiceUser;
+    // without a treasury pallet the rest of a penalty is burnt
+    type Slash = ();
+    type SlotLength = SlotLength;
+    type UnbondingPeriod = UnbondingPeriod;
+    type AttestorBasicRewardRatio = AttestorBasicRewardRatio;
+    type CommissionRateForService = CommissionRate;
+    type CommissionRateForOnDemand = CommissionRate;
+    type AttestorRewardEachSlot = RewardEachSlot;
+    type GeodeRewardEachSlot = RewardEachSlot;
+}
+
+parameter_types! {
+    pub const NotifyFreshnessBlockNumber: BlockNumber = 5;
+    pub const NotifyInterval: BlockNumber = 10;
+    pub const MaxGeodesPerAttestor: u32 = 100;
+    pub const ReputationDecayPeriod: BlockNumber = HOURS;
+    pub const ReputationDecay: Percent = Percent::from_percent(10);
+}
+
+impl pallet_attestor::Config for Runtime {
+    type Event = Event;
+    type Currency = Balances;
+    type Call = Call;
+    type AttestorAccounting = Accounting;
+    type AttestationVerifier = pallet_attestor::attestation::DcapVerifier;
+    type NotifyFreshnessBlockNumber = NotifyFreshnessBlockNumber;
+    type NotifyInterval = NotifyInterval;
+    type MaxGeodesPerAttestor = MaxGeodesPerAttestor;
+    type ReputationDecayPeriod = ReputationDecayPeriod;
+    type ReputationDecay = ReputationDecay;
+    type AttestorAdminOrigin = frame_system::EnsureRoot<AccountId>;
+    type OnAttestorRegistered = ();
+}
+
+parameter_types! {
+    pub const DispatchConfirmationTimeout: BlockNumber = 12;
+    pub const PutOnlineTimeout: BlockNumber = 40;
+    pub const AttestationExpiryBlockNumber: BlockNumber = 30;
+    pub const MaxBatchSize: u32 = 64;
+    pub const MaxHistoryLength: u32 = 32;
+    pub const DnsApprovalRatio: Percent = Percent::from_percent(67);
+    pub const DnsChallengeExpiry: BlockNumber = 600;
+}
+
+impl pallet_geode::Config for Runtime {
+    type Event = Event;
+    type Balance = Balance;
+    type GeodeAccounting = Accounting;
+    type DispatchConfirmationTimeout = DispatchConfirmationTimeout;
+    type PutOnlineTimeout = PutOnlineTimeout;
+    type AttestationExpiryBlockNumber = AttestationExpiryBlockNumber;
+    type MaxBatchSize = MaxBatchSize;
+    type MaxHistoryLength = MaxHistoryLength;
+    type DnsApprovalRatio = DnsApprovalRatio;
+    type DnsChallengeExpiry = DnsChallengeExpiry;
+    type CapabilityAdminOrigin = frame_system::EnsureRoot<AccountId>;
+    type OnGeodeStateChange = ();
+}
+
 // Create the runtime by composing the FRAME pallets that were previously configured.
 construct_runtime!(
     pub enum Runtime where
@@ -362,6 +456,9 @@ construct_runtime!(
         Game: pallet_game::{Pallet, Call, Storage, Event<T>},
         DAOPortal: pallet_daoportal::{Pallet, Call, Storage, Event<T>},
         Gmetadata: pallet_gmetadata::{Pallet, Call, Storage, Event<T>},
+        Attestor: pallet_attestor::{Pallet, Call, Storage, Event<T>, ValidateUnsigned},
+        Geode: pallet_geode::{Pallet, Call, Storage, Event<T>},
+        Accounting: pallet_accounting::{Pallet, Call, Storage, Event<T>},
     }
 );
 
@@ -392,7 +489,9 @@ pub type Executive = frame_executive::Executive<
     AllPallets,
 >;
 
+use pallet_attestor_rpc_runtime_api::AttestorFilter;
 use pallet_daoportal_rpc_runtime_api::{DAOProposal, Project, ProjectId, ProposalId};
+use pallet_geode_rpc_runtime_api::{Geode as GeodeRecord, GeodeState, StateTransition};
 use pallet_gmetadata_rpc_runtime_api::{GmetadataKey, GmetadataQueryResult, HexBytes};
 
 impl_runtime_apis! {
@@ -422,6 +521,58 @@ impl_runtime_apis! {
         }
     }
 
+    impl pallet_attestor_rpc_runtime_api::AttestorRuntimeApi<Block, AccountId> for Runtime {
+        fn attestor_list(filter: AttestorFilter) -> Vec<(Vec<u8>, Vec<u8>, u32)> {
+            Attestor::attestor_list(filter)
+        }
+
+        fn attestors_of_geode(geode: AccountId) -> Vec<(Vec<u8>, Vec<u8>)> {
+            Attestor::attestors_of_geode(geode)
+        }
+
+        fn suggest_attestors(geode: AccountId, count: u32) -> Vec<(AccountId, u32)> {
+            Attestor::suggest_attestors(geode, count)
+        }
+
+        fn attestor_reputation(attestor: AccountId) -> u32 {
+            Attestor::attestor_reputation(attestor)
+        }
+
+        fn attestor_reputations() -> Vec<(AccountId, u32)> {
+            Attestor::attestor_reputations()
+        }
+    }
+
+    impl pallet_geode_rpc_runtime_api::GeodeRuntimeApi<Block, AccountId, Hash> for Runtime {
+        fn registered_geodes() -> Vec<GeodeRecord<AccountId, Hash>> {
+            Geode::registered_geodes()
+        }
+
+        fn attested_geodes() -> Vec<GeodeRecord<AccountId, Hash>> {
+            Geode::attested_geodes()
+        }
+
+        fn attestor_attested_geodes(attestor: AccountId) -> Vec<GeodeRecord<AccountId, Hash>> {
+            Geode::attestor_attested_geodes(attestor)
+        }
+
+        fn attested_geodes_with(requirements: Vec<(Vec<u8>, u64)>) -> Vec<GeodeRecord<AccountId, Hash>> {
+            Geode::attested_geodes_with(requirements)
+        }
+
+        fn geodes_of_provider(provider: AccountId) -> Vec<GeodeRecord<AccountId, Hash>> {
+            Geode::geodes_of_provider(provider)
+        }
+
+        fn geode_state(geode: AccountId) -> Option<GeodeState> {
+            Geode::geode_state(geode)
+        }
+
+        fn geode_history(geode: AccountId) -> Vec<StateTransition> {
+            Geode::geode_history(geode)
+        }
+    }
+
     impl sp_api::Core<Block> for Runtime {
         fn version() -> RuntimeVersion {
             VERSION
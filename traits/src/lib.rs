@@ -1,15 +1,96 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use frame_support::dispatch::DispatchResultWithPostInfo;
+use frame_support::sp_runtime::{traits::Zero, RuntimeDebug};
+use frame_support::sp_std::prelude::*;
+use frame_support::traits::{Currency, Imbalance, OnUnbalanced};
 
 pub trait AttestorAccounting {
     type AccountId;
     fn attestor_staking(who: Self::AccountId) -> DispatchResultWithPostInfo;
+    /// Start unbonding the attestor stake, it stays slashable until withdrawn.
     fn attestor_unreserve(who: Self::AccountId) -> DispatchResultWithPostInfo;
 }
 
+/// Where the stake slashed for a geode misconduct went.
+#[derive(PartialEq, Eq, Clone, RuntimeDebug)]
+pub struct Penalty<AccountId, Balance> {
+    /// Total amount slashed from the provider.
+    pub slashed: Balance,
+    /// Amount paid to each reporting attestor.
+    pub reporters: Vec<(AccountId, Balance)>,
+    /// Amount paid to the owner of the affected service.
+    pub service_owner: Option<(AccountId, Balance)>,
+    /// Amount handed to the treasury.
+    pub treasury: Balance,
+}
+
 pub trait GeodeAccounting {
     type AccountId;
-    fn geode_staking(who: Self::AccountId) -> DispatchResultWithPostInfo;
-    fn geode_unreserve(who: Self::AccountId) -> DispatchResultWithPostInfo;
+    type Balance;
+    /// Minimum stake every geode keeps bonded.
+    fn geode_min_stake() -> Self::Balance;
+    /// Stake a geode loses for a misconduct.
+    fn geode_penalty() -> Self::Balance;
+    /// Bond `value` of the provider's funds as geode stake.
+    fn geode_staking(who: Self::AccountId, value: Self::Balance) -> DispatchResultWithPostInfo;
+    /// Start unbonding `value` of geode stake, it stays slashable until withdrawn.
+    fn geode_unreserve(who: Self::AccountId, value: Self::Balance) -> DispatchResultWithPostInfo;
+    /// Slash up to `value` of geode stake, including stake still unbonding.
+    /// Return the slashed amount.
+    fn geode_slash(who: Self::AccountId, value: Self::Balance) -> Self::Balance;
+    /// Slash up to `value` of geode stake for a misconduct, pay the reporters and the affected
+    /// service owner their shares and hand the rest to the treasury.
+    fn geode_penalize(
+        who: Self::AccountId,
+        value: Self::Balance,
+        reporters: Vec<Self::AccountId>,
+        service_owner: Option<Self::AccountId>,
+    ) -> Penalty<Self::AccountId, Self::Balance>;
+}
+
+/// Split a slashed amount: `reporter_share` evenly among `reporters`, then `owner_share` to
+/// `service_owner`, the rest goes to `Treasury`. Shares are cut short when too little got
+/// slashed, reporters first.
+pub fn distribute_penalty<AccountId, C, Treasury>(
+    slashed: C::NegativeImbalance,
+    reporters: Vec<AccountId>,
+    reporter_share: C::Balance,
+    service_owner: Option<AccountId>,
+    owner_share: C::Balance,
+) -> Penalty<AccountId, C::Balance>
+where
+    C: Currency<AccountId>,
+    Treasury: OnUnbalanced<C::NegativeImbalance>,
+{
+    let mut penalty = Penalty {
+        slashed: slashed.peek(),
+        reporters: Vec::with_capacity(reporters.len()),
+        service_owner: None,
+        treasury: Zero::zero(),
+    };
+    let mut rest = slashed;
+
+    if !reporters.is_empty() {
+        let each = reporter_share / C::Balance::from(reporters.len() as u32);
+        for reporter in reporters.into_iter() {
+            let (reward, remaining) = rest.split(each);
+            rest = remaining;
+            let paid = reward.peek();
+            C::resolve_creating(&reporter, reward);
+            penalty.reporters.push((reporter, paid));
+        }
+    }
+
+    if let Some(owner) = service_owner {
+        let (compensation, remaining) = rest.split(owner_share);
+        rest = remaining;
+        let paid = compensation.peek();
+        C::resolve_creating(&owner, compensation);
+        penalty.service_owner = Some((owner, paid));
+    }
+
+    penalty.treasury = rest.peek();
+    Treasury::on_unbalanced(rest);
+    penalty
 }